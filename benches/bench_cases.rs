@@ -9,6 +9,14 @@ struct BenchCase<'a> {
     pub expected_all_matches: Vec<String>,
 }
 
+// Compiles the patterns of all bench cases as one set through `cache`, a second call is
+// served from the cache entirely
+#[allow(dead_code)]
+fn compile_bench_cases(cache: &mut regex_engine::RegexCache) -> regex_engine::RegexSet {
+    let patterns: Vec<&str> = get_bench_cases().iter().map(|case| case.regex).collect();
+    cache.compile_set(&patterns).expect("Valid regexes")
+}
+
 // This function is used in the `benchmark` files
 #[allow(dead_code)]
 fn get_bench_cases() -> Vec<BenchCase<'static>> {
@@ -109,5 +117,12 @@ fn get_bench_cases() -> Vec<BenchCase<'static>> {
             expected_first_match: Some(format!("{}{}", "a".repeat(1000), "bc")),
             expected_all_matches: vec![format!("{}{}", "a".repeat(1000), "bc")],
         },
+        BenchCase {
+            regex: r"a*",
+            input: "abc".to_string(),
+            expected_is_match: false,
+            expected_first_match: Some("a".to_string()),
+            expected_all_matches: vec!["a".to_string(), "".to_string(), "".to_string()],
+        },
     ])
 }
@@ -1,7 +1,7 @@
 include!("bench_cases.rs");
 use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
 use regex as rust_regex;
-use regex_engine::{ConstructionType, Regex};
+use regex_engine::{ConstructionType, Regex, RegexBuilder, RegexCache};
 
 fn benchmark_regex_compile_time(c: &mut Criterion) {
     let cases = get_bench_cases();
@@ -28,6 +28,26 @@ fn benchmark_regex_compile_time(c: &mut Criterion) {
             },
         );
 
+        group.bench_with_input(
+            BenchmarkId::new("Brzozowski", case.regex),
+            &case.regex,
+            |b, regex| {
+                b.iter(|| {
+                    let _ = Regex::new(regex, ConstructionType::Brzozowski);
+                })
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("Antimirov", case.regex),
+            &case.regex,
+            |b, regex| {
+                b.iter(|| {
+                    let _ = Regex::new(regex, ConstructionType::Antimirov);
+                })
+            },
+        );
+
         group.bench_with_input(
             BenchmarkId::new("Rust", case.regex),
             &case.regex,
@@ -42,6 +62,44 @@ fn benchmark_regex_compile_time(c: &mut Criterion) {
     group.finish();
 }
 
+fn benchmark_regex_compile_set(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Regex Compile Set");
+
+    for (name, construction) in [
+        ("Thompson", ConstructionType::Thompson),
+        ("Glushkov", ConstructionType::Glushkov),
+        ("Brzozowski", ConstructionType::Brzozowski),
+        ("Antimirov", ConstructionType::Antimirov),
+    ] {
+        group.bench_function(BenchmarkId::new("Cold", name), |b| {
+            b.iter(|| compile_bench_cases(&mut RegexCache::new(construction)))
+        });
+
+        let mut warm_cache = RegexCache::new(construction);
+        compile_bench_cases(&mut warm_cache);
+        group.bench_function(BenchmarkId::new("Warm", name), |b| {
+            b.iter(|| compile_bench_cases(&mut warm_cache))
+        });
+    }
+    group.finish();
+}
+
+fn benchmark_regex_compile_repeated_groups(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Regex Compile Repeated Groups");
+    let repeated = "((a|b|c)*(a|b|c)d)".repeat(100);
+    let mut nested = String::from("a");
+    for letter in "bcdefghijklmnopqrstuvwxyz".chars().cycle().take(100) {
+        nested = format!("({nested})*{letter}");
+    }
+
+    for (name, pattern) in [("Repeated", &repeated), ("Nested", &nested)] {
+        group.bench_function(BenchmarkId::new("Glushkov", name), |b| {
+            b.iter(|| Regex::new(pattern, ConstructionType::Glushkov))
+        });
+    }
+    group.finish();
+}
+
 fn benchmark_regex_is_match(c: &mut Criterion) {
     let cases = get_bench_cases();
     let mut group = c.benchmark_group("Regex Is Match");
@@ -51,7 +109,9 @@ fn benchmark_regex_is_match(c: &mut Criterion) {
             Regex::new(case.regex, ConstructionType::Thompson).expect("Valid regex");
         let glushkov_regex =
             Regex::new(case.regex, ConstructionType::Glushkov).expect("Valid regex");
-        let rust_regex = rust_regex::Regex::new(&format!("^{}$", case.regex))
+        // `is_match` is implicitly anchored, so anchor the `regex` crate pattern exactly once
+        let (core_pattern, _, _) = Regex::anchors_stripped_pattern(case.regex);
+        let rust_regex = rust_regex::Regex::new(&format!("^{core_pattern}$"))
             .unwrap_or_else(|_| panic!("Failed to create pattern: {}", case.regex));
 
         group.bench_with_input(
@@ -177,11 +237,278 @@ fn benchmark_regex_find_all(c: &mut Criterion) {
     group.finish();
 }
 
+fn benchmark_regex_find_near_miss(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Regex Find Near Miss");
+    let pattern = r"a*b";
+
+    let thompson_regex = Regex::new(pattern, ConstructionType::Thompson).expect("Valid regex");
+    let glushkov_regex = Regex::new(pattern, ConstructionType::Glushkov).expect("Valid regex");
+    let rust_regex = rust_regex::Regex::new(pattern)
+        .unwrap_or_else(|_| panic!("Failed to create pattern: {pattern}"));
+
+    // Every start position almost matches, so a restarting search would be quadratic
+    for size in [100, 1_000, 10_000] {
+        let input = format!("{}c", "a".repeat(size));
+
+        group.bench_with_input(BenchmarkId::new("Thompson", size), &input, |b, input| {
+            b.iter(|| {
+                thompson_regex.find(input);
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("Glushkov", size), &input, |b, input| {
+            b.iter(|| {
+                glushkov_regex.find(input);
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("Rust", size), &input, |b, input| {
+            b.iter(|| {
+                rust_regex.find(input).map(|m| m.as_str());
+            })
+        });
+    }
+    group.finish();
+}
+
+fn benchmark_regex_find_overlapping_prefixes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Regex Find Overlapping Prefixes");
+    // No required literal, and every start position matches five chars before failing
+    let pattern = r"(a|b)(b|c)(c|a)(a|b)(b|c)(d|e)";
+
+    let thompson_regex = Regex::new(pattern, ConstructionType::Thompson).expect("Valid regex");
+    let glushkov_regex = Regex::new(pattern, ConstructionType::Glushkov).expect("Valid regex");
+    let rust_regex = rust_regex::Regex::new(pattern)
+        .unwrap_or_else(|_| panic!("Failed to create pattern: {pattern}"));
+
+    for size in [1_000, 10_000, 100_000] {
+        let input = format!("{}abd", "abc".repeat(size / 3));
+
+        group.bench_with_input(BenchmarkId::new("Thompson", size), &input, |b, input| {
+            b.iter(|| {
+                thompson_regex.find(input);
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("Glushkov", size), &input, |b, input| {
+            b.iter(|| {
+                glushkov_regex.find(input);
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("Rust", size), &input, |b, input| {
+            b.iter(|| {
+                rust_regex.find(input).map(|m| m.as_str());
+            })
+        });
+    }
+    group.finish();
+}
+
+fn benchmark_regex_find_all_exact(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Regex Find All Exact");
+    let pattern = r"ab";
+    let regex = Regex::new(pattern, ConstructionType::Glushkov).expect("Valid regex");
+
+    // Match heavy inputs where the growing Vec has to reallocate many times
+    for size in [100, 10_000, 100_000] {
+        let input = "ab ".repeat(size);
+
+        group.bench_with_input(BenchmarkId::new("Growing", size), &input, |b, input| {
+            b.iter(|| {
+                regex.findall(input);
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("Exact", size), &input, |b, input| {
+            b.iter(|| {
+                regex.findall_exact(input);
+            })
+        });
+    }
+    group.finish();
+}
+
+fn benchmark_regex_dot_star_literal(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Regex Dot Star Literal");
+    let input = format!("{}needle", "x".repeat(100_000));
+
+    // `(.)*` is not recognised by the rewrite, so it measures the plain DFA search
+    let rewritten_regex = Regex::new(r".*needle", ConstructionType::Glushkov).expect("Valid regex");
+    let dfa_regex = Regex::new(r"(.)*needle", ConstructionType::Glushkov).expect("Valid regex");
+    let rust_regex = rust_regex::Regex::new(r".*needle").expect("Valid regex");
+
+    group.bench_with_input(
+        BenchmarkId::new("Rewritten", ".*needle"),
+        &input,
+        |b, input| {
+            b.iter(|| {
+                rewritten_regex.find(input);
+            })
+        },
+    );
+
+    group.bench_with_input(BenchmarkId::new("Dfa", ".*needle"), &input, |b, input| {
+        b.iter(|| {
+            dfa_regex.find(input);
+        })
+    });
+
+    group.bench_with_input(BenchmarkId::new("Rust", ".*needle"), &input, |b, input| {
+        b.iter(|| {
+            rust_regex.find(input).map(|m| m.as_str());
+        })
+    });
+    group.finish();
+}
+
+fn benchmark_regex_foreign_chars(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Regex Foreign Chars");
+    let foreign_input = "xyz".repeat(50_000);
+    let late_foreign_input = format!("{}z", "ab".repeat(50_000));
+
+    let regex = Regex::new(r"(a|b)*c", ConstructionType::Glushkov).expect("Valid regex");
+    let rust_regex = rust_regex::Regex::new(r"^(a|b)*c$").expect("Valid regex");
+
+    for (name, input) in [
+        ("Foreign", &foreign_input),
+        ("Late Foreign", &late_foreign_input),
+    ] {
+        group.bench_with_input(BenchmarkId::new("Glushkov", name), input, |b, input| {
+            b.iter(|| regex.is_match(input))
+        });
+
+        group.bench_with_input(BenchmarkId::new("Rust", name), input, |b, input| {
+            b.iter(|| rust_regex.is_match(input))
+        });
+    }
+    group.finish();
+}
+
+fn benchmark_regex_required_literal(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Regex Required Literal");
+    let absent_input = "ab ".repeat(50_000);
+    let rare_input = format!("{}ab-foo-b", "ab ".repeat(50_000));
+
+    // Wrapping the literal in an alternation hides it from the prefilter
+    let prefiltered_regex =
+        Regex::new(r"(a|b)*-foo-(a|b)", ConstructionType::Glushkov).expect("Valid regex");
+    let dfa_regex =
+        Regex::new(r"(a|b)*(-foo-|-foo-)(a|b)", ConstructionType::Glushkov).expect("Valid regex");
+    let bounded_regex =
+        Regex::new(r"(a|b)(a|b)-foo-(a|b)", ConstructionType::Glushkov).expect("Valid regex");
+    let rust_regex = rust_regex::Regex::new(r"(a|b)*-foo-(a|b)").expect("Valid regex");
+
+    for (name, input) in [("Absent", &absent_input), ("Rare", &rare_input)] {
+        group.bench_with_input(BenchmarkId::new("Prefiltered", name), input, |b, input| {
+            b.iter(|| {
+                prefiltered_regex.find(input);
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("Dfa", name), input, |b, input| {
+            b.iter(|| {
+                dfa_regex.find(input);
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("Bounded", name), input, |b, input| {
+            b.iter(|| {
+                bounded_regex.find(input);
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("Rust", name), input, |b, input| {
+            b.iter(|| {
+                rust_regex.find(input).map(|m| m.as_str());
+            })
+        });
+    }
+    group.finish();
+}
+
+fn benchmark_regex_dense_table(c: &mut Criterion) {
+    let cases = get_bench_cases();
+    let mut group = c.benchmark_group("Regex Dense Table");
+
+    for case in &cases {
+        // Patterns beyond ASCII get no table, so both regexes use the map for them
+        let dense_regex = Regex::new(case.regex, ConstructionType::Glushkov).expect("Valid regex");
+        let hash_map_regex = RegexBuilder::new(case.regex, ConstructionType::Glushkov)
+            .dense_table(false)
+            .build()
+            .expect("Valid regex");
+        let (core_pattern, _, _) = Regex::anchors_stripped_pattern(case.regex);
+        let anchored_rust_regex = rust_regex::Regex::new(&format!("^{core_pattern}$"))
+            .unwrap_or_else(|_| panic!("Failed to create pattern: {}", case.regex));
+        let rust_regex = rust_regex::Regex::new(case.regex)
+            .unwrap_or_else(|_| panic!("Failed to create pattern: {}", case.regex));
+
+        group.bench_with_input(
+            BenchmarkId::new("Dense Is Match", case.regex),
+            &case.input,
+            |b, input| b.iter(|| dense_regex.is_match(input)),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("HashMap Is Match", case.regex),
+            &case.input,
+            |b, input| b.iter(|| hash_map_regex.is_match(input)),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("Rust Is Match", case.regex),
+            &case.input,
+            |b, input| b.iter(|| anchored_rust_regex.is_match(input)),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("Dense Find", case.regex),
+            &case.input,
+            |b, input| {
+                b.iter(|| {
+                    dense_regex.find(input);
+                })
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("HashMap Find", case.regex),
+            &case.input,
+            |b, input| {
+                b.iter(|| {
+                    hash_map_regex.find(input);
+                })
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("Rust Find", case.regex),
+            &case.input,
+            |b, input| {
+                b.iter(|| {
+                    rust_regex.find(input).map(|m| m.as_str());
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
 criterion_group!(
     benches,
     benchmark_regex_compile_time,
+    benchmark_regex_compile_set,
+    benchmark_regex_compile_repeated_groups,
     benchmark_regex_is_match,
     benchmark_regex_find_first,
-    benchmark_regex_find_all
+    benchmark_regex_find_all,
+    benchmark_regex_find_near_miss,
+    benchmark_regex_find_overlapping_prefixes,
+    benchmark_regex_find_all_exact,
+    benchmark_regex_dot_star_literal,
+    benchmark_regex_required_literal,
+    benchmark_regex_foreign_chars,
+    benchmark_regex_dense_table
 );
 criterion_main!(benches);
@@ -0,0 +1,42 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use regex_engine::{ConstructionType, Regex};
+
+fuzz_target!(|data: &[u8]| {
+    // The pattern and the searched text are separated by the first NUL byte
+    let Some(separator) = data.iter().position(|&b| b == 0) else {
+        return;
+    };
+    let (Ok(regex_str), Ok(text)) = (
+        std::str::from_utf8(&data[..separator]),
+        std::str::from_utf8(&data[separator + 1..]),
+    ) else {
+        return;
+    };
+
+    // Limit input size to avoid timeouts
+    if regex_str.len() > 50 || text.len() > 500 {
+        return;
+    }
+
+    for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+        let Ok(regex) = Regex::new(regex_str, construction) else {
+            continue;
+        };
+
+        // Every match has to be a slice of `text` on char boundaries, in order and without
+        // overlapping the previous match
+        let mut previous_end = 0;
+        for matched in regex.findall(text) {
+            let start = (matched.as_ptr() as usize)
+                .checked_sub(text.as_ptr() as usize)
+                .expect("Match starts inside the text");
+            let end = start + matched.len();
+            assert!(end <= text.len(), "Match ends inside the text");
+            assert!(text.is_char_boundary(start) && text.is_char_boundary(end));
+            assert!(start >= previous_end, "Matches are ordered and disjoint");
+            assert_eq!(&text[start..end], matched);
+            previous_end = end;
+        }
+    }
+});
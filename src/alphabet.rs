@@ -0,0 +1,578 @@
+use crate::RegexError;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+/// A set of characters stored as sorted, non overlapping, inclusive ranges.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct CharClass {
+    ranges: Vec<(char, char)>,
+}
+
+impl CharClass {
+    pub(crate) fn from_char(c: char) -> Self {
+        CharClass {
+            ranges: vec![(c, c)],
+        }
+    }
+
+    /// Builds the class of all characters for which `predicate` holds.
+    fn from_predicate(predicate: fn(char) -> bool) -> Self {
+        let mut ranges: Vec<(char, char)> = Vec::new();
+        for c in (0..=char::MAX as u32).filter_map(char::from_u32) {
+            if !predicate(c) {
+                continue;
+            }
+            match ranges.last_mut() {
+                Some(last) if last.1 as u32 + 1 == c as u32 => last.1 = c,
+                _ => ranges.push((c, c)),
+            }
+        }
+        CharClass { ranges }
+    }
+
+    /// Builds the class of the union of `ranges`, which may overlap and be in any order.
+    fn from_ranges(mut ranges: Vec<(char, char)>) -> Self {
+        ranges.sort_unstable();
+        let mut merged: Vec<(char, char)> = Vec::new();
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start as u32 <= last.1 as u32 + 1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        CharClass { ranges: merged }
+    }
+
+    pub(crate) fn ranges(&self) -> &[(char, char)] {
+        &self.ranges
+    }
+
+    pub(crate) fn contains(&self, c: char) -> bool {
+        self.ranges
+            .binary_search_by(|&(start, end)| {
+                if end < c {
+                    std::cmp::Ordering::Less
+                } else if start > c {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .is_ok()
+    }
+}
+
+/// Looks up a unicode property class such as `L` (letter) or `Lu` by name.
+///
+/// Only a pragmatic subset based on the `char` classification methods is supported, plus an
+/// approximation of the combining marks `M`. The tables are computed once from those
+/// predicates on first use.
+fn unicode_property(name: &str) -> Option<&'static CharClass> {
+    static LETTER: OnceLock<CharClass> = OnceLock::new();
+    static UPPERCASE_LETTER: OnceLock<CharClass> = OnceLock::new();
+    static LOWERCASE_LETTER: OnceLock<CharClass> = OnceLock::new();
+    static NUMBER: OnceLock<CharClass> = OnceLock::new();
+    static MARK: OnceLock<CharClass> = OnceLock::new();
+
+    match name {
+        "L" => Some(LETTER.get_or_init(|| CharClass::from_predicate(char::is_alphabetic))),
+        "Lu" => {
+            Some(UPPERCASE_LETTER.get_or_init(|| CharClass::from_predicate(char::is_uppercase)))
+        }
+        "Ll" => {
+            Some(LOWERCASE_LETTER.get_or_init(|| CharClass::from_predicate(char::is_lowercase)))
+        }
+        "N" => Some(NUMBER.get_or_init(|| CharClass::from_predicate(char::is_numeric))),
+        "M" => Some(MARK.get_or_init(|| CharClass::from_predicate(is_mark))),
+        _ => None,
+    }
+}
+
+/// Approximates the combining marks with the blocks of combining characters, variation
+/// selectors and emoji modifiers, as there is no `char` method for them.
+fn is_mark(c: char) -> bool {
+    matches!(
+        c,
+        '\u{300}'..='\u{36F}'
+            | '\u{483}'..='\u{489}'
+            | '\u{591}'..='\u{5BD}'
+            | '\u{610}'..='\u{61A}'
+            | '\u{64B}'..='\u{65F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{200D}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FE20}'..='\u{FE2F}'
+            | '\u{1F3FB}'..='\u{1F3FF}'
+            | '\u{E0100}'..='\u{E01EF}'
+    )
+}
+
+/// Characters that have to be escaped to be matched literally.
+pub(crate) const META_CHARS: &[char] = &[
+    '\\', '(', ')', '|', '*', '+', '?', '.', '[', ']', '{', '}', '^', '$',
+];
+
+/// Returns the index just after the escape sequence whose escaped character is at `pos`.
+pub(crate) fn escape_end(chars: &[char], pos: usize) -> usize {
+    if matches!(chars.get(pos), Some('p' | 'u')) && chars.get(pos + 1) == Some(&'{') {
+        return match chars[pos..].iter().position(|&c| c == '}') {
+            Some(offset) => pos + offset + 1,
+            None => chars.len(),
+        };
+    }
+    (pos + 1).min(chars.len())
+}
+
+/// Parses the escape sequence whose escaped character is at `pos` into the class it matches.
+///
+/// Returns the class together with the index just after the escape sequence. Apart from
+/// unicode properties and `\u{...}` codepoints every escaped character matches itself,
+/// backreferences are not supported so `\1` is the literal digit `1`.
+pub(crate) fn parse_escape(chars: &[char], pos: usize) -> Result<(CharClass, usize), RegexError> {
+    let Some(&escaped) = chars.get(pos) else {
+        return Err(RegexError::TrailingEscape);
+    };
+
+    let end = escape_end(chars, pos);
+    if escaped == 'p' && end > pos + 1 {
+        if chars[end - 1] != '}' {
+            return Err(RegexError::UnterminatedProperty);
+        }
+        let name: String = chars[pos + 2..end - 1].iter().collect();
+        let class = unicode_property(&name).ok_or(RegexError::UnknownProperty(name))?;
+        return Ok((class.clone(), end));
+    }
+
+    if escaped == 'u' && end > pos + 1 {
+        if chars[end - 1] != '}' {
+            return Err(RegexError::UnterminatedCodepoint);
+        }
+        let hex: String = chars[pos + 2..end - 1].iter().collect();
+        // Surrogates and values above `U+10FFFF` are not chars
+        let codepoint = (1..=6)
+            .contains(&hex.len())
+            .then(|| u32::from_str_radix(&hex, 16).ok())
+            .flatten()
+            .and_then(char::from_u32)
+            .ok_or(RegexError::InvalidCodepoint(hex))?;
+        return Ok((CharClass::from_char(codepoint), end));
+    }
+
+    Ok((CharClass::from_char(escaped), end))
+}
+
+/// Returns the index just after the bracket class `[...]` whose `[` is right before `pos`.
+///
+/// A class runs up to the next unescaped `]` and needs at least one member, so a `]` right
+/// after the `[` is a literal member. There is no end for an unclosed `[`, including `[]`.
+pub(crate) fn class_end(chars: &[char], pos: usize) -> Option<usize> {
+    let mut end = pos;
+    loop {
+        match chars.get(end)? {
+            ']' if end > pos => return Some(end + 1),
+            '\\' => end = escape_end(chars, end + 1),
+            _ => end += 1,
+        }
+    }
+}
+
+/// Splits the inside of a bracket class into its members, single characters and escapes.
+fn class_members(class: &[char]) -> Vec<&[char]> {
+    let mut members = Vec::new();
+    let mut pos = 0;
+    while pos < class.len() {
+        let end = match class[pos] {
+            '\\' => escape_end(class, pos + 1),
+            _ => pos + 1,
+        };
+        members.push(&class[pos..end]);
+        pos = end;
+    }
+    members
+}
+
+/// A member of a bracket class.
+pub(crate) enum ClassItem<'a> {
+    /// A single character or an escape as written in the pattern.
+    Member(&'a [char]),
+    /// An inclusive range of characters such as `a-z`.
+    Range(char, char),
+}
+
+/// Parses the inside of a bracket class into its members and ranges.
+///
+/// A `-` between two members forms a range, at the start or the end of the class it is a
+/// literal `-`. Both ends of a range have to be single characters and the range must not be
+/// reversed.
+pub(crate) fn class_items(class: &[char]) -> Result<Vec<ClassItem<'_>>, RegexError> {
+    let members = class_members(class);
+    let mut items = Vec::new();
+    let mut i = 0;
+    while i < members.len() {
+        if i + 2 < members.len() && members[i + 1] == ['-'] {
+            let range_end = |member: &[char]| {
+                class_char(member)
+                    .ok_or_else(|| RegexError::InvalidRangeEnd(member.iter().collect()))
+            };
+            let start = range_end(members[i])?;
+            let end = range_end(members[i + 2])?;
+            if start > end {
+                return Err(RegexError::ReversedRange { start, end });
+            }
+            items.push(ClassItem::Range(start, end));
+            i += 3;
+        } else {
+            items.push(ClassItem::Member(members[i]));
+            i += 1;
+        }
+    }
+    Ok(items)
+}
+
+/// Returns the single character a class member matches, if it matches exactly one.
+fn class_char(member: &[char]) -> Option<char> {
+    match member {
+        [c] => Some(*c),
+        ['\\', ..] => match parse_escape(member, 1).ok()?.0.ranges() {
+            [(start, end)] if start == end => Some(*start),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Parses the bracket class whose `[` is right before `pos` into the class it matches.
+///
+/// Returns the class together with the index just after the closing `]`. Ranges are kept as
+/// ranges instead of being enumerated, so even a class spanning all of unicode stays cheap.
+pub(crate) fn parse_class(chars: &[char], pos: usize) -> Result<(CharClass, usize), RegexError> {
+    let end = class_end(chars, pos).ok_or(RegexError::UnclosedClass)?;
+    let mut ranges = Vec::new();
+    for item in class_items(&chars[pos..end - 1])? {
+        match item {
+            ClassItem::Member(member @ ['\\', ..]) => {
+                ranges.extend(parse_escape(member, 1)?.0.ranges());
+            }
+            ClassItem::Member(member) => ranges.push((member[0], member[0])),
+            ClassItem::Range(start, end) => ranges.push((start, end)),
+        }
+    }
+    Ok((CharClass::from_ranges(ranges), end))
+}
+
+/// Collects every class a normalised pattern can consume.
+///
+/// Plain characters are treated as single character classes. Operators are included as well,
+/// which only refines the partition and never changes the matched language.
+pub(crate) fn pattern_classes(normalised_regex: &str) -> Result<Vec<CharClass>, RegexError> {
+    let chars: Vec<char> = normalised_regex.chars().collect();
+    let mut classes = Vec::new();
+
+    let mut pos = 0;
+    while pos < chars.len() {
+        if chars[pos] == '\\' {
+            let (class, end) = parse_escape(&chars, pos + 1)?;
+            classes.push(class);
+            pos = end;
+        } else if chars[pos] == '[' {
+            let (class, end) = parse_class(&chars, pos + 1)?;
+            classes.push(class);
+            pos = end;
+        } else {
+            classes.push(CharClass::from_char(chars[pos]));
+            pos += 1;
+        }
+    }
+
+    Ok(classes)
+}
+
+/// Partition of the characters used by a pattern into disjoint cells.
+///
+/// Every cell is either entirely inside or entirely outside each class of the pattern, so the
+/// DFA only needs one transition per cell. The transitions are keyed on the first character
+/// of the cell, its representative. Patterns made of plain characters only produce single
+/// character cells, in which case every character is its own representative.
+///
+/// An optional char map is applied to every input character before it is looked up, the
+/// pattern has to be mapped the same way when it is compiled.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Alphabet {
+    cells: Vec<(char, char)>,
+    has_ranges: bool,
+    char_map: Option<fn(char) -> char>,
+}
+
+impl Alphabet {
+    pub(crate) fn new(classes: &[CharClass]) -> Self {
+        // Sweep over the range boundaries counting how many ranges cover each interval
+        let mut boundaries: BTreeMap<u32, i32> = BTreeMap::new();
+        for class in classes {
+            for &(start, end) in class.ranges() {
+                *boundaries.entry(start as u32).or_default() += 1;
+                *boundaries.entry(end as u32 + 1).or_default() -= 1;
+            }
+        }
+
+        let mut cells = Vec::new();
+        let mut coverage = 0;
+        let mut boundary_iter = boundaries.into_iter().peekable();
+        while let Some((start, delta)) = boundary_iter.next() {
+            coverage += delta;
+            let Some(&(next_start, _)) = boundary_iter.peek() else {
+                break;
+            };
+            if coverage == 0 {
+                continue;
+            }
+
+            // Surrogates are not valid chars, so cells never start or end inside them
+            let start = char::from_u32(start).unwrap_or('\u{E000}');
+            let end = char::from_u32(next_start - 1).unwrap_or('\u{D7FF}');
+            if start <= end {
+                cells.push((start, end));
+            }
+        }
+
+        let has_ranges = cells.iter().any(|(start, end)| start != end);
+        Alphabet {
+            cells,
+            has_ranges,
+            char_map: None,
+        }
+    }
+
+    /// Returns the partition refined so that every char in `chars` gets a cell of its own.
+    pub(crate) fn with_chars(&self, chars: impl IntoIterator<Item = char>) -> Self {
+        let classes: Vec<CharClass> = self
+            .cells
+            .iter()
+            .map(|&cell| CharClass { ranges: vec![cell] })
+            .chain(chars.into_iter().map(CharClass::from_char))
+            .collect();
+        Alphabet {
+            char_map: self.char_map,
+            ..Alphabet::new(&classes)
+        }
+    }
+
+    /// Returns the coarsest partition that refines each of `alphabets`.
+    pub(crate) fn refinement(alphabets: &[&Alphabet]) -> Self {
+        let classes: Vec<CharClass> = alphabets
+            .iter()
+            .flat_map(|alphabet| &alphabet.cells)
+            .map(|&cell| CharClass { ranges: vec![cell] })
+            .collect();
+        Alphabet::new(&classes)
+    }
+
+    /// Rebuilds an alphabet from its sorted, disjoint cells as returned by `cells`.
+    pub(crate) fn from_cells(cells: &[(char, char)]) -> Self {
+        Alphabet {
+            cells: cells.to_vec(),
+            has_ranges: cells.iter().any(|(start, end)| start != end),
+            char_map: None,
+        }
+    }
+
+    pub(crate) fn cells(&self) -> &[(char, char)] {
+        &self.cells
+    }
+
+    pub(crate) fn set_char_map(&mut self, char_map: fn(char) -> char) {
+        self.char_map = Some(char_map);
+    }
+
+    pub(crate) fn has_char_map(&self) -> bool {
+        self.char_map.is_some()
+    }
+
+    /// Applies the char map, if there is one.
+    pub(crate) fn map_char(&self, c: char) -> char {
+        self.char_map.map_or(c, |char_map| char_map(c))
+    }
+
+    /// Returns the representatives of all cells.
+    pub(crate) fn cell_representatives(&self) -> impl Iterator<Item = char> + '_ {
+        self.cells.iter().map(|&(start, _)| start)
+    }
+
+    /// Maps an input character to the representative of its cell.
+    ///
+    /// Characters outside of every cell are returned unchanged, they have no transitions.
+    pub(crate) fn representative(&self, c: char) -> char {
+        let c = self.map_char(c);
+        if !self.has_ranges {
+            return c;
+        }
+
+        let index = self.cells.partition_point(|&(start, _)| start <= c);
+        match index.checked_sub(1).map(|i| self.cells[i]) {
+            Some((start, end)) if c <= end => start,
+            _ => c,
+        }
+    }
+
+    /// Returns the cell whose representative is `representative`.
+    pub(crate) fn cell(&self, representative: char) -> (char, char) {
+        if !self.has_ranges {
+            return (representative, representative);
+        }
+
+        match self
+            .cells
+            .binary_search_by_key(&representative, |&(start, _)| start)
+        {
+            Ok(index) => self.cells[index],
+            Err(_) => (representative, representative),
+        }
+    }
+
+    /// Returns the representatives of all cells that make up `class`.
+    pub(crate) fn representatives(&self, class: &CharClass) -> Vec<char> {
+        if !self.has_ranges {
+            return class
+                .ranges()
+                .iter()
+                .flat_map(|&(start, end)| start..=end)
+                .collect();
+        }
+
+        let mut representatives = Vec::new();
+        for &(start, end) in class.ranges() {
+            let first_cell = self
+                .cells
+                .partition_point(|&(cell_start, _)| cell_start < start);
+            for &(cell_start, _) in self.cells[first_cell..]
+                .iter()
+                .take_while(|&&(cell_start, _)| cell_start <= end)
+            {
+                representatives.push(cell_start);
+            }
+        }
+        representatives
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alphabet_partition_test() {
+        let alphabet = Alphabet::new(&[
+            CharClass {
+                ranges: vec![('a', 'z')],
+            },
+            CharClass::from_char('m'),
+            CharClass::from_char('1'),
+        ]);
+
+        assert_eq!(
+            alphabet.cells,
+            vec![('1', '1'), ('a', 'l'), ('m', 'm'), ('n', 'z')]
+        );
+        assert_eq!(alphabet.representative('c'), 'a');
+        assert_eq!(alphabet.representative('m'), 'm');
+        assert_eq!(alphabet.representative('q'), 'n');
+        assert_eq!(alphabet.representative('1'), '1');
+        assert_eq!(alphabet.representative('#'), '#');
+        assert_eq!(
+            alphabet.representatives(&CharClass {
+                ranges: vec![('a', 'z')],
+            }),
+            vec!['a', 'm', 'n']
+        );
+    }
+
+    #[test]
+    fn with_chars_test() {
+        let alphabet = Alphabet::new(&[CharClass {
+            ranges: vec![('a', 'z')],
+        }])
+        .with_chars(['m', '1']);
+
+        assert_eq!(
+            alphabet.cells,
+            vec![('1', '1'), ('a', 'l'), ('m', 'm'), ('n', 'z')]
+        );
+        assert_eq!(
+            alphabet.cell_representatives().collect::<Vec<char>>(),
+            vec!['1', 'a', 'm', 'n']
+        );
+    }
+
+    #[test]
+    fn refinement_test() {
+        let letters = Alphabet::new(&[CharClass {
+            ranges: vec![('a', 'z')],
+        }]);
+        let middle = Alphabet::new(&[CharClass {
+            ranges: vec![('m', 'p')],
+        }])
+        .with_chars(['1']);
+
+        let alphabet = Alphabet::refinement(&[&letters, &middle]);
+        assert_eq!(
+            alphabet.cells,
+            vec![('1', '1'), ('a', 'l'), ('m', 'p'), ('q', 'z')]
+        );
+    }
+
+    #[test]
+    fn parse_escape_test() {
+        let chars: Vec<char> = r"\p{L}x\p{Foo}\p{N".chars().collect();
+
+        let (class, end) = parse_escape(&chars, 1).expect("Known property");
+        assert!(class.ranges().iter().any(|&(s, e)| s <= 'é' && 'é' <= e));
+        assert_eq!(end, 5);
+
+        assert!(parse_escape(&chars, 7).is_err());
+        assert!(parse_escape(&chars, 14).is_err());
+    }
+
+    #[test]
+    fn unicode_escape_test() {
+        let chars: Vec<char> = r"\u{1F600}\u{D800}\u{110000}\u{}\u{41".chars().collect();
+
+        let (class, end) = parse_escape(&chars, 1).expect("Valid codepoint");
+        assert_eq!(class, CharClass::from_char('😀'));
+        assert_eq!(end, 9);
+
+        assert!(parse_escape(&chars, 10).is_err());
+        assert!(parse_escape(&chars, 18).is_err());
+        assert!(parse_escape(&chars, 28).is_err());
+        assert!(parse_escape(&chars, 32).is_err());
+    }
+
+    #[test]
+    fn parse_class_test() {
+        let cases = [
+            ("[a-cx]", vec![('a', 'c'), ('x', 'x')]),
+            ("[-a]", vec![('-', '-'), ('a', 'a')]),
+            ("[a-]", vec![('-', '-'), ('a', 'a')]),
+            ("[(-+]", vec![('(', '+')]),
+            (r"[a\-c]", vec![('-', '-'), ('a', 'a'), ('c', 'c')]),
+            ("[b-dac]", vec![('a', 'd')]),
+            (r"[\u{0}-\u{10FFFF}]", vec![('\u{0}', '\u{10FFFF}')]),
+            (
+                r"[a\]\-z]",
+                vec![('-', '-'), (']', ']'), ('a', 'a'), ('z', 'z')],
+            ),
+            ("[]a]", vec![(']', ']'), ('a', 'a')]),
+            ("[]-a]", vec![(']', 'a')]),
+            ("[a^]", vec![('^', '^'), ('a', 'a')]),
+        ];
+        for (pattern, ranges) in cases {
+            let chars: Vec<char> = pattern.chars().collect();
+            let (class, end) = parse_class(&chars, 1).expect("Valid class");
+            assert_eq!(class.ranges(), ranges, "Failed for class: {pattern}");
+            assert_eq!(end, chars.len());
+        }
+
+        let chars: Vec<char> = "[z-a]".chars().collect();
+        assert!(parse_class(&chars, 1).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use crate::alphabet::{Alphabet, pattern_classes};
+use crate::brzozowski::{Expr, concat};
+use crate::glushkov::{Nfa, nfa_to_dfa, parse_regex};
+use crate::{Dfa, RegexError, normalise_regex, validate_regex};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::rc::Rc;
+
+/// DFA determinised from the NFA of the Antimirov partial derivatives of the regex.
+///
+/// A partial derivative splits the Brzozowski derivative at its alternations into a set of
+/// terms, and every term is one NFA state. There are at most as many terms as the pattern has
+/// characters and classes plus one, so alternation heavy patterns get far fewer NFA states
+/// than with Thompson. The NFA has no epsilon transitions and is determinised by the same
+/// subset construction as Glushkov. The pattern is parsed like for Glushkov, so the same
+/// patterns are accepted.
+#[derive(Debug, Clone)]
+pub struct AntimirovDfa {
+    transitions: HashMap<(u32, char), u32>,
+    accepting_states: HashSet<u32>,
+    alphabet: Alphabet,
+}
+
+impl Dfa for AntimirovDfa {
+    fn new(regex: &str, size_limit: Option<usize>) -> Result<Self, RegexError> {
+        validate_regex(regex)?;
+
+        let normalised_regex = normalise_regex(regex);
+        let alphabet = Alphabet::new(&pattern_classes(&normalised_regex)?);
+        let expr = Expr::from_ast(&parse_regex(&normalised_regex)?);
+        let nfa = partial_derivatives_nfa(expr, &alphabet);
+        let mut regex_dfa: AntimirovDfa = nfa_to_dfa(nfa, size_limit)?;
+        regex_dfa.alphabet = alphabet;
+
+        <Self as Dfa>::optimise_dfa(&mut regex_dfa);
+        Ok(regex_dfa)
+    }
+
+    fn from_parts(
+        transitions: HashMap<(u32, char), u32>,
+        accepting_states: HashSet<u32>,
+        alphabet: Alphabet,
+    ) -> Self {
+        AntimirovDfa {
+            transitions,
+            accepting_states,
+            alphabet,
+        }
+    }
+
+    fn get_transitions(&self) -> &HashMap<(u32, char), u32> {
+        &self.transitions
+    }
+
+    fn get_accepting_states(&self) -> &HashSet<u32> {
+        &self.accepting_states
+    }
+
+    fn get_transitions_mut(&mut self) -> &mut HashMap<(u32, char), u32> {
+        &mut self.transitions
+    }
+
+    fn get_accepting_states_mut(&mut self) -> &mut HashSet<u32> {
+        &mut self.accepting_states
+    }
+
+    fn get_alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    fn get_alphabet_mut(&mut self) -> &mut Alphabet {
+        &mut self.alphabet
+    }
+}
+
+/// Returns the terms of the partial derivative of `expr` with respect to the alphabet cell of
+/// `representative`, whose union is the Brzozowski derivative.
+fn partial_derivatives(expr: &Rc<Expr>, representative: char) -> BTreeSet<Rc<Expr>> {
+    match &**expr {
+        Expr::Empty | Expr::Epsilon => BTreeSet::new(),
+        // The cells refine every class, so the representative stands for its whole cell
+        Expr::Class(class) if class.contains(representative) => {
+            BTreeSet::from([Rc::new(Expr::Epsilon)])
+        }
+        Expr::Class(_) => BTreeSet::new(),
+        Expr::Concat(first, rest) => {
+            let mut terms: BTreeSet<Rc<Expr>> = partial_derivatives(first, representative)
+                .into_iter()
+                .map(|term| concat(term, rest.clone()))
+                .collect();
+            if first.nullable() {
+                terms.extend(partial_derivatives(rest, representative));
+            }
+            terms
+        }
+        Expr::Alternation(alternatives) => alternatives
+            .iter()
+            .flat_map(|alternative| partial_derivatives(alternative, representative))
+            .collect(),
+        Expr::KleeneStar(inner) => partial_derivatives(inner, representative)
+            .into_iter()
+            .map(|term| concat(term, expr.clone()))
+            .collect(),
+    }
+}
+
+/// Builds the NFA whose states are `expr` and its partial derivative terms, numbered in the
+/// order they are found so the start state is 0.
+fn partial_derivatives_nfa(expr: Rc<Expr>, alphabet: &Alphabet) -> Nfa {
+    let representatives: Vec<char> = alphabet.cell_representatives().collect();
+    let mut transitions = HashMap::new();
+    let mut accepting_states = HashSet::new();
+    let mut states: HashMap<Rc<Expr>, u32> = HashMap::from([(expr.clone(), 0)]);
+    let mut queue = VecDeque::from([expr]);
+
+    while let Some(current) = queue.pop_front() {
+        let current_state = states[&current];
+        if current.nullable() {
+            accepting_states.insert(current_state);
+        }
+
+        for &representative in &representatives {
+            let targets: Vec<u32> = partial_derivatives(&current, representative)
+                .into_iter()
+                .map(|term| {
+                    let next_state = states.len() as u32;
+                    *states.entry(term.clone()).or_insert_with(|| {
+                        queue.push_back(term);
+                        next_state
+                    })
+                })
+                .collect();
+            if !targets.is_empty() {
+                transitions.insert((current_state, representative), targets);
+            }
+        }
+    }
+
+    Nfa {
+        start_state: 0,
+        transitions,
+        accepting_states,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_nfa(regex: &str) -> Nfa {
+        let normalised_regex = normalise_regex(regex);
+        let alphabet = Alphabet::new(&pattern_classes(&normalised_regex).expect("Valid classes"));
+        let expr = Expr::from_ast(&parse_regex(&normalised_regex).expect("Valid regex"));
+        partial_derivatives_nfa(expr, &alphabet)
+    }
+
+    #[test]
+    fn partial_derivatives_nfa_test() {
+        let nfa = build_nfa("(a|b)*abb");
+        let states: HashSet<u32> = nfa
+            .transitions
+            .iter()
+            .flat_map(|(&(source_state, _), targets)| {
+                std::iter::once(source_state).chain(targets.iter().copied())
+            })
+            .collect();
+        // The terms (a|b)*abb, bb, b and the empty string
+        assert_eq!(states.len(), 4);
+        assert_eq!(nfa.transitions[&(0, 'a')].len(), 2);
+        assert_eq!(nfa.accepting_states.len(), 1);
+
+        // An alternation of words shares no state between them but needs no epsilons either
+        let nfa = build_nfa("abc|abd|abe");
+        assert_eq!(nfa.transitions[&(0, 'a')].len(), 3);
+        assert!(!nfa.accepting_states.contains(&0));
+    }
+
+    #[test]
+    fn antimirov_dfa_test() {
+        let dfa = AntimirovDfa::new("(a|b)*abb", None).expect("Valid regex");
+        assert!(dfa.process("babb"));
+        assert!(!dfa.process("abba"));
+        assert!(AntimirovDfa::new("a**", None).is_err());
+    }
+}
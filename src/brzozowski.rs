@@ -0,0 +1,259 @@
+use crate::alphabet::{Alphabet, CharClass, pattern_classes};
+use crate::glushkov::{RegexAst, parse_regex};
+use crate::{Dfa, RegexError, check_size_limit, normalise_regex, validate_regex};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::rc::Rc;
+
+/// Regular expression the derivatives are taken of.
+///
+/// Expressions are only built through the constructors below, which keep them in a normal
+/// form: concatenations are nested to the right, alternations are flattened sets without the
+/// empty language, and the trivial cases involving `Empty` and `Epsilon` are simplified away.
+/// Two derivatives that only differ by these identities therefore compare equal, which is
+/// what makes the set of derivatives of an expression finite.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum Expr {
+    /// Matches nothing
+    Empty,
+    /// Matches only the empty string
+    Epsilon,
+    Class(CharClass),
+    Concat(Rc<Expr>, Rc<Expr>),
+    Alternation(BTreeSet<Rc<Expr>>),
+    KleeneStar(Rc<Expr>),
+}
+
+impl Expr {
+    pub(crate) fn from_ast(ast: &RegexAst) -> Rc<Expr> {
+        match ast {
+            RegexAst::Char(c) => Rc::new(Expr::Class(CharClass::from_char(*c))),
+            RegexAst::Class(class) => Rc::new(Expr::Class(class.clone())),
+            RegexAst::Concat(elements) => elements
+                .iter()
+                .rev()
+                .fold(Rc::new(Expr::Epsilon), |rest, element| {
+                    concat(Expr::from_ast(element), rest)
+                }),
+            RegexAst::Alternation(alternatives) => {
+                alternation(alternatives.iter().map(Expr::from_ast))
+            }
+            RegexAst::KleeneStar(inner) => kleene_star(Expr::from_ast(inner)),
+            RegexAst::Group(_, inner) => Expr::from_ast(inner),
+        }
+    }
+
+    pub(crate) fn nullable(&self) -> bool {
+        match self {
+            Expr::Empty | Expr::Class(_) => false,
+            Expr::Epsilon | Expr::KleeneStar(_) => true,
+            Expr::Concat(first, rest) => first.nullable() && rest.nullable(),
+            Expr::Alternation(alternatives) => alternatives.iter().any(|alt| alt.nullable()),
+        }
+    }
+
+    /// Returns the expression matching the rest of every string of `self` starting with a
+    /// char of the alphabet cell of `representative`.
+    fn derivative(self: &Rc<Self>, representative: char) -> Rc<Expr> {
+        match &**self {
+            Expr::Empty | Expr::Epsilon => Rc::new(Expr::Empty),
+            // The cells refine every class, so the representative stands for its whole cell
+            Expr::Class(class) if class.contains(representative) => Rc::new(Expr::Epsilon),
+            Expr::Class(_) => Rc::new(Expr::Empty),
+            Expr::Concat(first, rest) => {
+                let derived = concat(first.derivative(representative), rest.clone());
+                if first.nullable() {
+                    alternation([derived, rest.derivative(representative)])
+                } else {
+                    derived
+                }
+            }
+            Expr::Alternation(alternatives) => alternation(
+                alternatives
+                    .iter()
+                    .map(|alternative| alternative.derivative(representative)),
+            ),
+            Expr::KleeneStar(inner) => concat(inner.derivative(representative), self.clone()),
+        }
+    }
+}
+
+pub(crate) fn concat(first: Rc<Expr>, rest: Rc<Expr>) -> Rc<Expr> {
+    match (&*first, &*rest) {
+        (Expr::Empty, _) | (_, Expr::Empty) => Rc::new(Expr::Empty),
+        (Expr::Epsilon, _) => rest,
+        (_, Expr::Epsilon) => first,
+        (Expr::Concat(inner_first, inner_rest), _) => {
+            concat(inner_first.clone(), concat(inner_rest.clone(), rest))
+        }
+        _ => Rc::new(Expr::Concat(first, rest)),
+    }
+}
+
+fn alternation(alternatives: impl IntoIterator<Item = Rc<Expr>>) -> Rc<Expr> {
+    let mut flattened = BTreeSet::new();
+    for alternative in alternatives {
+        match &*alternative {
+            Expr::Empty => {}
+            Expr::Alternation(inner) => flattened.extend(inner.iter().cloned()),
+            _ => {
+                flattened.insert(alternative);
+            }
+        }
+    }
+    match flattened.len() {
+        0 => Rc::new(Expr::Empty),
+        1 => flattened.pop_first().expect("One alternative"),
+        _ => Rc::new(Expr::Alternation(flattened)),
+    }
+}
+
+fn kleene_star(inner: Rc<Expr>) -> Rc<Expr> {
+    match &*inner {
+        Expr::Empty | Expr::Epsilon => Rc::new(Expr::Epsilon),
+        Expr::KleeneStar(_) => inner,
+        _ => Rc::new(Expr::KleeneStar(inner)),
+    }
+}
+
+/// DFA built directly from the regex with Brzozowski derivatives, without an NFA in between.
+///
+/// Every state is a derivative of the pattern, the start state is the pattern itself and the
+/// transition for a cell of the alphabet leads to the derivative with respect to it. A state
+/// is accepting if its expression matches the empty string. The pattern is parsed like for
+/// Glushkov, so the same patterns are accepted.
+#[derive(Debug, Clone)]
+pub struct BrzozowskiDfa {
+    transitions: HashMap<(u32, char), u32>,
+    accepting_states: HashSet<u32>,
+    alphabet: Alphabet,
+}
+
+impl Dfa for BrzozowskiDfa {
+    fn new(regex: &str, size_limit: Option<usize>) -> Result<Self, RegexError> {
+        validate_regex(regex)?;
+
+        let normalised_regex = normalise_regex(regex);
+        let alphabet = Alphabet::new(&pattern_classes(&normalised_regex)?);
+        let expr = Expr::from_ast(&parse_regex(&normalised_regex)?);
+        let mut regex_dfa = derivatives_to_dfa(expr, alphabet, size_limit)?;
+
+        <Self as Dfa>::optimise_dfa(&mut regex_dfa);
+        Ok(regex_dfa)
+    }
+
+    fn from_parts(
+        transitions: HashMap<(u32, char), u32>,
+        accepting_states: HashSet<u32>,
+        alphabet: Alphabet,
+    ) -> Self {
+        BrzozowskiDfa {
+            transitions,
+            accepting_states,
+            alphabet,
+        }
+    }
+
+    fn get_transitions(&self) -> &HashMap<(u32, char), u32> {
+        &self.transitions
+    }
+
+    fn get_accepting_states(&self) -> &HashSet<u32> {
+        &self.accepting_states
+    }
+
+    fn get_transitions_mut(&mut self) -> &mut HashMap<(u32, char), u32> {
+        &mut self.transitions
+    }
+
+    fn get_accepting_states_mut(&mut self) -> &mut HashSet<u32> {
+        &mut self.accepting_states
+    }
+
+    fn get_alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    fn get_alphabet_mut(&mut self) -> &mut Alphabet {
+        &mut self.alphabet
+    }
+}
+
+/// Explores the derivatives of `expr` breadth first, numbering them in the order they are
+/// found so the start state is 0. The empty language is left out and becomes the implicit
+/// dead state.
+fn derivatives_to_dfa(
+    expr: Rc<Expr>,
+    alphabet: Alphabet,
+    size_limit: Option<usize>,
+) -> Result<BrzozowskiDfa, RegexError> {
+    let representatives: Vec<char> = alphabet.cell_representatives().collect();
+    let mut transitions = HashMap::new();
+    let mut accepting_states = HashSet::new();
+    let mut states: HashMap<Rc<Expr>, u32> = HashMap::from([(expr.clone(), 0)]);
+    let mut queue = VecDeque::from([expr]);
+
+    while let Some(current) = queue.pop_front() {
+        let current_state = states[&current];
+        if current.nullable() {
+            accepting_states.insert(current_state);
+        }
+
+        for &representative in &representatives {
+            let derived = current.derivative(representative);
+            if *derived == Expr::Empty {
+                continue;
+            }
+            let next_state = match states.get(&derived) {
+                Some(&state) => state,
+                None => {
+                    let state = states.len() as u32;
+                    states.insert(derived.clone(), state);
+                    queue.push_back(derived);
+                    state
+                }
+            };
+            transitions.insert((current_state, representative), next_state);
+            check_size_limit(transitions.len(), states.len(), size_limit)?;
+        }
+    }
+
+    Ok(BrzozowskiDfa {
+        transitions,
+        accepting_states,
+        alphabet,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expr(regex: &str) -> Rc<Expr> {
+        Expr::from_ast(&parse_regex(&normalise_regex(regex)).expect("Valid regex"))
+    }
+
+    #[test]
+    fn similar_derivatives_test() {
+        // The identities of the constructors make these pairs the same expression
+        assert_eq!(expr("(a|b)|a"), expr("b|a"));
+        assert_eq!(expr("(ab)c"), expr("a(bc)"));
+        assert_eq!(expr("(a*)*"), expr("a*"));
+        assert_eq!(expr("a*").derivative('a'), expr("a*"));
+        assert_eq!(expr("ab|ac").derivative('a'), expr("c|b"));
+        assert_eq!(*expr("ab").derivative('b'), Expr::Empty);
+    }
+
+    #[test]
+    fn derivatives_to_dfa_test() {
+        let dfa = BrzozowskiDfa::new("(a|b)*abb", None).expect("Valid regex");
+        // The minimal DFA of the textbook example
+        let states: HashSet<u32> = dfa
+            .get_transitions()
+            .iter()
+            .flat_map(|(&(source_state, _), &target_state)| [source_state, target_state])
+            .collect();
+        assert_eq!(states.len(), 4);
+        assert!(dfa.process("babb"));
+        assert!(!dfa.process("abba"));
+    }
+}
@@ -0,0 +1,184 @@
+use crate::RegexError;
+use crate::alphabet::{Alphabet, CharClass};
+use crate::glushkov::{RegexAst, parse_capture_regex};
+
+#[derive(Debug, Clone)]
+enum Instruction {
+    /// Consumes a char of the class
+    Class(CharClass),
+    /// Continues at both targets, preferring the first
+    Split(usize, usize),
+    Jump(usize),
+    /// Records the current offset in a slot, the start and end of group `n` are slots `2n`
+    /// and `2n + 1`
+    Save(usize),
+    Match,
+}
+
+/// Program of the tagged simulation that reports where the capture groups matched.
+///
+/// The AST of the pattern is compiled into a Pike VM program whose `Save` instructions record
+/// the offsets a group starts and ends at. The DFA already found the span of the match, so
+/// the program only runs anchored over that span. Of all threads consuming the whole span the
+/// one preferring earlier alternatives and longer repetitions wins, so a group inside a
+/// repetition reports its last iteration.
+#[derive(Debug, Clone)]
+pub(crate) struct CaptureProgram {
+    instructions: Vec<Instruction>,
+    slot_count: usize,
+}
+
+impl CaptureProgram {
+    /// Compiles the program for `pattern`, see `parse_capture_regex` for the expected pattern.
+    pub(crate) fn new(pattern: &str, dot_pattern: &str) -> Result<Self, RegexError> {
+        let ast = parse_capture_regex(pattern, dot_pattern)?;
+        let mut program = CaptureProgram {
+            instructions: vec![Instruction::Save(0)],
+            slot_count: 2,
+        };
+        program.compile(&ast);
+        program.instructions.push(Instruction::Save(1));
+        program.instructions.push(Instruction::Match);
+        Ok(program)
+    }
+
+    fn compile(&mut self, ast: &RegexAst) {
+        match ast {
+            RegexAst::Char(c) => self
+                .instructions
+                .push(Instruction::Class(CharClass::from_char(*c))),
+            RegexAst::Class(class) => self.instructions.push(Instruction::Class(class.clone())),
+            RegexAst::Concat(elements) => {
+                for element in elements {
+                    self.compile(element);
+                }
+            }
+            RegexAst::Alternation(alternatives) => {
+                let mut jumps = Vec::new();
+                let (last, rest) = alternatives.split_last().expect("At least one alternative");
+                for alternative in rest {
+                    let split = self.instructions.len();
+                    self.instructions.push(Instruction::Split(split + 1, 0));
+                    self.compile(alternative);
+                    jumps.push(self.instructions.len());
+                    self.instructions.push(Instruction::Jump(0));
+                    self.instructions[split] =
+                        Instruction::Split(split + 1, self.instructions.len());
+                }
+                self.compile(last);
+                let end = self.instructions.len();
+                for jump in jumps {
+                    self.instructions[jump] = Instruction::Jump(end);
+                }
+            }
+            RegexAst::KleeneStar(inner) => {
+                let split = self.instructions.len();
+                self.instructions.push(Instruction::Split(split + 1, 0));
+                self.compile(inner);
+                self.instructions.push(Instruction::Jump(split));
+                self.instructions[split] = Instruction::Split(split + 1, self.instructions.len());
+            }
+            RegexAst::Group(number, inner) => {
+                self.slot_count = self.slot_count.max(2 * number + 2);
+                self.instructions.push(Instruction::Save(2 * number));
+                self.compile(inner);
+                self.instructions.push(Instruction::Save(2 * number + 1));
+            }
+        }
+    }
+
+    /// Extends the program to match `literal` after the pattern, like `Regex::then_literal`.
+    pub(crate) fn then_literal(&mut self, literal: &str) {
+        // Jumps past the pattern target the final `Save`, which now is the first literal char
+        let end = self.instructions.len() - 2;
+        self.instructions.splice(
+            end..end,
+            literal
+                .chars()
+                .map(|c| Instruction::Class(CharClass::from_char(c))),
+        );
+    }
+
+    /// Returns the spans of the groups when the program matches exactly `start..end` of
+    /// `text`, with `None` for a group that did not participate. Every char is mapped with the
+    /// char map of `alphabet` first.
+    pub(crate) fn group_spans(
+        &self,
+        alphabet: &Alphabet,
+        text: &str,
+        start: usize,
+        end: usize,
+    ) -> Option<Vec<Option<(usize, usize)>>> {
+        let mut threads = Threads::new(self.instructions.len());
+        let mut next_threads = Threads::new(self.instructions.len());
+        threads.add(&self.instructions, 0, vec![None; self.slot_count], start);
+
+        for (offset, c) in text[start..end].char_indices() {
+            let next_pos = start + offset + c.len_utf8();
+            let c = alphabet.map_char(c);
+            for (pc, slots) in threads.list.drain(..) {
+                if let Instruction::Class(class) = &self.instructions[pc]
+                    && class.contains(c)
+                {
+                    next_threads.add(&self.instructions, pc + 1, slots, next_pos);
+                }
+            }
+            std::mem::swap(&mut threads, &mut next_threads);
+            next_threads.seen.fill(false);
+        }
+
+        // The threads are in priority order, the first one to match wins
+        let (_, slots) = threads
+            .list
+            .iter()
+            .find(|&&(pc, _)| matches!(self.instructions[pc], Instruction::Match))?;
+        Some(slots.chunks(2).map(|span| span[0].zip(span[1])).collect())
+    }
+}
+
+/// Threads waiting at a `Class` or `Match` instruction, in priority order.
+struct Threads {
+    list: Vec<(usize, Vec<Option<usize>>)>,
+    /// Instructions already reached at the current offset, a later thread reaching one again
+    /// has a lower priority and is dropped
+    seen: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Threads {
+            list: Vec::new(),
+            seen: vec![false; len],
+        }
+    }
+
+    /// Follows the instructions not consuming a char from `pc` on, with an explicit stack so
+    /// deeply nested patterns cannot overflow the call stack.
+    fn add(
+        &mut self,
+        instructions: &[Instruction],
+        pc: usize,
+        slots: Vec<Option<usize>>,
+        pos: usize,
+    ) {
+        let mut stack = vec![(pc, slots)];
+        while let Some((pc, mut slots)) = stack.pop() {
+            if std::mem::replace(&mut self.seen[pc], true) {
+                continue;
+            }
+            match &instructions[pc] {
+                Instruction::Class(_) | Instruction::Match => self.list.push((pc, slots)),
+                // The preferred target is pushed last so it is followed first
+                &Instruction::Split(preferred, other) => {
+                    stack.push((other, slots.clone()));
+                    stack.push((preferred, slots));
+                }
+                &Instruction::Jump(target) => stack.push((target, slots)),
+                &Instruction::Save(slot) => {
+                    slots[slot] = Some(pos);
+                    stack.push((pc + 1, slots));
+                }
+            }
+        }
+    }
+}
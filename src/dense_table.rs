@@ -0,0 +1,119 @@
+use crate::alphabet::Alphabet;
+use crate::{Dfa, byte_positions, leftmost_longest_match};
+use std::collections::{BTreeSet, HashMap};
+
+/// Number of columns of the table, one per ASCII char.
+const ASCII_LEN: usize = 128;
+/// Largest number of states a table is built for, which keeps it at most 512 KiB.
+const MAX_DENSE_STATES: usize = 1024;
+/// Marks a missing transition.
+const NO_STATE: u32 = u32::MAX;
+
+/// Transition table of a DFA over an ASCII alphabet, with one row of 128 targets per state.
+///
+/// Following a transition is a single index instead of a lookup of the representative and a
+/// hash of the state, which pays off on long inputs. The DFA states are renumbered to the
+/// rows of the table.
+#[derive(Debug, Clone)]
+pub(crate) struct DenseTable {
+    next: Vec<u32>,
+    accepting: Vec<bool>,
+    start: u32,
+}
+
+impl DenseTable {
+    /// Builds the table for `dfa`, or returns `None` if a cell of its alphabet reaches beyond
+    /// ASCII or it has too many states for a table to be worth its memory.
+    pub(crate) fn new(dfa: &dyn Dfa) -> Option<Self> {
+        let alphabet = dfa.get_alphabet();
+        if alphabet.cells().iter().any(|&(_, end)| !end.is_ascii())
+            || dfa
+                .get_transitions()
+                .keys()
+                .any(|&(_, symbol)| !symbol.is_ascii())
+        {
+            return None;
+        }
+
+        let mut states: BTreeSet<u32> = dfa.get_accepting_states().iter().copied().collect();
+        states.insert(dfa.start_state());
+        for (&(source_state, _), &target_state) in dfa.get_transitions() {
+            states.insert(source_state);
+            states.insert(target_state);
+        }
+        if states.len() > MAX_DENSE_STATES {
+            return None;
+        }
+        let rows: HashMap<u32, u32> = states
+            .iter()
+            .enumerate()
+            .map(|(row, &state)| (state, row as u32))
+            .collect();
+
+        // Every char of a cell takes the transition of the cell's representative
+        let mut next = vec![NO_STATE; states.len() * ASCII_LEN];
+        for (&(source_state, representative), target_state) in dfa.get_transitions() {
+            let (cell_start, cell_end) = alphabet.cell(representative);
+            let row = rows[&source_state] as usize;
+            for c in cell_start..=cell_end {
+                next[row * ASCII_LEN + c as usize] = rows[target_state];
+            }
+        }
+        let accepting = states
+            .iter()
+            .map(|state| dfa.get_accepting_states().contains(state))
+            .collect();
+
+        Some(DenseTable {
+            next,
+            accepting,
+            start: rows[&dfa.start_state()],
+        })
+    }
+
+    /// Returns whether the DFA accepts all of `text`, mapping every char with the char map of
+    /// `alphabet` first.
+    pub(crate) fn process(&self, alphabet: &Alphabet, text: &str) -> bool {
+        let mut row = self.start;
+        for c in text.chars() {
+            let c = alphabet.map_char(c);
+            if !c.is_ascii() {
+                return false;
+            }
+            row = self.next[row as usize * ASCII_LEN + c as usize];
+            if row == NO_STATE {
+                return false;
+            }
+        }
+        self.accepting[row as usize]
+    }
+
+    fn step(&self, alphabet: &Alphabet, row: u32, c: char) -> Option<u32> {
+        let c = alphabet.map_char(c);
+        if !c.is_ascii() {
+            return None;
+        }
+        let next_row = self.next[row as usize * ASCII_LEN + c as usize];
+        (next_row != NO_STATE).then_some(next_row)
+    }
+
+    /// Finds the leftmost-longest match starting at or after `start_pos`, like `Dfa::find_at`.
+    ///
+    /// Runs the same search, but every step is an index into the table and the rows already
+    /// reached by a char are marked in a `Vec` instead of a `HashSet`.
+    pub(crate) fn find_at(
+        &self,
+        alphabet: &Alphabet,
+        text: &str,
+        start_pos: usize,
+    ) -> Option<(usize, usize)> {
+        leftmost_longest_match(
+            self.start,
+            start_pos,
+            byte_positions(text, start_pos),
+            &mut vec![false; self.accepting.len()],
+            |row| self.accepting[row as usize],
+            |row, c| self.step(alphabet, row, c),
+        )
+    }
+}
@@ -1,41 +1,64 @@
-use crate::{Dfa, is_valid_regex, normalise_regex};
+use crate::alphabet::{Alphabet, CharClass, parse_class, parse_escape, pattern_classes};
+use crate::{
+    Dfa, RegexError, check_size_limit, comment_end, group_name, normalise_regex, repetition_bounds,
+    repetition_end, validate_regex,
+};
 use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::rc::Rc;
 
 #[derive(Debug, Clone)]
-enum RegexAst {
+pub(crate) enum RegexAst {
     Char(char),
+    Class(CharClass),
     Concat(Vec<RegexAst>),
     Alternation(Vec<RegexAst>),
     KleeneStar(Box<RegexAst>),
+    /// Capture group with its number, matching the same as its inner AST.
+    Group(usize, Box<RegexAst>),
 }
 
+/// NFA without epsilon transitions, as built by Glushkov and Antimirov.
 #[derive(Debug)]
-struct Nfa {
-    transitions: HashMap<(u32, char), Vec<u32>>,
-    accepting_states: HashSet<u32>,
+pub(crate) struct Nfa {
+    pub(crate) start_state: u32,
+    pub(crate) transitions: HashMap<(u32, char), Vec<u32>>,
+    pub(crate) accepting_states: HashSet<u32>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GlushkovDfa {
     transitions: HashMap<(u32, char), u32>,
     accepting_states: HashSet<u32>,
+    alphabet: Alphabet,
 }
 
 impl Dfa for GlushkovDfa {
-    fn new(regex: &str) -> Result<Self, String> {
-        if !is_valid_regex(regex) {
-            return Err(format!("{regex} is not a valid regular expression!"));
-        }
+    fn new(regex: &str, size_limit: Option<usize>) -> Result<Self, RegexError> {
+        validate_regex(regex)?;
 
         let normalised_regex = normalise_regex(regex);
+        let alphabet = Alphabet::new(&pattern_classes(&normalised_regex)?);
         let ast = parse_regex(&normalised_regex)?;
-        let nfa = glushkov_construction(ast)?;
-        let mut regex_dfa = nfa_to_dfa(nfa);
+        let nfa = glushkov_construction(ast, &alphabet)?;
+        let mut regex_dfa: GlushkovDfa = nfa_to_dfa(nfa, size_limit)?;
+        regex_dfa.alphabet = alphabet;
 
         <Self as Dfa>::optimise_dfa(&mut regex_dfa);
         Ok(regex_dfa)
     }
 
+    fn from_parts(
+        transitions: HashMap<(u32, char), u32>,
+        accepting_states: HashSet<u32>,
+        alphabet: Alphabet,
+    ) -> Self {
+        GlushkovDfa {
+            transitions,
+            accepting_states,
+            alphabet,
+        }
+    }
+
     fn get_transitions(&self) -> &HashMap<(u32, char), u32> {
         &self.transitions
     }
@@ -51,21 +74,31 @@ impl Dfa for GlushkovDfa {
     fn get_accepting_states_mut(&mut self) -> &mut HashSet<u32> {
         &mut self.accepting_states
     }
+
+    fn get_alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    fn get_alphabet_mut(&mut self) -> &mut Alphabet {
+        &mut self.alphabet
+    }
 }
 
 // Parser for regex string to AST
-fn parse_regex(regex: &str) -> Result<RegexAst, String> {
+pub(crate) fn parse_regex(regex: &str) -> Result<RegexAst, RegexError> {
     let chars: Vec<char> = regex.chars().collect();
     let (ast, pos) = parse_alternation(&chars, 0)?;
 
     if pos != chars.len() {
-        return Err("Unexpected characters at end of regex".to_string());
+        return Err(RegexError::Syntax(
+            "Unexpected characters at end of regex".to_string(),
+        ));
     }
 
     Ok(ast)
 }
 
-fn parse_alternation(chars: &[char], mut pos: usize) -> Result<(RegexAst, usize), String> {
+fn parse_alternation(chars: &[char], mut pos: usize) -> Result<(RegexAst, usize), RegexError> {
     let mut alternatives = Vec::new();
 
     let (first_alt, new_pos) = parse_concatenation(chars, pos)?;
@@ -86,7 +119,7 @@ fn parse_alternation(chars: &[char], mut pos: usize) -> Result<(RegexAst, usize)
     }
 }
 
-fn parse_concatenation(chars: &[char], mut pos: usize) -> Result<(RegexAst, usize), String> {
+fn parse_concatenation(chars: &[char], mut pos: usize) -> Result<(RegexAst, usize), RegexError> {
     let mut elements = Vec::new();
 
     while pos < chars.len() && chars[pos] != '|' && chars[pos] != ')' {
@@ -108,9 +141,9 @@ fn parse_concatenation(chars: &[char], mut pos: usize) -> Result<(RegexAst, usiz
     }
 }
 
-fn parse_factor(chars: &[char], mut pos: usize) -> Result<(RegexAst, usize), String> {
+fn parse_factor(chars: &[char], mut pos: usize) -> Result<(RegexAst, usize), RegexError> {
     if pos >= chars.len() {
-        return Err("Unexpected end of regex".to_string());
+        return Err(RegexError::Syntax("Unexpected end of regex".to_string()));
     }
 
     let (base, new_pos) = match chars[pos] {
@@ -118,20 +151,24 @@ fn parse_factor(chars: &[char], mut pos: usize) -> Result<(RegexAst, usize), Str
             pos += 1; // skip '('
             let (inner, inner_pos) = parse_alternation(chars, pos)?;
             if inner_pos >= chars.len() || chars[inner_pos] != ')' {
-                return Err("Unmatched opening parenthesis".to_string());
+                return Err(RegexError::UnbalancedParens);
             }
             (inner, inner_pos + 1) // skip ')'
         }
-        '\\' => {
-            if pos + 1 >= chars.len() {
-                return Err("Invalid escape sequence".to_string());
+        '\\' | '[' => {
+            let (class, end) = if chars[pos] == '\\' {
+                parse_escape(chars, pos + 1)?
+            } else {
+                parse_class(chars, pos + 1)?
+            };
+            match class.ranges() {
+                &[(start, end_char)] if start == end_char => (RegexAst::Char(start), end),
+                _ => (RegexAst::Class(class), end),
             }
-            pos += 1; // skip '\'
-            (RegexAst::Char(chars[pos]), pos + 1)
         }
         c if c.is_ascii() && !"()|*+\\".contains(c) => (RegexAst::Char(c), pos + 1),
         _ => {
-            return Err(format!("Unexpected character: {}", chars[pos]));
+            return Err(RegexError::UnexpectedChar(chars[pos]));
         }
     };
 
@@ -146,19 +183,182 @@ fn parse_factor(chars: &[char], mut pos: usize) -> Result<(RegexAst, usize), Str
     }
 }
 
-fn glushkov_construction(ast: RegexAst) -> Result<Nfa, String> {
-    let mut state_counter = 0u32;
-    let mut state_to_char: HashMap<u32, char> = HashMap::new();
+/// Parses `pattern` as written into an AST keeping its capture groups, for the capture
+/// simulation.
+///
+/// Unlike `parse_regex` the pattern is not normalised first, so groups are numbered by their
+/// opening parenthesis like `capture_names` numbers them. `+`, `?` and bounded repetitions are
+/// lowered to stars and alternations here, repeating the group nodes of their atom, and every
+/// `.` becomes the AST of `dot_pattern`. The groups of `dot_pattern` are plain groups. Anchors
+/// and quotes have to be handled before, and the pattern has to be valid.
+pub(crate) fn parse_capture_regex(
+    pattern: &str,
+    dot_pattern: &str,
+) -> Result<RegexAst, RegexError> {
+    let dot = CaptureParser::new(dot_pattern, None).parse()?;
+    CaptureParser::new(pattern, Some(dot)).parse()
+}
+
+/// Recursive descent parser behind `parse_capture_regex`.
+struct CaptureParser {
+    chars: Vec<char>,
+    /// AST a `.` stands for, or `None` while parsing the dot itself
+    dot: Option<RegexAst>,
+    next_group: usize,
+}
+
+impl CaptureParser {
+    fn new(pattern: &str, dot: Option<RegexAst>) -> Self {
+        CaptureParser {
+            chars: pattern.chars().collect(),
+            dot,
+            next_group: 1,
+        }
+    }
 
-    // Assign unique state numbers to each character occurrence
-    assign_positions(&ast, &mut state_counter, &mut state_to_char);
+    fn parse(mut self) -> Result<RegexAst, RegexError> {
+        let (ast, pos) = self.alternation(0)?;
+        if pos != self.chars.len() {
+            return Err(RegexError::UnbalancedParens);
+        }
+        Ok(ast)
+    }
+
+    fn alternation(&mut self, pos: usize) -> Result<(RegexAst, usize), RegexError> {
+        let (first, mut pos) = self.concatenation(pos)?;
+        let mut alternatives = vec![first];
+        while self.chars.get(pos) == Some(&'|') {
+            let (alternative, end) = self.concatenation(pos + 1)?;
+            alternatives.push(alternative);
+            pos = end;
+        }
+        if alternatives.len() == 1 {
+            Ok((alternatives.pop().expect("One alternative"), pos))
+        } else {
+            Ok((RegexAst::Alternation(alternatives), pos))
+        }
+    }
+
+    fn concatenation(&mut self, mut pos: usize) -> Result<(RegexAst, usize), RegexError> {
+        let mut elements = Vec::new();
+        while pos < self.chars.len() && !matches!(self.chars[pos], '|' | ')') {
+            if let Some(end) = comment_end(&self.chars, pos + 1).filter(|_| self.chars[pos] == '(')
+            {
+                pos = end;
+                continue;
+            }
+            let (atom, end) = self.atom(pos)?;
+            let (element, end) = self.quantified(atom, end)?;
+            elements.push(element);
+            pos = end;
+        }
+        if elements.len() == 1 {
+            Ok((elements.pop().expect("One element"), pos))
+        } else {
+            Ok((RegexAst::Concat(elements), pos))
+        }
+    }
+
+    fn atom(&mut self, pos: usize) -> Result<(RegexAst, usize), RegexError> {
+        let chars = &self.chars;
+        match chars[pos] {
+            '(' => {
+                let start = group_name(chars, pos + 1).map_or(pos + 1, |(_, end)| end);
+                let group = self.dot.is_some();
+                let number = self.next_group;
+                if group {
+                    self.next_group += 1;
+                }
+                let (inner, end) = self.alternation(start)?;
+                if self.chars.get(end) != Some(&')') {
+                    return Err(RegexError::UnbalancedParens);
+                }
+                let ast = if group {
+                    RegexAst::Group(number, Box::new(inner))
+                } else {
+                    inner
+                };
+                Ok((ast, end + 1))
+            }
+            '\\' | '[' => {
+                let (class, end) = if chars[pos] == '\\' {
+                    parse_escape(chars, pos + 1)?
+                } else {
+                    parse_class(chars, pos + 1)?
+                };
+                Ok((RegexAst::Class(class), end))
+            }
+            '.' => match &self.dot {
+                Some(dot) => Ok((dot.clone(), pos + 1)),
+                None => {
+                    let printable: Vec<char> = " -~]".chars().collect();
+                    let (class, _) = parse_class(&printable, 0)?;
+                    Ok((RegexAst::Class(class), pos + 1))
+                }
+            },
+            '*' | '+' | '?' => Err(RegexError::DanglingQuantifier),
+            c => Ok((RegexAst::Char(c), pos + 1)),
+        }
+    }
+
+    /// Applies the quantifiers following an atom, skipping a lazy `?` and comments.
+    fn quantified(&self, atom: RegexAst, mut pos: usize) -> Result<(RegexAst, usize), RegexError> {
+        let chars = &self.chars;
+        let mut ast = atom;
+        let mut follows_quantifier = false;
+        while pos < chars.len() {
+            if let Some(end) = comment_end(chars, pos + 1).filter(|_| chars[pos] == '(') {
+                pos = end;
+                continue;
+            }
+            ast = match chars[pos] {
+                '?' if follows_quantifier => {
+                    pos += 1;
+                    break;
+                }
+                '*' => RegexAst::KleeneStar(Box::new(ast)),
+                '+' => RegexAst::Concat(vec![ast.clone(), RegexAst::KleeneStar(Box::new(ast))]),
+                '?' => RegexAst::Alternation(vec![ast, RegexAst::Concat(vec![])]),
+                '{' if repetition_end(chars, pos + 1).is_some() => {
+                    let end = repetition_end(chars, pos + 1).expect("Valid repetition");
+                    let (min, max) = repetition_bounds(&chars[pos + 1..end - 1])
+                        .ok_or(RegexError::InvalidRepetition)?;
+                    let mut elements = vec![ast.clone(); min];
+                    match max {
+                        Some(max) => elements.extend((min..max).map(|_| {
+                            RegexAst::Alternation(vec![ast.clone(), RegexAst::Concat(vec![])])
+                        })),
+                        None => elements.push(RegexAst::KleeneStar(Box::new(ast))),
+                    }
+                    pos = end - 1;
+                    RegexAst::Concat(elements)
+                }
+                _ => break,
+            };
+            pos += 1;
+            follows_quantifier = true;
+        }
+        Ok((ast, pos))
+    }
+}
+
+fn glushkov_construction(ast: RegexAst, alphabet: &Alphabet) -> Result<Nfa, RegexError> {
+    // Assign unique state numbers to each character or class occurrence
+    let mut positions = Positions {
+        ranges: HashMap::new(),
+        symbols: HashMap::new(),
+    };
+    let mut state_counter = 0u32;
+    assign_positions(&ast, alphabet, &mut state_counter, &mut positions);
 
     let start_state = state_counter;
 
-    // Compute First, Last, Follow sets - each with fresh position counter
-    let first_set = first(&ast);
-    let last_set = last(&ast);
-    let follow_map = follow(&ast);
+    // Compute First, Last, Follow sets from the same position numbering
+    let mut shared = SharedSets::new(&ast, &positions.ranges);
+    let first_set = first_positions(&ast, &mut shared);
+    let last_set = last_positions(&ast, &mut shared);
+    let mut follow_map = HashMap::new();
+    follow_positions(&ast, &mut shared, &mut follow_map);
 
     // Build NFA
     let mut transitions = HashMap::new();
@@ -166,7 +366,7 @@ fn glushkov_construction(ast: RegexAst) -> Result<Nfa, String> {
 
     // Transitions from start state
     for &state in &first_set {
-        if let Some(&ch) = state_to_char.get(&state) {
+        for &ch in positions.symbols.get(&state).into_iter().flatten() {
             transitions
                 .entry((start_state, ch))
                 .or_insert_with(Vec::new)
@@ -177,7 +377,7 @@ fn glushkov_construction(ast: RegexAst) -> Result<Nfa, String> {
     // Internal transitions based on follow sets
     for (state, follow_states) in follow_map {
         for &follow_state in &follow_states {
-            if let Some(&ch) = state_to_char.get(&follow_state) {
+            for &ch in positions.symbols.get(&follow_state).into_iter().flatten() {
                 transitions
                     .entry((state, ch))
                     .or_insert_with(Vec::new)
@@ -195,164 +395,263 @@ fn glushkov_construction(ast: RegexAst) -> Result<Nfa, String> {
     }
 
     Ok(Nfa {
+        start_state,
         transitions,
         accepting_states,
     })
 }
 
-fn first(ast: &RegexAst) -> HashSet<u32> {
-    let mut positions = HashMap::new();
-    let mut counter = 0;
-    map_ast_to_positions(ast, &mut counter, &mut positions);
-    first_positions(ast, &positions)
+/// Numbering of the character and class occurrences of an AST.
+///
+/// Both maps are filled in a single traversal, so the position ranges used for the First,
+/// Last and Follow sets always line up with the symbols each position consumes.
+struct Positions {
+    ranges: HashMap<*const RegexAst, (u32, u32)>,
+    symbols: HashMap<u32, Vec<char>>,
 }
 
-fn last(ast: &RegexAst) -> HashSet<u32> {
-    let mut positions = HashMap::new();
-    let mut counter = 0;
-    map_ast_to_positions(ast, &mut counter, &mut positions);
-    last_positions(ast, &positions)
+/// Structure of a subtree with its children replaced by their ids.
+#[derive(PartialEq, Eq, Hash)]
+enum SubtreeKey {
+    Char(char),
+    Class(CharClass),
+    Concat(Vec<usize>),
+    Alternation(Vec<usize>),
+    KleeneStar(usize),
+    Group(usize),
 }
 
-fn follow(ast: &RegexAst) -> HashMap<u32, HashSet<u32>> {
-    let mut positions = HashMap::new();
-    let mut counter = 0;
-    map_ast_to_positions(ast, &mut counter, &mut positions);
+/// Nullability and First and Last positions of a subtree, relative to its first position.
+struct SubtreeSets {
+    nullable: bool,
+    first: Vec<u32>,
+    last: Vec<u32>,
+}
 
-    let mut result = HashMap::new();
-    follow_positions(ast, &positions, &mut result);
-    result
+/// Follow sets of a subtree, relative to its first position.
+type RelativeFollows = Vec<(u32, Vec<u32>)>;
+
+/// Computes the First, Last and Follow sets once per structurally identical subtree.
+///
+/// Identical subtrees cover position ranges of the same length, so their sets only differ by
+/// the start of the range. Large generated patterns often repeat the same group many times,
+/// which is then only analysed once and shifted to every other occurrence.
+struct SharedSets<'a> {
+    ranges: &'a HashMap<*const RegexAst, (u32, u32)>,
+    ids: HashMap<*const RegexAst, usize>,
+    occurrences: Vec<usize>,
+    sets: HashMap<usize, Rc<SubtreeSets>>,
+    follows: HashMap<usize, Rc<RelativeFollows>>,
 }
 
-// Helper function to create a mapping from AST nodes to their position ranges
-fn map_ast_to_positions(
-    ast: &RegexAst,
-    counter: &mut u32,
-    positions: &mut HashMap<*const RegexAst, (u32, u32)>,
-) {
-    let start_pos = *counter;
+impl<'a> SharedSets<'a> {
+    fn new(ast: &RegexAst, ranges: &'a HashMap<*const RegexAst, (u32, u32)>) -> Self {
+        let mut shared = SharedSets {
+            ranges,
+            ids: HashMap::new(),
+            occurrences: Vec::new(),
+            sets: HashMap::new(),
+            follows: HashMap::new(),
+        };
+        shared.intern(ast, &mut HashMap::new());
+        shared
+    }
 
-    match ast {
-        RegexAst::Char(_) => {
-            *counter += 1;
-        }
-        RegexAst::Concat(elements) => {
-            for element in elements {
-                map_ast_to_positions(element, counter, positions);
-            }
-        }
-        RegexAst::Alternation(alternatives) => {
-            for alt in alternatives {
-                map_ast_to_positions(alt, counter, positions);
-            }
-        }
-        RegexAst::KleeneStar(inner) => {
-            map_ast_to_positions(inner, counter, positions);
+    /// Assigns every subtree an id that is shared by all structurally identical subtrees.
+    fn intern(&mut self, ast: &RegexAst, keys: &mut HashMap<SubtreeKey, usize>) -> usize {
+        let key = match ast {
+            RegexAst::Char(ch) => SubtreeKey::Char(*ch),
+            RegexAst::Class(class) => SubtreeKey::Class(class.clone()),
+            RegexAst::Concat(elements) => SubtreeKey::Concat(
+                elements
+                    .iter()
+                    .map(|element| self.intern(element, keys))
+                    .collect(),
+            ),
+            RegexAst::Alternation(alternatives) => SubtreeKey::Alternation(
+                alternatives
+                    .iter()
+                    .map(|alt| self.intern(alt, keys))
+                    .collect(),
+            ),
+            RegexAst::KleeneStar(inner) => SubtreeKey::KleeneStar(self.intern(inner, keys)),
+            RegexAst::Group(_, inner) => SubtreeKey::Group(self.intern(inner, keys)),
+        };
+
+        let next_id = keys.len();
+        let id = *keys.entry(key).or_insert(next_id);
+        if id == self.occurrences.len() {
+            self.occurrences.push(0);
         }
+        self.occurrences[id] += 1;
+        self.ids.insert(ast as *const RegexAst, id);
+        id
     }
 
-    positions.insert(ast as *const RegexAst, (start_pos, *counter));
-}
+    fn start(&self, ast: &RegexAst) -> u32 {
+        self.ranges[&(ast as *const RegexAst)].0
+    }
 
-fn first_positions(
-    ast: &RegexAst,
-    positions: &HashMap<*const RegexAst, (u32, u32)>,
-) -> HashSet<u32> {
-    match ast {
-        RegexAst::Char(_) => {
-            let (start_pos, _) = positions[&(ast as *const RegexAst)];
-            let mut result = HashSet::new();
-            result.insert(start_pos);
-            result
+    fn subtree_sets(&mut self, ast: &RegexAst) -> Rc<SubtreeSets> {
+        let id = self.ids[&(ast as *const RegexAst)];
+        if let Some(sets) = self.sets.get(&id) {
+            return Rc::clone(sets);
         }
-        RegexAst::Concat(elements) => {
-            let mut result = HashSet::new();
-            for element in elements {
-                result.extend(first_positions(element, positions));
-                if !nullable(element) {
-                    break;
+
+        let start = self.start(ast);
+        let sets = match ast {
+            RegexAst::Char(_) | RegexAst::Class(_) => SubtreeSets {
+                nullable: false,
+                first: vec![0],
+                last: vec![0],
+            },
+            RegexAst::Concat(elements) => {
+                let element_sets: Vec<(u32, Rc<SubtreeSets>)> = elements
+                    .iter()
+                    .map(|element| (self.start(element) - start, self.subtree_sets(element)))
+                    .collect();
+                let mut first = Vec::new();
+                for (offset, sets) in &element_sets {
+                    first.extend(sets.first.iter().map(|pos| pos + offset));
+                    if !sets.nullable {
+                        break;
+                    }
+                }
+                let mut last = Vec::new();
+                for (offset, sets) in element_sets.iter().rev() {
+                    last.extend(sets.last.iter().map(|pos| pos + offset));
+                    if !sets.nullable {
+                        break;
+                    }
+                }
+                SubtreeSets {
+                    // Empty concat is nullable (represents epsilon)
+                    nullable: element_sets.iter().all(|(_, sets)| sets.nullable),
+                    first,
+                    last,
                 }
             }
-            result
-        }
-        RegexAst::Alternation(alternatives) => {
-            let mut result = HashSet::new();
-            for alt in alternatives {
-                result.extend(first_positions(alt, positions));
+            RegexAst::Alternation(alternatives) => {
+                let mut sets = SubtreeSets {
+                    nullable: false,
+                    first: Vec::new(),
+                    last: Vec::new(),
+                };
+                for alt in alternatives {
+                    let offset = self.start(alt) - start;
+                    let alt_sets = self.subtree_sets(alt);
+                    sets.nullable |= alt_sets.nullable;
+                    sets.first
+                        .extend(alt_sets.first.iter().map(|pos| pos + offset));
+                    sets.last
+                        .extend(alt_sets.last.iter().map(|pos| pos + offset));
+                }
+                sets
             }
-            result
-        }
-        RegexAst::KleeneStar(inner) => first_positions(inner, positions),
+            RegexAst::KleeneStar(inner) => {
+                let inner_sets = self.subtree_sets(inner);
+                SubtreeSets {
+                    nullable: true,
+                    first: inner_sets.first.clone(),
+                    last: inner_sets.last.clone(),
+                }
+            }
+            RegexAst::Group(_, inner) => {
+                let inner_sets = self.subtree_sets(inner);
+                SubtreeSets {
+                    nullable: inner_sets.nullable,
+                    first: inner_sets.first.clone(),
+                    last: inner_sets.last.clone(),
+                }
+            }
+        };
+
+        let sets = Rc::new(sets);
+        self.sets.insert(id, Rc::clone(&sets));
+        sets
     }
 }
 
-fn last_positions(
+fn first_positions(ast: &RegexAst, shared: &mut SharedSets) -> HashSet<u32> {
+    let start = shared.start(ast);
+    let sets = shared.subtree_sets(ast);
+    sets.first.iter().map(|pos| pos + start).collect()
+}
+
+fn last_positions(ast: &RegexAst, shared: &mut SharedSets) -> HashSet<u32> {
+    let start = shared.start(ast);
+    let sets = shared.subtree_sets(ast);
+    sets.last.iter().map(|pos| pos + start).collect()
+}
+
+fn follow_positions(
     ast: &RegexAst,
-    positions: &HashMap<*const RegexAst, (u32, u32)>,
-) -> HashSet<u32> {
-    match ast {
-        RegexAst::Char(_) => {
-            let (start_pos, _) = positions[&(ast as *const RegexAst)];
-            let mut result = HashSet::new();
-            result.insert(start_pos);
-            result
-        }
-        RegexAst::Concat(elements) => {
-            let mut result = HashSet::new();
-            for element in elements.iter().rev() {
-                result.extend(last_positions(element, positions));
-                if !nullable(element) {
-                    break;
-                }
-            }
-            result
-        }
-        RegexAst::Alternation(alternatives) => {
-            let mut result = HashSet::new();
-            for alt in alternatives {
-                result.extend(last_positions(alt, positions));
-            }
-            result
+    shared: &mut SharedSets,
+    result: &mut HashMap<u32, HashSet<u32>>,
+) {
+    let id = shared.ids[&(ast as *const RegexAst)];
+    if shared.occurrences[id] == 1 {
+        subtree_follow_positions(ast, shared, result);
+        return;
+    }
+
+    // The Follow sets within a repeated subtree only connect its own positions
+    let start = shared.start(ast);
+    let follows = match shared.follows.get(&id) {
+        Some(follows) => Rc::clone(follows),
+        None => {
+            let mut subtree_result = HashMap::new();
+            subtree_follow_positions(ast, shared, &mut subtree_result);
+            let follows: RelativeFollows = subtree_result
+                .into_iter()
+                .map(|(pos, follow)| {
+                    let follow = follow.into_iter().map(|pos| pos - start).collect();
+                    (pos - start, follow)
+                })
+                .collect();
+            let follows = Rc::new(follows);
+            shared.follows.insert(id, Rc::clone(&follows));
+            follows
         }
-        RegexAst::KleeneStar(inner) => last_positions(inner, positions),
+    };
+    for (pos, follow) in follows.iter() {
+        result
+            .entry(pos + start)
+            .or_default()
+            .extend(follow.iter().map(|pos| pos + start));
     }
 }
 
-fn follow_positions(
+fn subtree_follow_positions(
     ast: &RegexAst,
-    positions: &HashMap<*const RegexAst, (u32, u32)>,
+    shared: &mut SharedSets,
     result: &mut HashMap<u32, HashSet<u32>>,
 ) {
     match ast {
-        RegexAst::Char(_) => {
+        RegexAst::Char(_) | RegexAst::Class(_) => {
             // Base case - no follow computation needed
         }
         RegexAst::Concat(elements) => {
             // Process each element recursively
             for element in elements {
-                follow_positions(element, positions, result);
+                follow_positions(element, shared, result);
             }
 
             // Add follow relationships between consecutive elements
-            for i in 0..elements.len() {
-                let last_i = last_positions(&elements[i], positions);
-
-                // For each subsequent element j > i
-                for j in (i + 1)..elements.len() {
-                    // Check if all elements between i and j are nullable
-                    let all_between_nullable = elements[(i + 1)..j].iter().all(nullable);
+            for (i, element) in elements.iter().enumerate() {
+                let last_i = last_positions(element, shared);
 
-                    if j == i + 1 || all_between_nullable {
-                        let first_j = first_positions(&elements[j], positions);
+                // For each subsequent element j > i, as long as the elements between are nullable
+                for next_element in &elements[(i + 1)..] {
+                    let first_j = first_positions(next_element, shared);
 
-                        // Add follow relationships from last(i) to first(j)
-                        for &last_state in &last_i {
-                            result.entry(last_state).or_default().extend(&first_j);
-                        }
+                    // Add follow relationships from last(i) to first(j)
+                    for &last_state in &last_i {
+                        result.entry(last_state).or_default().extend(&first_j);
                     }
 
                     // If element j is not nullable, we can't skip further
-                    if !nullable(&elements[j]) {
+                    if !shared.subtree_sets(next_element).nullable {
                         break;
                     }
                 }
@@ -360,90 +659,108 @@ fn follow_positions(
         }
         RegexAst::Alternation(alternatives) => {
             for alt in alternatives {
-                follow_positions(alt, positions, result);
+                follow_positions(alt, shared, result);
             }
         }
         RegexAst::KleeneStar(inner) => {
-            follow_positions(inner, positions, result);
+            follow_positions(inner, shared, result);
 
             // Kleene star: last positions can loop back to first positions
-            let inner_last = last_positions(inner, positions);
-            let inner_first = first_positions(inner, positions);
+            let inner_last = last_positions(inner, shared);
+            let inner_first = first_positions(inner, shared);
 
             for &last_state in &inner_last {
                 result.entry(last_state).or_default().extend(&inner_first);
             }
         }
+        RegexAst::Group(_, inner) => follow_positions(inner, shared, result),
+    }
+}
+
+/// Returns whether a star of `regex` applies to a subpattern that already matches the empty
+/// string, as in `(a*)*` or `(a|b?)*`, which a plain star over the same characters matches as
+/// well.
+pub(crate) fn has_redundant_star(regex: &str) -> Result<bool, RegexError> {
+    fn visit(ast: &RegexAst) -> bool {
+        match ast {
+            RegexAst::Char(_) | RegexAst::Class(_) => false,
+            RegexAst::Concat(items) | RegexAst::Alternation(items) => items.iter().any(visit),
+            RegexAst::KleeneStar(inner) => nullable(inner) || visit(inner),
+            RegexAst::Group(_, inner) => visit(inner),
+        }
     }
+    Ok(visit(&parse_regex(&normalise_regex(regex))?))
 }
 
 fn nullable(ast: &RegexAst) -> bool {
     match ast {
-        RegexAst::Char(_) => false,
+        RegexAst::Char(_) | RegexAst::Class(_) => false,
         RegexAst::Concat(elements) => {
             // Empty concat is nullable (represents epsilon)
             elements.is_empty() || elements.iter().all(nullable)
         }
         RegexAst::Alternation(alternatives) => alternatives.iter().any(nullable),
         RegexAst::KleeneStar(_) => true,
+        RegexAst::Group(_, inner) => nullable(inner),
     }
 }
 
-fn assign_positions(ast: &RegexAst, counter: &mut u32, state_to_char: &mut HashMap<u32, char>) {
+// Helper function to number each leaf and map AST nodes to their position ranges
+fn assign_positions(
+    ast: &RegexAst,
+    alphabet: &Alphabet,
+    counter: &mut u32,
+    positions: &mut Positions,
+) {
+    let start_pos = *counter;
+
     match ast {
         RegexAst::Char(ch) => {
-            let state = *counter;
             *counter += 1;
-            state_to_char.insert(state, *ch);
+            positions.symbols.insert(start_pos, vec![*ch]);
+        }
+        RegexAst::Class(class) => {
+            *counter += 1;
+            positions
+                .symbols
+                .insert(start_pos, alphabet.representatives(class));
         }
         RegexAst::Concat(elements) => {
             for element in elements {
-                assign_positions(element, counter, state_to_char);
+                assign_positions(element, alphabet, counter, positions);
             }
         }
         RegexAst::Alternation(alternatives) => {
             for alt in alternatives {
-                assign_positions(alt, counter, state_to_char);
+                assign_positions(alt, alphabet, counter, positions);
             }
         }
-        RegexAst::KleeneStar(inner) => {
-            assign_positions(inner, counter, state_to_char);
+        RegexAst::KleeneStar(inner) | RegexAst::Group(_, inner) => {
+            assign_positions(inner, alphabet, counter, positions);
         }
     }
+
+    positions
+        .ranges
+        .insert(ast as *const RegexAst, (start_pos, *counter));
 }
 
-fn nfa_to_dfa(nfa: Nfa) -> GlushkovDfa {
+/// Determinises `nfa` with the subset construction into a DFA of any construction type, with
+/// an empty alphabet the caller fills in.
+pub(crate) fn nfa_to_dfa<D: Dfa>(nfa: Nfa, size_limit: Option<usize>) -> Result<D, RegexError> {
     let mut dfa_transitions = HashMap::new();
     let mut dfa_accepting_states = HashSet::new();
     let mut state_sets_to_dfa_state: HashMap<BTreeSet<u32>, u32> = HashMap::new();
     let mut queue = VecDeque::new();
     let mut next_dfa_state = 0u32;
 
-    // Get alphabet from NFA
-    let alphabet: HashSet<char> = nfa.transitions.keys().map(|(_, ch)| *ch).collect();
+    // Get alphabet from NFA, visited in order so the DFA states are numbered the same on every
+    // run
+    let alphabet: BTreeSet<char> = nfa.transitions.keys().map(|(_, ch)| *ch).collect();
 
-    // Find start state (highest numbered state in NFA)
-    let mut all_nfa_states = HashSet::new();
-
-    for &(from_state, _) in nfa.transitions.keys() {
-        all_nfa_states.insert(from_state);
-    }
-    for target_states in nfa.transitions.values() {
-        for &to_state in target_states {
-            all_nfa_states.insert(to_state);
-        }
-    }
-    for &accepting_state in &nfa.accepting_states {
-        all_nfa_states.insert(accepting_state);
-    }
-
-    let start_state = all_nfa_states.iter().max().copied().unwrap_or(0);
-
-    let start_set: BTreeSet<u32> = {
-        let mut set = BTreeSet::new();
-        set.insert(start_state);
-        set
-    };
+    // The start state is numbered after all positions, it is not necessarily the highest
+    // numbered state that occurs in a transition (e.g. for the empty language)
+    let start_set: BTreeSet<u32> = BTreeSet::from([nfa.start_state]);
 
     state_sets_to_dfa_state.insert(start_set.clone(), next_dfa_state);
     queue.push_back(start_set);
@@ -484,27 +801,29 @@ fn nfa_to_dfa(nfa: Nfa) -> GlushkovDfa {
                 };
 
                 dfa_transitions.insert((current_dfa_state, symbol), next_dfa_state);
+                check_size_limit(
+                    dfa_transitions.len(),
+                    state_sets_to_dfa_state.len(),
+                    size_limit,
+                )?;
             }
         }
     }
 
     // Normalize to start from state 0
-    normalize_dfa_states(dfa_transitions, dfa_accepting_states)
+    Ok(normalize_dfa_states(dfa_transitions, dfa_accepting_states))
 }
 
-fn normalize_dfa_states(
+fn normalize_dfa_states<D: Dfa>(
     transitions: HashMap<(u32, char), u32>,
     accepting_states: HashSet<u32>,
-) -> GlushkovDfa {
+) -> D {
     if transitions.is_empty() && accepting_states.is_empty() {
-        return GlushkovDfa {
-            transitions,
-            accepting_states,
-        };
+        return D::from_parts(transitions, accepting_states, Alphabet::default());
     }
 
     // Find all states
-    let mut all_states = HashSet::new();
+    let mut all_states = BTreeSet::new();
     for &(from, _) in transitions.keys() {
         all_states.insert(from);
     }
@@ -514,10 +833,7 @@ fn normalize_dfa_states(
     all_states.extend(&accepting_states);
 
     if all_states.is_empty() {
-        return GlushkovDfa {
-            transitions,
-            accepting_states,
-        };
+        return D::from_parts(transitions, accepting_states, Alphabet::default());
     }
 
     // Create mapping with 0 as start state
@@ -547,8 +863,234 @@ fn normalize_dfa_states(
         new_accepting_states.insert(state_mapping[&state]);
     }
 
-    GlushkovDfa {
-        transitions: new_transitions,
-        accepting_states: new_accepting_states,
+    D::from_parts(new_transitions, new_accepting_states, Alphabet::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn optimise_partial_dfa_test() {
+        // `(aa)*(a|bb)`: the accepting states 1 and 2 only differ in that 2 has no transition
+        // at all, which Hopcroft on the partial DFA could not see. Whether they got merged
+        // depended on the order the splitters were taken in, so the DFA is minimised afresh
+        // a few times.
+        for _ in 0..20 {
+            let mut dfa = GlushkovDfa {
+                transitions: HashMap::from([
+                    ((0, 'a'), 1),
+                    ((0, 'b'), 3),
+                    ((1, 'a'), 0),
+                    ((3, 'b'), 2),
+                ]),
+                accepting_states: HashSet::from([1, 2]),
+                alphabet: Alphabet::default(),
+            };
+            dfa.optimise_dfa();
+            assert!(dfa.process("aabb"));
+            assert!(dfa.process("aaa"));
+            assert!(!dfa.process("bbaa"));
+            assert_eq!(dfa.get_accepting_states().len(), 2);
+        }
+    }
+
+    #[test]
+    fn repeated_subexpression_test() {
+        let ast = parse_regex("(ab)(ab)").expect("Valid regex");
+        let mut positions = Positions {
+            ranges: HashMap::new(),
+            symbols: HashMap::new(),
+        };
+        let mut counter = 0;
+        assign_positions(&ast, &Alphabet::default(), &mut counter, &mut positions);
+
+        let mut shared = SharedSets::new(&ast, &positions.ranges);
+        assert_eq!(first_positions(&ast, &mut shared), HashSet::from([0]));
+        assert_eq!(last_positions(&ast, &mut shared), HashSet::from([3]));
+
+        let mut follow_map = HashMap::new();
+        follow_positions(&ast, &mut shared, &mut follow_map);
+        assert_eq!(
+            follow_map,
+            HashMap::from([
+                (0, HashSet::from([1])),
+                (1, HashSet::from([2])),
+                (2, HashSet::from([3])),
+            ])
+        );
+
+        let generated_dfa = GlushkovDfa::new("(ab)(ab)", None).expect("Valid dfa");
+        assert!(generated_dfa.process("abab"));
+        for string in ["ab", "aabb", "ababab", ""] {
+            assert!(!generated_dfa.process(string));
+        }
+    }
+
+    #[test]
+    fn shared_subtree_sets_test() {
+        let pattern = "((a|b)*c)((a|b)*c)(a|b)";
+        let ast = parse_regex(pattern).expect("Valid regex");
+        let mut positions = Positions {
+            ranges: HashMap::new(),
+            symbols: HashMap::new(),
+        };
+        let mut counter = 0;
+        assign_positions(&ast, &Alphabet::default(), &mut counter, &mut positions);
+
+        let mut shared = SharedSets::new(&ast, &positions.ranges);
+        // The two `((a|b)*c)` groups and all three `(a|b)` groups share their ids
+        assert_eq!(
+            shared
+                .occurrences
+                .iter()
+                .filter(|&&count| count > 1)
+                .count(),
+            6
+        );
+
+        assert_eq!(first_positions(&ast, &mut shared), HashSet::from([0, 1, 2]));
+        assert_eq!(last_positions(&ast, &mut shared), HashSet::from([6, 7]));
+        let mut follow_map = HashMap::new();
+        follow_positions(&ast, &mut shared, &mut follow_map);
+        assert_eq!(
+            follow_map,
+            HashMap::from([
+                (0, HashSet::from([0, 1, 2])),
+                (1, HashSet::from([0, 1, 2])),
+                (2, HashSet::from([3, 4, 5])),
+                (3, HashSet::from([3, 4, 5])),
+                (4, HashSet::from([3, 4, 5])),
+                (5, HashSet::from([6, 7])),
+            ])
+        );
+
+        let generated_dfa = GlushkovDfa::new(pattern, None).expect("Valid dfa");
+        for string in ["cca", "ccb", "abcbaca", "acbcb"] {
+            assert!(generated_dfa.process(string), "Rejected '{string}'");
+        }
+        for string in ["cc", "ca", "abcab", "ccab"] {
+            assert!(!generated_dfa.process(string), "Accepted '{string}'");
+        }
+    }
+
+    #[test]
+    fn position_numbering_test() {
+        let ast = parse_regex("(a|b*c)*d|e(f|)g").expect("Valid regex");
+        let mut positions = Positions {
+            ranges: HashMap::new(),
+            symbols: HashMap::new(),
+        };
+        let mut counter = 0;
+        assign_positions(&ast, &Alphabet::default(), &mut counter, &mut positions);
+
+        // Every leaf position consumes the character at the same index in the pattern
+        let leaves: Vec<char> = "abcdefg".chars().collect();
+        assert_eq!(counter as usize, leaves.len());
+        for (position, &ch) in leaves.iter().enumerate() {
+            assert_eq!(positions.symbols[&(position as u32)], vec![ch]);
+        }
+
+        let generated_dfa = GlushkovDfa::new("(a|b*c)*d|e(f|)g", None).expect("Valid dfa");
+        for string in ["d", "abcd", "bbcad", "eg", "efg"] {
+            assert!(generated_dfa.process(string), "Rejected '{string}'");
+        }
+        for string in ["", "bd", "ed", "effg", "abc"] {
+            assert!(!generated_dfa.process(string), "Accepted '{string}'");
+        }
+    }
+
+    #[test]
+    fn single_char_start_state_test() {
+        let alphabet = Alphabet::new(&pattern_classes("a").expect("Valid classes"));
+        let ast = parse_regex("a").expect("Valid regex");
+        let nfa = glushkov_construction(ast, &alphabet).expect("Valid nfa");
+        // Position 0 is the `a`, the start state comes right after it
+        assert_eq!(nfa.start_state, 1);
+        assert_eq!(nfa.transitions, HashMap::from([((1, 'a'), vec![0])]));
+        assert_eq!(nfa.accepting_states, HashSet::from([0]));
+
+        let converted_dfa: GlushkovDfa = nfa_to_dfa(nfa, None).expect("No size limit");
+        assert_eq!(converted_dfa.transitions, HashMap::from([((0, 'a'), 1)]));
+        assert_eq!(converted_dfa.accepting_states, HashSet::from([1]));
+
+        let generated_dfa = GlushkovDfa::new("a", None).expect("Valid dfa");
+        assert_eq!(generated_dfa.start_state(), 0);
+        assert!(!generated_dfa.accepting_states.contains(&0));
+        let next_state = generated_dfa.step(0, 'a').expect("Transition on 'a'");
+        assert!(generated_dfa.accepting_states.contains(&next_state));
+        assert!(generated_dfa.process("a"));
+        for string in ["", "aa", "b"] {
+            assert!(!generated_dfa.process(string), "Accepted '{string}'");
+        }
+    }
+
+    #[test]
+    fn deterministic_nfa_to_dfa_test() {
+        let compile = |regex: &str| {
+            let normalised_regex = normalise_regex(regex);
+            let alphabet =
+                Alphabet::new(&pattern_classes(&normalised_regex).expect("Valid classes"));
+            let ast = parse_regex(&normalised_regex).expect("Valid regex");
+            let nfa = glushkov_construction(ast, &alphabet).expect("Valid nfa");
+            nfa_to_dfa::<GlushkovDfa>(nfa, None).expect("No size limit")
+        };
+        for regex in ["(a|b|c)*(ab|cd)e", "[a-f]x|[d-k]y|z*"] {
+            let first = compile(regex);
+            for _ in 0..20 {
+                let dfa = compile(regex);
+                assert_eq!(dfa.transitions, first.transitions, "Failed for '{regex}'");
+                assert_eq!(dfa.accepting_states, first.accepting_states);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_alternation_group_test() {
+        let ast = parse_regex("(|)").expect("Valid regex");
+        assert!(matches!(
+            &ast,
+            RegexAst::Alternation(alternatives)
+                if alternatives.len() == 2
+                    && alternatives
+                        .iter()
+                        .all(|alt| matches!(alt, RegexAst::Concat(elements) if elements.is_empty()))
+        ));
+        assert!(nullable(&ast));
+
+        let generated_dfa = GlushkovDfa::new("(|)", None).expect("Valid dfa");
+        assert!(generated_dfa.transitions.is_empty());
+        assert_eq!(generated_dfa.accepting_states, HashSet::from([0]));
+        assert!(generated_dfa.process(""));
+        assert!(!generated_dfa.process("a"));
+    }
+
+    #[test]
+    fn empty_alternative_test() {
+        let cases = [
+            ("a|", vec!["", "a"], vec!["b", "aa"]),
+            ("|a", vec!["", "a"], vec!["b", "aa"]),
+            ("a||b", vec!["", "a", "b"], vec!["ab", "c"]),
+        ];
+
+        for (regex, accepted, rejected) in cases {
+            let ast = parse_regex(regex).expect("Valid regex");
+            assert!(nullable(&ast), "Expected '{regex}' to be nullable");
+
+            let generated_dfa = GlushkovDfa::new(regex, None).expect("Valid dfa");
+            assert!(generated_dfa.accepting_states.contains(&0));
+            for string in accepted {
+                assert!(
+                    generated_dfa.process(string),
+                    "'{regex}' rejected '{string}'"
+                );
+            }
+            for string in rejected {
+                assert!(
+                    !generated_dfa.process(string),
+                    "'{regex}' accepted '{string}'"
+                );
+            }
+        }
     }
 }
@@ -1,130 +1,178 @@
-use crate::{glushkov::GlushkovDfa, thompson::ThompsonDfa};
-use std::collections::{HashMap, HashSet, VecDeque};
+use crate::{
+    alphabet::Alphabet, antimirov::AntimirovDfa, brzozowski::BrzozowskiDfa,
+    captures::CaptureProgram, dense_table::DenseTable, glushkov::GlushkovDfa,
+    thompson::ThompsonDfa,
+};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 
+mod alphabet;
+mod antimirov;
+mod brzozowski;
+mod captures;
+mod dense_table;
 mod glushkov;
+mod serialise;
+mod state_elimination;
 mod thompson;
 
 trait Dfa {
-    fn new(regex: &str) -> Result<Self, String>
+    /// Compiles `regex`, aborting the subset construction once the DFA is estimated to take
+    /// more than `size_limit` bytes.
+    fn new(regex: &str, size_limit: Option<usize>) -> Result<Self, RegexError>
+    where
+        Self: std::marker::Sized;
+    fn from_parts(
+        transitions: HashMap<(u32, char), u32>,
+        accepting_states: HashSet<u32>,
+        alphabet: Alphabet,
+    ) -> Self
     where
         Self: std::marker::Sized;
     fn get_transitions(&self) -> &HashMap<(u32, char), u32>;
     fn get_accepting_states(&self) -> &HashSet<u32>;
     fn get_transitions_mut(&mut self) -> &mut HashMap<(u32, char), u32>;
     fn get_accepting_states_mut(&mut self) -> &mut HashSet<u32>;
+    fn get_alphabet(&self) -> &Alphabet;
+    fn get_alphabet_mut(&mut self) -> &mut Alphabet;
+
+    /// Returns the state matching starts in.
+    fn start_state(&self) -> u32 {
+        0
+    }
+
+    /// Follows the transition for `c` out of `state`, if there is one.
+    fn step(&self, state: u32, c: char) -> Option<u32> {
+        self.get_transitions()
+            .get(&(state, self.get_alphabet().representative(c)))
+            .copied()
+    }
+
     fn optimise_dfa(&mut self) {
-        let mut partition: HashMap<u32, usize> = HashMap::new();
-        let mut accepting_states_set: HashSet<u32> = self.get_accepting_states().clone();
-        let mut non_accepting_states: HashSet<u32> = HashSet::new();
-        let mut all_states: HashSet<u32> = HashSet::new();
+        // Hopcroft's algorithm on the completed DFA. Missing transitions lead to an implicit
+        // dead state, otherwise states that only differ in which symbols they can consume
+        // could end up merged.
+        const DEAD_STATE: u32 = u32::MAX;
 
-        for &(state, _) in self.get_transitions().keys() {
-            all_states.insert(state);
-            if self.get_accepting_states().contains(&state) {
-                accepting_states_set.insert(state);
-            } else {
-                non_accepting_states.insert(state);
-            }
-        }
+        #[cfg(debug_assertions)]
+        let original_dfa = (
+            self.get_transitions().clone(),
+            self.get_accepting_states().clone(),
+        );
 
-        for state in self.get_accepting_states().iter() {
-            all_states.insert(*state);
+        let start_state = self.start_state();
+        let mut all_states: BTreeSet<u32> = BTreeSet::from([start_state, DEAD_STATE]);
+        let mut alphabet: BTreeSet<char> = BTreeSet::new();
+        for (&(source_state, symbol), &target_state) in self.get_transitions() {
+            all_states.insert(source_state);
+            all_states.insert(target_state);
+            alphabet.insert(symbol);
         }
+        all_states.extend(self.get_accepting_states());
 
-        for state in all_states.iter() {
-            if self.get_accepting_states().contains(state) {
-                partition.insert(*state, 0);
-            } else {
-                partition.insert(*state, 1);
+        let mut inverse_transitions: HashMap<(u32, char), Vec<u32>> = HashMap::new();
+        for &state in &all_states {
+            for &symbol in &alphabet {
+                let target_state = self
+                    .get_transitions()
+                    .get(&(state, symbol))
+                    .copied()
+                    .unwrap_or(DEAD_STATE);
+                inverse_transitions
+                    .entry((target_state, symbol))
+                    .or_default()
+                    .push(state);
             }
         }
 
-        let mut partition_list: Vec<HashSet<u32>> = Vec::new();
-        partition_list.push(accepting_states_set);
-        partition_list.push(non_accepting_states);
+        let (accepting_states_set, non_accepting_states): (BTreeSet<u32>, BTreeSet<u32>) =
+            all_states
+                .iter()
+                .partition(|state| self.get_accepting_states().contains(state));
 
+        let mut partition: HashMap<u32, usize> = HashMap::new();
+        let mut partition_list: Vec<BTreeSet<u32>> = Vec::new();
         let mut worklist: VecDeque<usize> = VecDeque::new();
-        if !partition_list[0].is_empty() {
-            worklist.push_back(0);
-        }
-        if partition_list.len() > 1 && !partition_list[1].is_empty() {
-            worklist.push_back(1);
-        }
+        let mut in_worklist: Vec<bool> = Vec::new();
 
-        while let Some(current_partition_index) = worklist.pop_front() {
-            let mut states_to_check: HashMap<char, HashSet<u32>> = HashMap::new();
-            for (&(source_state, symbol), &target_state) in self.get_transitions() {
-                if partition[&target_state] == current_partition_index {
-                    states_to_check
-                        .entry(symbol)
-                        .or_default()
-                        .insert(source_state);
-                }
+        for states in [accepting_states_set, non_accepting_states] {
+            if states.is_empty() {
+                continue;
+            }
+            let partition_index = partition_list.len();
+            for &state in &states {
+                partition.insert(state, partition_index);
             }
+            partition_list.push(states);
+            worklist.push_back(partition_index);
+            in_worklist.push(true);
+        }
 
-            for (_, states_to_split) in states_to_check.iter() {
-                let mut partitions_to_split: HashSet<usize> = HashSet::new();
+        while let Some(splitter_index) = worklist.pop_front() {
+            in_worklist[splitter_index] = false;
+            let splitter = partition_list[splitter_index].clone();
 
-                for &state in states_to_split.iter() {
-                    let partition_index = partition[&state];
-                    if partition_list[partition_index].len() > 1 {
-                        partitions_to_split.insert(partition_index);
+            for &symbol in &alphabet {
+                let mut states_to_split: HashSet<u32> = HashSet::new();
+                for &target_state in &splitter {
+                    if let Some(source_states) = inverse_transitions.get(&(target_state, symbol)) {
+                        states_to_split.extend(source_states);
                     }
                 }
 
-                for &partition_index_to_split in partitions_to_split.iter() {
-                    let mut intersection: HashSet<u32> = HashSet::new();
-                    let mut difference: HashSet<u32> = HashSet::new();
-
-                    for &state in partition_list[partition_index_to_split].iter() {
-                        if states_to_split.contains(&state) {
-                            intersection.insert(state);
-                        } else {
-                            difference.insert(state);
-                        }
-                    }
-
-                    if !intersection.is_empty() && !difference.is_empty() {
-                        let new_partition_index = partition_list.len();
+                let partitions_to_split: BTreeSet<usize> = states_to_split
+                    .iter()
+                    .map(|state| partition[state])
+                    .collect();
 
-                        for &state in intersection.iter() {
-                            partition.insert(state, new_partition_index);
-                        }
+                for partition_index_to_split in partitions_to_split {
+                    let (intersection, difference): (BTreeSet<u32>, BTreeSet<u32>) = partition_list
+                        [partition_index_to_split]
+                        .iter()
+                        .partition(|state| states_to_split.contains(state));
 
-                        partition_list.push(intersection);
+                    if intersection.is_empty() || difference.is_empty() {
+                        continue;
+                    }
 
-                        for &state in &difference {
-                            partition.insert(state, partition_index_to_split);
-                        }
-                        partition_list[partition_index_to_split] = difference;
+                    let new_partition_index = partition_list.len();
+                    for &state in &intersection {
+                        partition.insert(state, new_partition_index);
+                    }
+                    partition_list.push(intersection);
+                    partition_list[partition_index_to_split] = difference;
+                    in_worklist.push(false);
 
-                        if partition_list[new_partition_index].len()
+                    // A queued partition must have both halves queued, otherwise the smaller
+                    // half is enough to distinguish all states
+                    if in_worklist[partition_index_to_split]
+                        || partition_list[new_partition_index].len()
                             < partition_list[partition_index_to_split].len()
-                        {
-                            worklist.push_back(new_partition_index);
-                        } else {
-                            worklist.push_back(partition_index_to_split);
-                        }
+                    {
+                        worklist.push_back(new_partition_index);
+                        in_worklist[new_partition_index] = true;
+                    } else {
+                        worklist.push_back(partition_index_to_split);
+                        in_worklist[partition_index_to_split] = true;
                     }
                 }
             }
         }
 
-        // Build new transitions and accepting states
+        // Build new transitions and accepting states, dropping states equivalent to the dead
+        // state. The start state keeps its id, the others are numbered by their smallest member.
+        let dead_partition = partition[&DEAD_STATE];
         let mut minimal_transitions: HashMap<(u32, char), u32> = HashMap::new();
         let mut minimal_accepting_states: HashSet<u32> = HashSet::new();
         let mut new_state_map: HashMap<usize, u32> = HashMap::new();
 
-        let mut next_state_id: u32 = 0;
-
-        if let Some(partition_index) = partition.get(&0) {
-            new_state_map.insert(*partition_index, next_state_id);
-            next_state_id += 1;
-        }
-
-        for (_, &partition_index) in partition.iter() {
-            if let std::collections::hash_map::Entry::Vacant(e) =
-                new_state_map.entry(partition_index)
+        new_state_map.insert(partition[&start_state], 0);
+        let mut next_state_id: u32 = 1;
+        for state in &all_states {
+            let partition_index = partition[state];
+            if partition_index != dead_partition
+                && let std::collections::hash_map::Entry::Vacant(e) =
+                    new_state_map.entry(partition_index)
             {
                 e.insert(next_state_id);
                 next_state_id += 1;
@@ -132,18 +180,21 @@ trait Dfa {
         }
 
         for (original_state, &partition_index) in partition.iter() {
-            let new_state_id = new_state_map[&partition_index];
             if self.get_accepting_states().contains(original_state) {
-                minimal_accepting_states.insert(new_state_id);
+                minimal_accepting_states
+                    .insert(swap_start_id(new_state_map[&partition_index], start_state));
             }
         }
 
         for (&(source_state, symbol), &target_state) in self.get_transitions() {
             let source_partition = partition[&source_state];
             let target_partition = partition[&target_state];
+            if source_partition == dead_partition || target_partition == dead_partition {
+                continue;
+            }
 
-            let new_source_state = new_state_map[&source_partition];
-            let new_target_state = new_state_map[&target_partition];
+            let new_source_state = swap_start_id(new_state_map[&source_partition], start_state);
+            let new_target_state = swap_start_id(new_state_map[&target_partition], start_state);
 
             minimal_transitions.insert((new_source_state, symbol), new_target_state);
         }
@@ -151,6 +202,86 @@ trait Dfa {
         // Modify the existing DFA in-place
         *self.get_transitions_mut() = minimal_transitions;
         *self.get_accepting_states_mut() = minimal_accepting_states;
+
+        #[cfg(debug_assertions)]
+        if let Some(witness) = language_difference(
+            (&original_dfa.0, &original_dfa.1, start_state),
+            (
+                self.get_transitions(),
+                self.get_accepting_states(),
+                start_state,
+            ),
+        ) {
+            panic!("Minimisation changed the language, only one DFA accepts {witness:?}");
+        }
+    }
+
+    /// Turns the DFA into one for its language followed by `literal`.
+    ///
+    /// Runs a subset construction over pairs of a state of the original DFA and the set of
+    /// positions reached inside `literal`, so the pattern itself never has to be parsed again.
+    fn concat_literal(&mut self, literal: &str) {
+        let literal: Vec<char> = literal
+            .chars()
+            .map(|c| self.get_alphabet().map_char(c))
+            .collect();
+        let alphabet = self.get_alphabet().with_chars(literal.iter().copied());
+        let symbols: Vec<char> = alphabet.cell_representatives().collect();
+
+        // `None` stands for the original DFA having no transition left
+        type ConcatState = (Option<u32>, BTreeSet<usize>);
+        let enter_literal = |state: Option<u32>, positions: &mut BTreeSet<usize>| {
+            if state.is_some_and(|state| self.get_accepting_states().contains(&state)) {
+                positions.insert(0);
+            }
+        };
+
+        let start_state = self.start_state();
+        let mut start_positions = BTreeSet::new();
+        enter_literal(Some(start_state), &mut start_positions);
+        let start: ConcatState = (Some(start_state), start_positions);
+
+        let mut state_ids: HashMap<ConcatState, u32> = HashMap::from([(start.clone(), 0)]);
+        let mut queue: VecDeque<ConcatState> = VecDeque::from([start]);
+        let mut transitions: HashMap<(u32, char), u32> = HashMap::new();
+        let mut accepting_states: HashSet<u32> = HashSet::new();
+
+        while let Some((state, positions)) = queue.pop_front() {
+            let state_id = swap_start_id(state_ids[&(state, positions.clone())], start_state);
+            if positions.contains(&literal.len()) {
+                accepting_states.insert(state_id);
+            }
+
+            for &symbol in &symbols {
+                let next_state = state.and_then(|state| self.step(state, symbol));
+                let mut next_positions: BTreeSet<usize> = positions
+                    .iter()
+                    .filter(|&&position| literal.get(position) == Some(&symbol))
+                    .map(|position| position + 1)
+                    .collect();
+                enter_literal(next_state, &mut next_positions);
+                if next_state.is_none() && next_positions.is_empty() {
+                    continue;
+                }
+
+                let next = (next_state, next_positions);
+                let next_id = match state_ids.get(&next) {
+                    Some(&id) => id,
+                    None => {
+                        let id = state_ids.len() as u32;
+                        state_ids.insert(next.clone(), id);
+                        queue.push_back(next);
+                        id
+                    }
+                };
+                transitions.insert((state_id, symbol), swap_start_id(next_id, start_state));
+            }
+        }
+
+        *self.get_transitions_mut() = transitions;
+        *self.get_accepting_states_mut() = accepting_states;
+        *self.get_alphabet_mut() = alphabet;
+        self.optimise_dfa();
     }
 
     /// Determines if the given input string exactly matches the regex pattern.
@@ -171,9 +302,9 @@ trait Dfa {
     /// the pattern from start to finish, equivalent to "^(a|b)*$".
     ///
     fn process(&self, input: &str) -> bool {
-        let mut current_state = 0;
+        let mut current_state = self.start_state();
         for c in input.chars() {
-            if let Some(&next_state) = self.get_transitions().get(&(current_state, c)) {
+            if let Some(next_state) = self.step(current_state, c) {
                 current_state = next_state;
             } else {
                 return false;
@@ -182,431 +313,6845 @@ trait Dfa {
         self.get_accepting_states().contains(&current_state)
     }
 
-    fn find_first_match<'a>(&self, text: &'a str) -> Option<&'a str> {
-        let mut start_pos = 0;
-        while start_pos < text.len() {
-            let mut current_state = 0;
-            let mut match_start = None;
-            let mut match_end = None;
-
-            for (i, c) in text.chars().enumerate().skip(start_pos) {
-                if let Some(&next_state) = self.get_transitions().get(&(current_state, c)) {
-                    current_state = next_state;
-                    match_start = match_start.or(Some(i));
+    /// Encodes the DFA into a byte key that is equal for DFAs with the same structure.
+    ///
+    /// States are renumbered in breadth first order from the start state, following the
+    /// transitions sorted by symbol, so the key does not depend on how a construction happened
+    /// to number its states. Every transition is encoded with the full cell of characters it
+    /// consumes. Two minimal DFAs get the same key exactly if they accept the same language
+    /// over the same alphabet cells.
+    fn canonical_key(&self) -> Vec<u8> {
+        let mut outgoing: HashMap<u32, Vec<(char, u32)>> = HashMap::new();
+        for (&(source_state, symbol), &target_state) in self.get_transitions() {
+            outgoing
+                .entry(source_state)
+                .or_default()
+                .push((symbol, target_state));
+        }
+        for transitions in outgoing.values_mut() {
+            transitions.sort_unstable();
+        }
 
-                    if self.get_accepting_states().contains(&current_state) {
-                        match_end = Some(i)
-                    }
-                } else {
-                    break;
+        let mut canonical_ids: HashMap<u32, u32> = HashMap::from([(self.start_state(), 0)]);
+        let mut order: Vec<u32> = vec![self.start_state()];
+        let mut index = 0;
+        while index < order.len() {
+            for &(_, target_state) in outgoing.get(&order[index]).into_iter().flatten() {
+                if let std::collections::hash_map::Entry::Vacant(e) =
+                    canonical_ids.entry(target_state)
+                {
+                    e.insert(order.len() as u32);
+                    order.push(target_state);
                 }
             }
+            index += 1;
+        }
+
+        let mut key = Vec::new();
+        key.extend((order.len() as u32).to_le_bytes());
+        for state in &order {
+            key.push(self.get_accepting_states().contains(state) as u8);
+            let transitions = outgoing.get(state).map_or(&[][..], Vec::as_slice);
+            key.extend((transitions.len() as u32).to_le_bytes());
+            for &(symbol, target_state) in transitions {
+                let (cell_start, cell_end) = self.get_alphabet().cell(symbol);
+                key.extend((cell_start as u32).to_le_bytes());
+                key.extend((cell_end as u32).to_le_bytes());
+                key.extend(canonical_ids[&target_state].to_le_bytes());
+            }
+        }
+        key
+    }
+
+    /// Returns the states visited while consuming `input`, beginning with the start state and
+    /// stopping at the first character without a transition.
+    fn trace(&self, input: &str) -> Vec<u32> {
+        let mut states = vec![self.start_state()];
+        for c in input.chars() {
+            match self.step(states[states.len() - 1], c) {
+                Some(next_state) => states.push(next_state),
+                None => break,
+            }
+        }
+        states
+    }
+
+    /// Renders the DFA in the graphviz DOT format.
+    ///
+    /// The states and edges traversed while consuming `trace_input` are highlighted. A character
+    /// without a transition is drawn as a dashed edge into a separate dead end node.
+    fn to_dot(&self, trace_input: Option<&str>) -> String {
+        use std::fmt::Write;
+
+        let mut traced_states: HashSet<u32> = HashSet::new();
+        let mut traced_edges: HashSet<(u32, char)> = HashSet::new();
+        let mut dead_end: Option<(u32, char)> = None;
+        if let Some(input) = trace_input {
+            let states = self.trace(input);
+            traced_states.extend(&states);
+            let mut chars = input.chars();
+            for (pair, c) in states.windows(2).zip(chars.by_ref()) {
+                traced_edges.insert((pair[0], self.get_alphabet().representative(c)));
+            }
+            dead_end = chars.next().map(|c| (states[states.len() - 1], c));
+        }
+
+        let mut all_states: BTreeSet<u32> = BTreeSet::from([self.start_state()]);
+        for (&(source_state, _), &target_state) in self.get_transitions() {
+            all_states.insert(source_state);
+            all_states.insert(target_state);
+        }
+        all_states.extend(self.get_accepting_states());
+
+        let mut transitions: Vec<(&(u32, char), &u32)> = self.get_transitions().iter().collect();
+        transitions.sort();
+
+        let highlight = |traced: bool| {
+            if traced {
+                ", color=red, penwidth=2"
+            } else {
+                ""
+            }
+        };
+        let label = |c: char| match c {
+            '"' | '\\' => format!("\\{c}"),
+            _ => c.to_string(),
+        };
 
-            if let (Some(start), Some(end)) = (match_start, match_end) {
-                return Some(&text[start..=end]);
+        let mut dot = String::from("digraph dfa {\n    rankdir=LR;\n    start [shape=point];\n");
+        let _ = writeln!(
+            dot,
+            "    start -> {}{};",
+            self.start_state(),
+            if trace_input.is_some() {
+                " [color=red, penwidth=2]"
+            } else {
+                ""
+            }
+        );
+        for state in all_states {
+            let shape = if self.get_accepting_states().contains(&state) {
+                "doublecircle"
             } else {
-                start_pos += 1;
+                "circle"
+            };
+            let traced = traced_states.contains(&state);
+            let _ = writeln!(dot, "    {state} [shape={shape}{}];", highlight(traced));
+        }
+        for (&(source_state, symbol), target_state) in transitions {
+            let traced = traced_edges.contains(&(source_state, symbol));
+            let _ = writeln!(
+                dot,
+                "    {source_state} -> {target_state} [label=\"{}\"{}];",
+                label(symbol),
+                highlight(traced)
+            );
+        }
+        if let Some((state, c)) = dead_end {
+            dot.push_str("    dead_end [shape=octagon, label=\"dead end\", color=red];\n");
+            let _ = writeln!(
+                dot,
+                "    {state} -> dead_end [label=\"{}\", style=dashed, color=red];",
+                label(c)
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Returns the end of the longest match starting at `start_pos` over `chars`, which yields
+    /// every char together with its start and end position.
+    fn longest_match_in(
+        &self,
+        start_pos: usize,
+        chars: &mut dyn Iterator<Item = (usize, char, usize)>,
+    ) -> Option<usize> {
+        let mut current_state = self.start_state();
+        let mut longest_end = self
+            .get_accepting_states()
+            .contains(&current_state)
+            .then_some(start_pos);
+
+        for (_, c, end) in chars {
+            let Some(next_state) = self.step(current_state, c) else {
+                break;
+            };
+            current_state = next_state;
+            if self.get_accepting_states().contains(&current_state) {
+                longest_end = Some(end);
+            }
+        }
+        longest_end
+    }
+
+    /// Returns the leftmost start at or after the byte offset `start_pos` from which the whole
+    /// rest of `text` is a match.
+    fn leftmost_suffix_match(&self, text: &str, start_pos: usize) -> Option<usize> {
+        self.leftmost_suffix_match_in(text.len(), &mut byte_positions(text, start_pos))
+    }
+
+    /// Returns the leftmost start from which the rest of `chars` is a match, `end_pos` is the
+    /// position after the last char.
+    fn leftmost_suffix_match_in(
+        &self,
+        end_pos: usize,
+        chars: &mut dyn Iterator<Item = (usize, char, usize)>,
+    ) -> Option<usize> {
+        // Same thread simulation as `find_at`, but only threads alive at the end can match
+        let mut threads: Vec<(u32, usize)> = Vec::new();
+        let mut next_threads: Vec<(u32, usize)> = Vec::new();
+        let mut seen_states: HashSet<u32> = HashSet::new();
+
+        for (i, c, _) in chars {
+            threads.push((self.start_state(), i));
+
+            seen_states.clear();
+            for &(state, start) in &threads {
+                if let Some(next_state) = self.step(state, c)
+                    && seen_states.insert(next_state)
+                {
+                    next_threads.push((next_state, start));
+                }
+            }
+            std::mem::swap(&mut threads, &mut next_threads);
+            next_threads.clear();
+        }
+        threads.push((self.start_state(), end_pos));
+
+        threads
+            .iter()
+            .find(|(state, _)| self.get_accepting_states().contains(state))
+            .map(|&(_, start)| start)
+    }
+
+    /// Finds the leftmost-longest match starting at or after the byte offset `start_pos`
+    /// and returns its byte span. The match is empty if the pattern matches the empty string
+    /// and nothing longer starts at `start_pos`.
+    fn find_at(&self, text: &str, start_pos: usize) -> Option<(usize, usize)> {
+        self.find_in(start_pos, &mut byte_positions(text, start_pos))
+    }
+
+    /// Finds the leftmost-longest match starting at or after `start_pos` over `chars`, which
+    /// yields every char together with its start and end position.
+    fn find_in(
+        &self,
+        start_pos: usize,
+        chars: &mut dyn Iterator<Item = (usize, char, usize)>,
+    ) -> Option<(usize, usize)> {
+        leftmost_longest_match(
+            self.start_state(),
+            start_pos,
+            chars,
+            &mut HashSet::new(),
+            |state| self.get_accepting_states().contains(&state),
+            |state, c| self.step(state, c),
+        )
+    }
+
+    /// Returns the states from which an accepting state can be reached.
+    fn live_states(&self) -> HashSet<u32> {
+        let mut live_states = self.get_accepting_states().clone();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (&(source_state, _), target_state) in self.get_transitions() {
+                if live_states.contains(target_state) && live_states.insert(source_state) {
+                    changed = true;
+                }
+            }
+        }
+        live_states
+    }
+
+    fn min_match_len(&self) -> Option<usize> {
+        let start_state = self.start_state();
+        let mut distance: HashMap<u32, usize> = HashMap::from([(start_state, 0)]);
+        let mut queue: VecDeque<u32> = VecDeque::from([start_state]);
+
+        while let Some(state) = queue.pop_front() {
+            if self.get_accepting_states().contains(&state) {
+                return Some(distance[&state]);
+            }
+            for (&(source_state, _), &target_state) in self.get_transitions() {
+                if source_state == state && !distance.contains_key(&target_state) {
+                    distance.insert(target_state, distance[&state] + 1);
+                    queue.push_back(target_state);
+                }
             }
         }
 
         None
     }
 
-    fn find_all_matches<'a>(&self, input: &'a str) -> Vec<&'a str> {
-        let mut matches: Vec<&str> = Vec::new();
+    fn max_match_len(&self) -> Option<usize> {
+        let live_states = self.live_states();
+        let start_state = self.start_state();
+        if !live_states.contains(&start_state) {
+            return None;
+        }
 
-        let mut start_pos = 0;
-        while start_pos < input.len() {
-            let mut current_state = 0;
-            let mut match_start: Option<usize> = None;
-            let mut match_end: Option<usize> = None;
+        // Only live states reachable from the start matter for the length of a match
+        let mut edges: HashMap<u32, Vec<u32>> = HashMap::new();
+        let mut in_degree: HashMap<u32, usize> = HashMap::from([(start_state, 0)]);
+        let mut queue: VecDeque<u32> = VecDeque::from([start_state]);
+        while let Some(state) = queue.pop_front() {
+            for (&(source_state, _), &target_state) in self.get_transitions() {
+                if source_state != state || !live_states.contains(&target_state) {
+                    continue;
+                }
+                edges.entry(state).or_default().push(target_state);
+                if !in_degree.contains_key(&target_state) {
+                    queue.push_back(target_state);
+                }
+                *in_degree.entry(target_state).or_default() += 1;
+            }
+        }
 
-            for (i, c) in input.chars().enumerate().skip(start_pos) {
-                if let Some(&next_state) = self.get_transitions().get(&(current_state, c)) {
-                    current_state = next_state;
-                    match_start = match_start.or(Some(start_pos));
+        // A live cycle through the start state allows arbitrarily long matches
+        if in_degree[&start_state] > 0 {
+            return None;
+        }
 
-                    if self.get_accepting_states().contains(&current_state) {
-                        match_end = Some(i);
-                    }
-                } else {
-                    break;
+        // Longest path in topological order, a leftover state means there is a cycle
+        let mut longest_path: HashMap<u32, usize> = HashMap::from([(start_state, 0)]);
+        let mut ready: Vec<u32> = vec![start_state];
+        let mut processed = 0;
+        let mut max_len = 0;
+        while let Some(state) = ready.pop() {
+            processed += 1;
+            let length = longest_path[&state];
+            if self.get_accepting_states().contains(&state) {
+                max_len = max_len.max(length);
+            }
+            for &target_state in edges.get(&state).into_iter().flatten() {
+                let target_length = longest_path.entry(target_state).or_default();
+                *target_length = (*target_length).max(length + 1);
+                let degree = in_degree.get_mut(&target_state).unwrap();
+                *degree -= 1;
+                if *degree == 0 {
+                    ready.push(target_state);
+                }
+            }
+        }
+
+        (processed == in_degree.len()).then_some(max_len)
+    }
+
+    fn sample_random(&self, rng_seed: u64, max_len: usize) -> Option<String> {
+        // Shortest distance (in transitions) from each state to any accepting state
+        let mut distance_to_accept: HashMap<u32, usize> = HashMap::new();
+        let mut queue: VecDeque<u32> = VecDeque::new();
+        for &state in self.get_accepting_states() {
+            distance_to_accept.insert(state, 0);
+            queue.push_back(state);
+        }
+        while let Some(state) = queue.pop_front() {
+            let distance = distance_to_accept[&state];
+            for (&(source_state, _), &target_state) in self.get_transitions() {
+                if target_state == state && !distance_to_accept.contains_key(&source_state) {
+                    distance_to_accept.insert(source_state, distance + 1);
+                    queue.push_back(source_state);
                 }
             }
+        }
+
+        if distance_to_accept
+            .get(&self.start_state())
+            .is_none_or(|&distance| distance > max_len)
+        {
+            return None;
+        }
+
+        let mut rng = SplitMix64::new(rng_seed);
+        let mut current_state = self.start_state();
+        let mut sample = String::new();
+        let mut remaining = max_len;
+
+        loop {
+            // Only follow transitions that can still reach an accepting state within budget
+            let mut candidates: Vec<(char, u32)> = self
+                .get_transitions()
+                .iter()
+                .filter(|&(&(source_state, _), target_state)| {
+                    source_state == current_state
+                        && distance_to_accept
+                            .get(target_state)
+                            .is_some_and(|&distance| distance < remaining)
+                })
+                .map(|(&(_, symbol), &target_state)| (symbol, target_state))
+                .collect();
+            candidates.sort_unstable();
+
+            if self.get_accepting_states().contains(&current_state)
+                && (candidates.is_empty() || rng.next_below(candidates.len() + 1) == 0)
+            {
+                return Some(sample);
+            }
 
-            if let (Some(start), Some(end)) = (match_start, match_end) {
-                matches.push(&input[start..=end]);
-                start_pos = end + 1;
+            let (symbol, next_state) = candidates[rng.next_below(candidates.len())];
+            // Any char of the symbol's cell takes the transition, unless the char map sends it
+            // to another cell
+            let c = random_cell_member(&mut rng, self.get_alphabet().cell(symbol));
+            sample.push(if self.get_alphabet().representative(c) == symbol {
+                c
             } else {
-                start_pos += 1;
+                symbol
+            });
+            current_state = next_state;
+            remaining -= 1;
+        }
+    }
+}
+
+/// Returns a char drawn uniformly from the cell `start..=end`.
+fn random_cell_member(rng: &mut SplitMix64, (start, end): (char, char)) -> char {
+    // A cell spanning the surrogates has a gap there, they are not chars
+    const SURROGATE_COUNT: u32 = 0x800;
+    let gap = if (start as u32) < 0xD800 && (end as u32) > 0xDFFF {
+        SURROGATE_COUNT
+    } else {
+        0
+    };
+    let len = end as u32 - start as u32 + 1 - gap;
+    let mut codepoint = start as u32 + rng.next_below(len as usize) as u32;
+    if gap > 0 && codepoint >= 0xD800 {
+        codepoint += gap;
+    }
+    char::from_u32(codepoint).expect("Surrogates are skipped")
+}
+
+/// Swaps the ids `0` and `start_state`, so a DFA numbered from a start state of `0` can be
+/// renumbered to start at `start_state`.
+fn swap_start_id(state: u32, start_state: u32) -> u32 {
+    if state == 0 {
+        start_state
+    } else if state == start_state {
+        0
+    } else {
+        state
+    }
+}
+
+/// Returns the shortest string accepted by exactly one of two DFAs, each given by its
+/// transitions, accepting states and start state.
+///
+/// Walks both DFAs in lockstep over every symbol either of them uses, a missing transition
+/// leads to a dead state. Used to check minimisation in debug builds.
+#[cfg(debug_assertions)]
+fn language_difference(
+    dfa: (&HashMap<(u32, char), u32>, &HashSet<u32>, u32),
+    other_dfa: (&HashMap<(u32, char), u32>, &HashSet<u32>, u32),
+) -> Option<String> {
+    // A state of each DFA, `None` being the dead state
+    type StatePair = (Option<u32>, Option<u32>);
+
+    let symbols: BTreeSet<char> = dfa
+        .0
+        .keys()
+        .chain(other_dfa.0.keys())
+        .map(|&(_, symbol)| symbol)
+        .collect();
+    let accepts = |accepting_states: &HashSet<u32>, state: Option<u32>| {
+        state.is_some_and(|state| accepting_states.contains(&state))
+    };
+
+    let start_pair = (Some(dfa.2), Some(other_dfa.2));
+    let mut visited: HashSet<StatePair> = HashSet::from([start_pair]);
+    let mut queue: VecDeque<(StatePair, String)> = VecDeque::from([(start_pair, String::new())]);
+    while let Some(((state, other_state), input)) = queue.pop_front() {
+        if accepts(dfa.1, state) != accepts(other_dfa.1, other_state) {
+            return Some(input);
+        }
+
+        for &symbol in &symbols {
+            let next_pair = (
+                state.and_then(|state| dfa.0.get(&(state, symbol)).copied()),
+                other_state.and_then(|state| other_dfa.0.get(&(state, symbol)).copied()),
+            );
+            if next_pair != (None, None) && visited.insert(next_pair) {
+                let mut next_input = input.clone();
+                next_input.push(symbol);
+                queue.push_back((next_pair, next_input));
             }
         }
+    }
+    None
+}
 
-        matches
+/// Minimal SplitMix64 generator so random sampling is reproducible without extra dependencies.
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn next_below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
     }
 }
 
+/// The construction used to build the DFA of a `Regex`.
+///
+/// All of them match the same strings. Glushkov is the default, its construction avoids the
+/// epsilon transitions of Thompson and compiles patterns with `.` considerably faster.
+/// Brzozowski builds the DFA directly from the derivatives of the pattern without an NFA in
+/// between. Antimirov builds a small NFA from partial derivatives, which pays off for
+/// alternation heavy patterns. Like Glushkov both reject non ASCII literals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ConstructionType {
     Thompson,
+    #[default]
     Glushkov,
+    Brzozowski,
+    Antimirov,
+}
+
+/// How byte matching treats input that is not valid UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Utf8Mode {
+    /// Every byte is matched as the char of the same value, so a byte above `0x7F` never
+    /// matches a pattern over ASCII. A pattern that can match other chars needs the input to
+    /// be valid UTF-8 instead.
+    #[default]
+    Ascii,
+    /// Invalid sequences are replaced by `U+FFFD` before matching, as
+    /// `String::from_utf8_lossy` does.
+    Lossy,
+}
+
+/// How the transitions of a compiled regex are stored, see `Regex::representation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Representation {
+    /// A table indexed by state and char, chosen when the alphabet of the pattern is ASCII
+    /// and the DFA is small.
+    DenseTable,
+    /// A map keyed on the state and the representative of the char's alphabet cell, used
+    /// for large or sparse alphabets.
+    HashMap,
+}
+
+/// A suspicious but valid construct found by `Regex::new_with_warnings`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Warning {
+    /// A star is applied to a subpattern that already matches the empty string, as in
+    /// `(a*)*`, so it can be simplified.
+    RedundantNestedQuantifier,
+    /// An alternative is empty, as in `a|` or `(|b)`, a `?` states the intent more clearly.
+    EmptyAlternative,
+    /// The pattern matches the empty string, so it matches at every position of a text.
+    MatchesEmptyString,
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            Warning::RedundantNestedQuantifier => "redundant nested quantifier",
+            Warning::EmptyAlternative => "empty alternative",
+            Warning::MatchesEmptyString => "pattern matches empty string",
+        };
+        f.write_str(message)
+    }
+}
+
+/// The reason a pattern could not be compiled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegexError {
+    /// The pattern is empty.
+    EmptyPattern,
+    /// The pattern contains a raw NUL character, which `\u{0}` matches instead.
+    NulInPattern,
+    /// A `(` is never closed or a `)` has no matching `(`.
+    UnbalancedParens,
+    /// A quantifier has no atom to repeat, as in `*a` or `(|+)`.
+    DanglingQuantifier,
+    /// A quantifier follows another quantifier, as in `a+*` or the possessive `a*+`. Only a
+    /// single lazy `?` may follow, the repeated part has to be grouped otherwise, as in `(a+)*`.
+    StackedQuantifier(char),
+    /// The pattern ends with a `\` that escapes nothing.
+    TrailingEscape,
+    /// A `(?` starts neither a comment nor a named group, or a group name is empty or reused.
+    InvalidGroup,
+    /// A bounded repetition is unterminated or its counts are malformed, as in `a{2,1}`.
+    InvalidRepetition,
+    /// A bracket class is never closed.
+    UnclosedClass,
+    /// An end of a class range is not a single character, as in `[\p{L}-z]`.
+    InvalidRangeEnd(String),
+    /// A class range ends before it starts, as in `[z-a]`.
+    ReversedRange { start: char, end: char },
+    /// A `\p{` is missing its closing `}`.
+    UnterminatedProperty,
+    /// A `\p{...}` names a property that is not supported.
+    UnknownProperty(String),
+    /// A `\u{` is missing its closing `}`.
+    UnterminatedCodepoint,
+    /// A `\u{...}` is not the codepoint of a char, e.g. a surrogate.
+    InvalidCodepoint(String),
+    /// The construction cannot handle the character, e.g. a non ASCII literal under Glushkov,
+    /// Brzozowski or Antimirov.
+    UnexpectedChar(char),
+    /// An anchored pattern has a top level alternation that is not grouped.
+    AnchoredAlternation,
+    /// A `^` or `$` is not at the very start or end of the pattern.
+    MisplacedAnchor,
+    /// `Regex::union_all` was given an anchored pattern.
+    CombinedAnchors,
+    /// A bounded repetition count is above the repetition limit.
+    RepetitionLimitExceeded { count: usize, limit: usize },
+    /// The compiled automaton exceeds the size limit, given in bytes.
+    StateLimitExceeded { limit: usize },
+    /// A class range is too large to apply a char map to each of its characters.
+    CharMapRangeTooLarge { start: char, end: char },
+    /// The pattern at `index` of a list of patterns failed to compile.
+    InPattern {
+        index: usize,
+        error: Box<RegexError>,
+    },
+    /// Any other malformed pattern the constructions cannot parse.
+    Syntax(String),
+    /// Bytes passed to `Regex::from_bytes` are corrupt or were written by an incompatible
+    /// version.
+    BadSerialFormat,
+    /// Input to `Regex::is_match_bytes` is not valid UTF-8 from the byte at `offset` on.
+    InvalidUtf8 { offset: usize },
+}
+
+impl std::fmt::Display for RegexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegexError::EmptyPattern => f.write_str("Empty pattern"),
+            RegexError::NulInPattern => {
+                f.write_str("NUL character in pattern, use \\u{0} to match it")
+            }
+            RegexError::UnbalancedParens => f.write_str("Unbalanced parentheses"),
+            RegexError::DanglingQuantifier => f.write_str("Quantifier without an atom to repeat"),
+            RegexError::StackedQuantifier(quantifier) => write!(
+                f,
+                "Quantifier {quantifier} follows another quantifier, group the repeated part instead"
+            ),
+            RegexError::TrailingEscape => f.write_str("Invalid escape sequence"),
+            RegexError::InvalidGroup => f.write_str("Invalid group syntax"),
+            RegexError::InvalidRepetition => f.write_str("Invalid repetition counts"),
+            RegexError::UnclosedClass => f.write_str("Unterminated or empty class"),
+            RegexError::InvalidRangeEnd(member) => {
+                write!(f, "Invalid range end in class: {member}")
+            }
+            RegexError::ReversedRange { start, end } => {
+                write!(f, "Reversed range in class: {start}-{end}")
+            }
+            RegexError::UnterminatedProperty => f.write_str("Unterminated unicode property"),
+            RegexError::UnknownProperty(name) => write!(f, "Unknown unicode property: {name}"),
+            RegexError::UnterminatedCodepoint => f.write_str("Unterminated unicode escape"),
+            RegexError::InvalidCodepoint(hex) => write!(f, "Invalid codepoint: {hex}"),
+            RegexError::UnexpectedChar(c) => write!(f, "Unexpected character: {c}"),
+            RegexError::AnchoredAlternation => {
+                f.write_str("Anchors around a top level alternation need a group, e.g. ^(a|b)$")
+            }
+            RegexError::MisplacedAnchor => {
+                f.write_str("Anchors are only supported at the start and end of the pattern")
+            }
+            RegexError::CombinedAnchors => f.write_str("Anchors cannot be combined"),
+            RegexError::RepetitionLimitExceeded { count, limit } => {
+                write!(f, "Repetition count {count} exceeds the limit of {limit}")
+            }
+            RegexError::StateLimitExceeded { limit } => write!(
+                f,
+                "The compiled automaton exceeds the size limit of {limit} bytes"
+            ),
+            RegexError::CharMapRangeTooLarge { start, end } => {
+                write!(
+                    f,
+                    "Class range too large to apply a char map: {start}-{end}"
+                )
+            }
+            RegexError::InPattern { index, error } => write!(f, "Pattern {index}: {error}"),
+            RegexError::Syntax(message) => f.write_str(message),
+            RegexError::BadSerialFormat => f.write_str("Invalid or incompatible serialised regex"),
+            RegexError::InvalidUtf8 { offset } => {
+                write!(f, "Invalid UTF-8 at byte offset {offset}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegexError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RegexError::InPattern { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
 }
 
+#[derive(Clone)]
 enum DfaType {
     Thompson(ThompsonDfa),
     Glushkov(GlushkovDfa),
+    Brzozowski(BrzozowskiDfa),
+    Antimirov(AntimirovDfa),
 }
 
+#[derive(Clone)]
 pub struct Regex {
     dfa: DfaType,
+    // Literal following a leading `.*`, searched for directly instead of walking the DFA
+    dot_star_literal: Option<String>,
+    // Literal every match contains, used to skip text that cannot contain a match
+    required_literal: Option<String>,
+    min_match_len: Option<usize>,
+    max_match_len: Option<usize>,
+    // Representatives that have a transition, text with any other char cannot match
+    symbols: HashSet<char>,
+    // Whether matches have to start at the beginning or end at the end of the text
+    anchored_start: bool,
+    anchored_end: bool,
+    capture_names: Vec<Option<String>>,
+    // Reports the group spans of a match, `None` if the regex was not compiled from a pattern
+    capture_program: Option<CaptureProgram>,
+    // Table used by `is_match` and unanchored searches instead of the map of the DFA over ASCII
+    // alphabets
+    dense_table: Option<DenseTable>,
+    #[cfg(feature = "debug")]
+    normalised_pattern: Option<String>,
 }
 
 impl Regex {
-    pub fn new(pattern: &str, construction: ConstructionType) -> Result<Self, String> {
-        let dfa_type = match construction {
-            ConstructionType::Thompson => DfaType::Thompson(ThompsonDfa::new(pattern)?),
-            ConstructionType::Glushkov => DfaType::Glushkov(GlushkovDfa::new(pattern)?),
-        };
-        Ok(Regex { dfa: dfa_type })
+    pub fn new(pattern: &str, construction: ConstructionType) -> Result<Self, RegexError> {
+        Regex::compile(pattern, construction, DEFAULT_REPETITION_LIMIT, None)
     }
 
-    /// Determines if the provided `text` is an exact match for the regex pattern.
-    ///
-    /// This method interprets the regex pattern as though it is bracketed by start (`^`)
-    /// and end (`$`) anchors, requiring the entire `text` to conform to the pattern.
+    /// Compiles the regex like `new` and also collects warnings about constructs that are
+    /// valid but probably not intended, handy to lint generated patterns.
     ///
     /// # Parameters
     ///
-    /// - `text`: A string slice that represents the text to be verified against the regex.
+    /// - `pattern`: The regex pattern to compile.
+    /// - `construction`: The construction type used to build the DFA.
     ///
     /// # Returns
     ///
-    /// Returns `true` if the `text` completely matches the regex pattern encompassed by implicit
-    /// anchors, otherwise returns `false`.
+    /// Returns the `Regex` together with its warnings, each kind reported at most once, or
+    /// the error of `new` if the pattern is invalid.
     ///
     /// # Example
     ///
     /// ```rust
-    /// use regex_engine::{Regex, ConstructionType};
+    /// use regex_engine::{Regex, ConstructionType, Warning};
     ///
-    /// let regex = Regex::new("(a|b)*", ConstructionType::Thompson).expect("Valied regex");
-    /// assert!(regex.is_match("abba"));
-    /// assert!(!regex.is_match("abc"));
+    /// let (regex, warnings) =
+    ///     Regex::new_with_warnings("x|", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert!(regex.is_match("x"));
+    /// assert_eq!(warnings, vec![Warning::EmptyAlternative, Warning::MatchesEmptyString]);
     /// ```
-    pub fn is_match(&self, text: &str) -> bool {
-        match &self.dfa {
-            DfaType::Thompson(dfa) => dfa.process(text),
-            DfaType::Glushkov(dfa) => dfa.process(text),
+    pub fn new_with_warnings(
+        pattern: &str,
+        construction: ConstructionType,
+    ) -> Result<(Self, Vec<Warning>), RegexError> {
+        let regex = Regex::new(pattern, construction)?;
+        let expanded = expand_quotes(pattern);
+        let (core, _, _) = Regex::anchors_stripped_pattern(&expanded);
+
+        let mut warnings = Vec::new();
+        // Patterns the Glushkov parser rejects, like non-ASCII literals, skip this check
+        if glushkov::has_redundant_star(core).unwrap_or(false) {
+            warnings.push(Warning::RedundantNestedQuantifier);
+        }
+        if has_empty_alternative(core) {
+            warnings.push(Warning::EmptyAlternative);
         }
+        if regex.dfa().process("") {
+            warnings.push(Warning::MatchesEmptyString);
+        }
+        Ok((regex, warnings))
     }
 
-    /// Searches for the first occurrence of a sequence in `text` that matches the regex pattern.
+    fn compile(
+        pattern: &str,
+        construction: ConstructionType,
+        repetition_limit: usize,
+        size_limit: Option<usize>,
+    ) -> Result<Self, RegexError> {
+        let pattern: &str = &expand_quotes(pattern);
+        check_repetition_limit(pattern, repetition_limit)?;
+        let (pattern, anchored_start, anchored_end) = Regex::anchors_stripped_pattern(pattern);
+        if (anchored_start || anchored_end) && has_top_level_alternation(pattern) {
+            return Err(RegexError::AnchoredAlternation);
+        }
+        if has_inner_anchor(pattern) {
+            return Err(RegexError::MisplacedAnchor);
+        }
+        let dfa_type = match construction {
+            ConstructionType::Thompson => DfaType::Thompson(ThompsonDfa::new(pattern, size_limit)?),
+            ConstructionType::Glushkov => DfaType::Glushkov(GlushkovDfa::new(pattern, size_limit)?),
+            ConstructionType::Brzozowski => {
+                DfaType::Brzozowski(BrzozowskiDfa::new(pattern, size_limit)?)
+            }
+            ConstructionType::Antimirov => {
+                DfaType::Antimirov(AntimirovDfa::new(pattern, size_limit)?)
+            }
+        };
+        let mut regex = Regex::from_dfa(dfa_type, dot_star_literal(pattern));
+        let normalised_pattern = normalise_regex(pattern);
+        regex.required_literal = required_literal(&normalised_pattern);
+        regex.capture_names = capture_names(pattern);
+        regex.capture_program = Some(CaptureProgram::new(pattern, ".")?);
+        regex.anchored_start = anchored_start;
+        regex.anchored_end = anchored_end;
+        #[cfg(feature = "debug")]
+        {
+            regex.normalised_pattern = Some(normalised_pattern);
+        }
+        Ok(regex)
+    }
+
+    fn from_dfa(dfa_type: DfaType, dot_star_literal: Option<String>) -> Self {
+        let mut regex = Regex {
+            dfa: dfa_type,
+            dot_star_literal,
+            required_literal: None,
+            min_match_len: None,
+            max_match_len: None,
+            symbols: HashSet::new(),
+            anchored_start: false,
+            anchored_end: false,
+            capture_names: vec![None],
+            capture_program: None,
+            dense_table: None,
+            #[cfg(feature = "debug")]
+            normalised_pattern: None,
+        };
+        regex.dense_table = DenseTable::new(regex.dfa());
+        regex.min_match_len = regex.dfa().min_match_len();
+        regex.max_match_len = regex.dfa().max_match_len();
+        regex.symbols = regex
+            .dfa()
+            .get_transitions()
+            .keys()
+            .map(|&(_, symbol)| symbol)
+            .collect();
+        regex
+    }
+
+    /// Builds a regex matching exactly the strings in `accept`.
     ///
-    /// This method locates and returns the first substring of `text` that matches the regex,
-    /// if such a substring exists.
+    /// The examples are inserted into a trie, which is already a DFA for the finite language,
+    /// and the trie is then minimised so common suffixes share their states as well. The result
+    /// uses the default construction type.
     ///
     /// # Parameters
     ///
-    /// - `text`: A string slice in which to search for the regex pattern.
+    /// - `accept`: The strings the regex has to match, every other string is rejected.
     ///
     /// # Returns
     ///
-    /// Returns an `Option<&str>` which contains the first matching substring if a match is found,
-    /// or `None` if no match occurs.
+    /// Returns a `Regex` whose language is exactly `accept`.
     ///
     /// # Example
     ///
     /// ```rust
-    /// use regex_engine::{Regex, ConstructionType};
+    /// use regex_engine::Regex;
     ///
-    /// let regex = Regex::new("ab+", ConstructionType::Thompson).expect("Valied regex");
-    /// if let Some(matched) = regex.find("aabbcc") {
-    ///     println!("Found: {}", matched);
-    /// }
-    /// // Output: Found: abb
+    /// let regex = Regex::from_examples(&["cat", "cart", "dog"]);
+    /// assert!(regex.is_match("cart"));
+    /// assert!(!regex.is_match("car"));
+    /// assert!(regex.is_finite_language());
     /// ```
-    pub fn find<'a>(&self, text: &'a str) -> Option<&'a str> {
-        match &self.dfa {
-            DfaType::Thompson(dfa) => dfa.find_first_match(text),
-            DfaType::Glushkov(dfa) => dfa.find_first_match(text),
-        }
-    }
+    pub fn from_examples(accept: &[&str]) -> Regex {
+        let mut transitions: HashMap<(u32, char), u32> = HashMap::new();
+        let mut accepting_states: HashSet<u32> = HashSet::new();
+        let mut next_state: u32 = 1;
 
-    pub fn findall<'a>(&self, text: &'a str) -> Vec<&'a str> {
-        match &self.dfa {
-            DfaType::Thompson(dfa) => dfa.find_all_matches(text),
-            DfaType::Glushkov(dfa) => dfa.find_all_matches(text),
+        for example in accept {
+            let mut current_state = 0;
+            for c in example.chars() {
+                current_state = *transitions.entry((current_state, c)).or_insert_with(|| {
+                    next_state += 1;
+                    next_state - 1
+                });
+            }
+            accepting_states.insert(current_state);
         }
+
+        let alphabet =
+            Alphabet::default().with_chars(accept.iter().flat_map(|example| example.chars()));
+        let mut dfa = GlushkovDfa::from_parts(transitions, accepting_states, alphabet);
+        dfa.optimise_dfa();
+        Regex::from_dfa(DfaType::Glushkov(dfa), None)
     }
-}
 
-pub fn is_valid_regex(regex: &str) -> bool {
+    /// Compiles every pattern and combines them into a single regex matching the union of
+    /// their languages.
+    ///
+    /// The automata are joined by a product construction and minimised again, so matching
+    /// walks one DFA instead of one per pattern as a `RegexSet` does. Use a `RegexSet` to find
+    /// out which of the patterns match.
+    ///
+    /// # Parameters
+    ///
+    /// - `patterns`: The regex patterns whose languages are combined.
+    /// - `construction`: The construction type used for every pattern.
+    ///
+    /// # Returns
+    ///
+    /// Returns the combined `Regex`, or the error of the first pattern that fails to compile
+    /// together with its index. An empty `patterns` slice results in a regex matching nothing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::union_all(&["ab+", "c*d"], ConstructionType::Glushkov)
+    ///     .expect("Valid regexes");
+    /// assert!(regex.is_match("abb"));
+    /// assert!(regex.is_match("ccd"));
+    /// assert!(!regex.is_match("abd"));
+    /// ```
+    pub fn union_all(
+        patterns: &[&str],
+        construction: ConstructionType,
+    ) -> Result<Regex, RegexError> {
+        let regexes = patterns
+            .iter()
+            .enumerate()
+            .map(|(index, pattern)| {
+                match Regex::new(pattern, construction) {
+                    Ok(regex) if regex.anchored_start || regex.anchored_end => {
+                        Err(RegexError::CombinedAnchors)
+                    }
+                    result => result,
+                }
+                .map_err(|error| RegexError::InPattern {
+                    index,
+                    error: Box::new(error),
+                })
+            })
+            .collect::<Result<Vec<Regex>, RegexError>>()?;
+        let dfas: Vec<&dyn Dfa> = regexes.iter().map(|regex| regex.dfa()).collect();
+        let alphabets: Vec<&Alphabet> = dfas.iter().map(|dfa| dfa.get_alphabet()).collect();
+        let alphabet = Alphabet::refinement(&alphabets);
+        let symbols: Vec<char> = alphabet.cell_representatives().collect();
+
+        // Every state of the product is the tuple of the states of all DFAs, `None` once a DFA
+        // is stuck
+        let start: Vec<Option<u32>> = dfas.iter().map(|dfa| Some(dfa.start_state())).collect();
+        let mut state_ids: HashMap<Vec<Option<u32>>, u32> = HashMap::from([(start.clone(), 0)]);
+        let mut queue: VecDeque<Vec<Option<u32>>> = VecDeque::from([start]);
+        let mut transitions: HashMap<(u32, char), u32> = HashMap::new();
+        let mut accepting_states: HashSet<u32> = HashSet::new();
+
+        while let Some(states) = queue.pop_front() {
+            let state_id = state_ids[&states];
+            if dfas.iter().zip(&states).any(|(dfa, state)| {
+                state.is_some_and(|state| dfa.get_accepting_states().contains(&state))
+            }) {
+                accepting_states.insert(state_id);
+            }
+
+            for &symbol in &symbols {
+                let next_states: Vec<Option<u32>> = dfas
+                    .iter()
+                    .zip(&states)
+                    .map(|(dfa, state)| state.and_then(|state| dfa.step(state, symbol)))
+                    .collect();
+                if next_states.iter().all(Option::is_none) {
+                    continue;
+                }
+
+                let next_id = state_ids.len() as u32;
+                let target_id = *state_ids.entry(next_states.clone()).or_insert_with(|| {
+                    queue.push_back(next_states);
+                    next_id
+                });
+                transitions.insert((state_id, symbol), target_id);
+            }
+        }
+
+        let mut dfa = GlushkovDfa::from_parts(transitions, accepting_states, alphabet);
+        dfa.optimise_dfa();
+        Ok(Regex::from_dfa(DfaType::Glushkov(dfa), None))
+    }
+
+    fn dfa(&self) -> &dyn Dfa {
+        match &self.dfa {
+            DfaType::Thompson(dfa) => dfa,
+            DfaType::Glushkov(dfa) => dfa,
+            DfaType::Brzozowski(dfa) => dfa,
+            DfaType::Antimirov(dfa) => dfa,
+        }
+    }
+
+    fn dfa_mut(&mut self) -> &mut dyn Dfa {
+        match &mut self.dfa {
+            DfaType::Thompson(dfa) => dfa,
+            DfaType::Glushkov(dfa) => dfa,
+            DfaType::Brzozowski(dfa) => dfa,
+            DfaType::Antimirov(dfa) => dfa,
+        }
+    }
+
+    /// Returns a regex matching this regex followed by the literal string `literal`.
+    ///
+    /// The compiled automaton is extended directly, so the original pattern is not parsed
+    /// again. Every char of `literal` is matched as is, operators included.
+    ///
+    /// # Parameters
+    ///
+    /// - `literal`: The text that has to follow a match of this regex.
+    ///
+    /// # Returns
+    ///
+    /// Returns a new `Regex` using the same construction type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("(a|b)", ConstructionType::Glushkov).expect("Valid regex");
+    /// let regex = regex.then_literal("x*");
+    /// assert!(regex.is_match("ax*"));
+    /// assert!(!regex.is_match("axx"));
+    /// ```
+    pub fn then_literal(&self, literal: &str) -> Regex {
+        let mut dfa_type = self.dfa.clone();
+        match &mut dfa_type {
+            DfaType::Thompson(dfa) => dfa.concat_literal(literal),
+            DfaType::Glushkov(dfa) => dfa.concat_literal(literal),
+            DfaType::Brzozowski(dfa) => dfa.concat_literal(literal),
+            DfaType::Antimirov(dfa) => dfa.concat_literal(literal),
+        }
+        let dot_star_literal = self
+            .dot_star_literal
+            .as_ref()
+            .map(|prefix| format!("{prefix}{literal}"));
+        let mut regex = Regex::from_dfa(dfa_type, dot_star_literal);
+        regex.anchored_start = self.anchored_start;
+        regex.anchored_end = self.anchored_end;
+        regex.capture_names = self.capture_names.clone();
+        regex.capture_program = self.capture_program.clone().map(|mut program| {
+            program.then_literal(literal);
+            program
+        });
+        // With a char map the literal fast paths stay disabled
+        if !self.dfa().get_alphabet().has_char_map() {
+            regex.required_literal = match &self.required_literal {
+                Some(required) if required.chars().count() >= literal.chars().count() => {
+                    Some(required.clone())
+                }
+                _ => (!literal.is_empty()).then(|| literal.to_string()),
+            };
+        }
+        regex
+    }
+
+    /// Returns a regex whose matches have to start at the beginning of the text.
+    ///
+    /// Only the search methods such as `find`, `findall` and `longest_match_at` are affected,
+    /// so a match at a later start position is never reported, and `is_match` always
+    /// requires the entire text to match. The compiled automaton is reused as is.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.find("xab"), Some("ab"));
+    /// assert_eq!(regex.prepend_anchor().find("xab"), None);
+    /// ```
+    pub fn prepend_anchor(&self) -> Regex {
+        Regex {
+            anchored_start: true,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a regex whose matches have to end at the end of the text.
+    ///
+    /// Only the search methods such as `find`, `findall` and `longest_match_at` are affected,
+    /// so a match that stops short of the end is never reported, and `is_match` always
+    /// requires the entire text to match. The compiled automaton is reused as is.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.find("abx"), Some("ab"));
+    /// assert_eq!(regex.append_anchor().find("abx"), None);
+    /// ```
+    pub fn append_anchor(&self) -> Regex {
+        Regex {
+            anchored_end: true,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the end of every match starting exactly at the byte offset `start_pos`, in
+    /// ascending order.
+    ///
+    /// Every search for a match at a given position goes through here, so the anchors are
+    /// checked in one place: with a start anchor nothing matches after offset 0 and with an
+    /// end anchor only a match reaching the end of `text` is reported.
+    fn match_ends_at<'a>(
+        &'a self,
+        text: &'a str,
+        start_pos: usize,
+    ) -> impl Iterator<Item = usize> + 'a {
+        let dfa = self.dfa();
+        let mut current_state = (!self.anchored_start || start_pos == 0).then(|| dfa.start_state());
+        let mut chars = text[start_pos..].chars();
+        let mut end = start_pos;
+
+        std::iter::from_fn(move || {
+            while let Some(state) = current_state {
+                let state_end = end;
+                current_state = chars.next().and_then(|c| {
+                    end += c.len_utf8();
+                    dfa.step(state, c)
+                });
+                if dfa.get_accepting_states().contains(&state)
+                    && (!self.anchored_end || state_end == text.len())
+                {
+                    return Some(state_end);
+                }
+            }
+            None
+        })
+    }
+
+    /// Finds the leftmost-longest match starting at or after the byte offset `start_pos`.
+    fn find_at(&self, text: &str, start_pos: usize) -> Option<(usize, usize)> {
+        if self.anchored_start {
+            return self
+                .match_ends_at(text, start_pos)
+                .last()
+                .map(|end| (start_pos, end));
+        }
+        if self.anchored_end {
+            return self
+                .dfa()
+                .leftmost_suffix_match(text, start_pos)
+                .map(|start| (start, text.len()));
+        }
+
+        match &self.dot_star_literal {
+            Some(literal) => find_dot_star_literal(text, start_pos, literal),
+            None => {
+                let start_pos = match &self.required_literal {
+                    Some(literal) => prefilter_start(text, start_pos, literal, self.max_match_len)?,
+                    None => start_pos,
+                };
+                match &self.dense_table {
+                    Some(table) => table.find_at(self.dfa().get_alphabet(), text, start_pos),
+                    None => self.dfa().find_at(text, start_pos),
+                }
+            }
+        }
+    }
+
+    /// Returns an iterator over the byte spans of all non overlapping matches.
+    fn spans<'r, 't>(&'r self, text: &'t str) -> Spans<'r, 't> {
+        Spans {
+            regex: self,
+            text,
+            start_pos: 0,
+            last_end: None,
+        }
+    }
+
+    /// Returns the byte spans of all non overlapping matches from left to right.
+    fn find_all_spans(&self, text: &str) -> Vec<(usize, usize)> {
+        self.spans(text).collect()
+    }
+
+    /// Strips a leading `^` and a trailing `$` anchor from `pattern`.
+    ///
+    /// This is a pure string helper for handing the core of a pattern to other tools, no
+    /// automaton is built. An escaped trailing `\$` is part of the pattern and kept.
+    ///
+    /// # Parameters
+    ///
+    /// - `pattern`: The regex pattern to inspect.
+    ///
+    /// # Returns
+    ///
+    /// Returns the pattern without anchors, whether a start anchor was present and whether
+    /// an end anchor was present.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::Regex;
+    ///
+    /// assert_eq!(Regex::anchors_stripped_pattern("^abc$"), ("abc", true, true));
+    /// assert_eq!(Regex::anchors_stripped_pattern("abc"), ("abc", false, false));
+    /// ```
+    pub fn anchors_stripped_pattern(pattern: &str) -> (&str, bool, bool) {
+        let (core, has_start_anchor) = match pattern.strip_prefix('^') {
+            Some(rest) => (rest, true),
+            None => (pattern, false),
+        };
+
+        let escaping_backslashes = core
+            .strip_suffix('$')
+            .map(|rest| rest.chars().rev().take_while(|&c| c == '\\').count());
+        match escaping_backslashes {
+            Some(count) if count % 2 == 0 => (&core[..core.len() - 1], has_start_anchor, true),
+            _ => (core, has_start_anchor, false),
+        }
+    }
+
+    /// Determines if the provided `text` is an exact match for the regex pattern.
+    ///
+    /// This method interprets the regex pattern as though it is bracketed by start (`^`)
+    /// and end (`$`) anchors, requiring the entire `text` to conform to the pattern.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice that represents the text to be verified against the regex.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the `text` completely matches the regex pattern encompassed by implicit
+    /// anchors, otherwise returns `false`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("(a|b)*", ConstructionType::Thompson).expect("Valied regex");
+    /// assert!(regex.is_match("abba"));
+    /// assert!(!regex.is_match("abc"));
+    /// ```
+    pub fn is_match(&self, text: &str) -> bool {
+        // A char takes 1 to 4 bytes, so the byte length bounds the length in chars
+        let too_short = self
+            .min_match_len
+            .is_none_or(|min_len| text.len() < min_len);
+        let too_long = self
+            .max_match_len
+            .is_some_and(|max_len| text.len() > max_len.saturating_mul(4));
+        if too_short || too_long {
+            return false;
+        }
+
+        let alphabet = self.dfa().get_alphabet();
+        if let Some(dense_table) = &self.dense_table {
+            return dense_table.process(alphabet, text);
+        }
+
+        // Rejects text with a char the DFA never consumes without walking it
+        if !text
+            .chars()
+            .all(|c| self.symbols.contains(&alphabet.representative(c)))
+        {
+            return false;
+        }
+
+        self.dfa().process(text)
+    }
+
+    /// Determines if `line` is matched as a whole, like `grep -x`.
+    ///
+    /// This is `is_match` for text read line by line, a single trailing `\n` or `\r\n` line
+    /// terminator is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab+", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert!(regex.matches_whole_line("abb\r\n"));
+    /// assert!(!regex.matches_whole_line("abbc"));
+    /// ```
+    pub fn matches_whole_line(&self, line: &str) -> bool {
+        let line = line.strip_suffix('\n').unwrap_or(line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        self.is_match(line)
+    }
+
+    /// Returns the lines of `text` that contain a match, like `grep`.
+    ///
+    /// Lines are split as by `str::lines`, so they do not include their `\n` or `\r\n`
+    /// terminator and a final line terminator does not start another line.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab+", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.find_lines("xab\nba\nabb\n"), vec!["xab", "abb"]);
+    /// ```
+    pub fn find_lines<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        text.lines()
+            .filter(|line| self.find_at(line, 0).is_some())
+            .collect()
+    }
+
+    /// Determines if `bytes` is an exact match for the regex pattern, see [`Regex::is_match`].
+    ///
+    /// # Parameters
+    ///
+    /// - `bytes`: The input, which does not need to be valid UTF-8.
+    /// - `mode`: How bytes are turned into chars, see [`Utf8Mode`].
+    ///
+    /// # Returns
+    ///
+    /// Returns whether `bytes` matches, or `RegexError::InvalidUtf8` with the offset of the
+    /// first invalid byte in [`Utf8Mode::Ascii`] if the pattern can match characters outside
+    /// of ASCII and `bytes` is not valid UTF-8.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType, RegexError, Utf8Mode};
+    ///
+    /// let regex = Regex::new("(a|b)*", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.is_match_bytes(b"abba", Utf8Mode::Ascii), Ok(true));
+    /// assert_eq!(regex.is_match_bytes(b"ab\xffba", Utf8Mode::Ascii), Ok(false));
+    ///
+    /// let regex = Regex::new(r"\p{L}*", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(
+    ///     regex.is_match_bytes(b"ab\xffba", Utf8Mode::Ascii),
+    ///     Err(RegexError::InvalidUtf8 { offset: 2 })
+    /// );
+    /// ```
+    pub fn is_match_bytes(&self, bytes: &[u8], mode: Utf8Mode) -> Result<bool, RegexError> {
+        match mode {
+            Utf8Mode::Ascii => {
+                let dfa = self.dfa();
+                let alphabet = dfa.get_alphabet();
+                if dfa
+                    .get_transitions()
+                    .keys()
+                    .any(|&(_, representative)| !alphabet.cell(representative).1.is_ascii())
+                {
+                    let text =
+                        std::str::from_utf8(bytes).map_err(|error| RegexError::InvalidUtf8 {
+                            offset: error.valid_up_to(),
+                        })?;
+                    return Ok(self.is_match(text));
+                }
+                Ok(bytes.is_ascii()
+                    && std::str::from_utf8(bytes).is_ok_and(|text| self.is_match(text)))
+            }
+            Utf8Mode::Lossy => Ok(self.is_match(&String::from_utf8_lossy(bytes))),
+        }
+    }
+
+    /// Returns the pattern as it was fed to the construction after `normalise_regex`, which
+    /// expands e.g. `a+` to `aa*`.
+    ///
+    /// Only available with the `debug` feature. Returns `None` for regexes that were not
+    /// compiled from a pattern, such as the ones built by `from_examples` or `then_literal`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("a+", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.normalised_pattern(), Some("aa*"));
+    /// ```
+    #[cfg(feature = "debug")]
+    pub fn normalised_pattern(&self) -> Option<&str> {
+        self.normalised_pattern.as_deref()
+    }
+
+    /// Returns the length in chars of the shortest string the regex matches, or `None` if it
+    /// matches nothing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab(c|de)*", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.min_match_len(), Some(2));
+    /// ```
+    pub fn min_match_len(&self) -> Option<usize> {
+        self.min_match_len
+    }
+
+    /// Returns the length in chars of the longest string the regex matches, or `None` if
+    /// matches can be arbitrarily long or the regex matches nothing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab(c|de)", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.max_match_len(), Some(4));
+    /// let regex = Regex::new("ab(c|de)*", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.max_match_len(), None);
+    /// ```
+    pub fn max_match_len(&self) -> Option<usize> {
+        self.max_match_len
+    }
+
+    /// Returns the name of every capture group, `None` for unnamed groups.
+    ///
+    /// Index `0` stands for the whole match and is always `None`, the groups follow in the
+    /// order of their opening parenthesis. Groups are named with `(?P<name>...)`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("(?P<year>ab)-(c)", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(
+    ///     regex.capture_names(),
+    ///     vec![None, Some("year".to_string()), None]
+    /// );
+    /// ```
+    pub fn capture_names(&self) -> Vec<Option<String>> {
+        self.capture_names.clone()
+    }
+
+    /// Determines whether the regex matches only finitely many strings.
+    ///
+    /// The language is infinite exactly if a cycle of the DFA is reachable from the start and
+    /// can reach an accepting state, which is also when `max_match_len` has no bound. A regex
+    /// matching nothing has a finite language.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("(a|b)c", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert!(regex.is_finite_language());
+    /// let regex = Regex::new("a*", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert!(!regex.is_finite_language());
+    /// ```
+    pub fn is_finite_language(&self) -> bool {
+        self.min_match_len.is_none() || self.max_match_len.is_some()
+    }
+
+    /// Searches for the first occurrence of a sequence in `text` that matches the regex pattern.
+    ///
+    /// This method locates and returns the first substring of `text` that matches the regex,
+    /// if such a substring exists.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice in which to search for the regex pattern.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `Option<&str>` which contains the first matching substring if a match is found,
+    /// or `None` if no match occurs.
+    /// A pattern matching the empty string always finds a match, which is empty if nothing longer
+    /// matches at the start of `text`, also for an empty `text`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab+", ConstructionType::Thompson).expect("Valied regex");
+    /// if let Some(matched) = regex.find("aabbcc") {
+    ///     println!("Found: {}", matched);
+    /// }
+    /// // Output: Found: abb
+    /// ```
+    pub fn find<'a>(&self, text: &'a str) -> Option<&'a str> {
+        self.find_at(text, 0).map(|(start, end)| &text[start..end])
+    }
+
+    /// Searches for the first match like `find`, but keeps where it was found in `text`.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice in which to search for the regex pattern.
+    ///
+    /// # Returns
+    ///
+    /// Returns the leftmost-longest `Match` with its byte offsets in `text`, or `None` if
+    /// nothing matches.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("b+", ConstructionType::Glushkov).expect("Valid regex");
+    /// let found = regex.find_match("äbbc").expect("Match");
+    /// assert_eq!((found.start(), found.end()), (2, 4));
+    /// assert_eq!(found.as_str(), "bb");
+    /// ```
+    pub fn find_match<'t>(&self, text: &'t str) -> Option<Match<'t>> {
+        self.find_at(text, 0)
+            .map(|(start, end)| Match { text, start, end })
+    }
+
+    /// Searches for the first match like `find_match` and reports where each capture group
+    /// matched within it.
+    ///
+    /// The match itself is found by the DFA as usual. A tagged simulation of the pattern then
+    /// runs over just the matched text to place the groups, preferring earlier alternatives
+    /// and longer repetitions. A group inside a repetition reports its last iteration.
+    ///
+    /// Regexes that were not compiled from a pattern, like those of `from_examples`,
+    /// `union_all` or `from_bytes`, only report group 0.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice in which to search for the regex pattern.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Captures` of the leftmost-longest match, or `None` if nothing matches.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("(a+)(b+)", ConstructionType::Glushkov).expect("Valid regex");
+    /// let captures = regex.captures("xaaabb").expect("Match");
+    /// assert_eq!(captures.get(0).map(|m| m.as_str()), Some("aaabb"));
+    /// assert_eq!(captures.get(1).map(|m| m.as_str()), Some("aaa"));
+    /// assert_eq!(captures.get(2).map(|m| m.as_str()), Some("bb"));
+    /// ```
+    pub fn captures<'t>(&self, text: &'t str) -> Option<Captures<'t>> {
+        let (start, end) = self.find_at(text, 0)?;
+        let spans = self
+            .capture_program
+            .as_ref()
+            .and_then(|program| program.group_spans(self.dfa().get_alphabet(), text, start, end))
+            .unwrap_or_else(|| {
+                let mut spans = vec![None; self.capture_names.len()];
+                spans[0] = Some((start, end));
+                spans
+            });
+        Some(Captures {
+            text,
+            spans,
+            names: self.capture_names.clone(),
+        })
+    }
+
+    /// Determines if the characters in `chars` are an exact match for the regex pattern.
+    ///
+    /// This is `is_match` for text that is already split into chars, so it does not have to be
+    /// collected into a `String` first.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab*", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert!(regex.is_match_chars(&['a', 'b', 'b']));
+    /// assert!(!regex.is_match_chars(&['b']));
+    /// ```
+    pub fn is_match_chars(&self, chars: &[char]) -> bool {
+        let too_short = self
+            .min_match_len
+            .is_none_or(|min_len| chars.len() < min_len);
+        let too_long = self
+            .max_match_len
+            .is_some_and(|max_len| chars.len() > max_len);
+        if too_short || too_long {
+            return false;
+        }
+
+        let dfa = self.dfa();
+        chars
+            .iter()
+            .try_fold(dfa.start_state(), |state, &c| dfa.step(state, c))
+            .is_some_and(|state| dfa.get_accepting_states().contains(&state))
+    }
+
+    /// Searches for the first match in `chars` like `find` does in a string slice.
+    ///
+    /// # Parameters
+    ///
+    /// - `chars`: The characters in which to search for the regex pattern.
+    ///
+    /// # Returns
+    ///
+    /// Returns the start and end index of the leftmost-longest match in `chars`, counted in
+    /// chars and not in bytes, or `None` if nothing matches.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("bc", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.find_chars(&['a', 'é', 'b', 'c']), Some((2, 4)));
+    /// ```
+    pub fn find_chars(&self, chars: &[char]) -> Option<(usize, usize)> {
+        let dfa = self.dfa();
+        let mut positions = chars.iter().enumerate().map(|(i, &c)| (i, c, i + 1));
+        match (self.anchored_start, self.anchored_end) {
+            (true, true) => self.is_match_chars(chars).then_some((0, chars.len())),
+            (true, false) => dfa.longest_match_in(0, &mut positions).map(|end| (0, end)),
+            (false, true) => dfa
+                .leftmost_suffix_match_in(chars.len(), &mut positions)
+                .map(|start| (start, chars.len())),
+            (false, false) => dfa.find_in(0, &mut positions),
+        }
+    }
+
+    /// Returns the characters that can follow `prefix` without leaving the DFA.
+    ///
+    /// The DFA is walked over `prefix` from the start, the characters with a transition out of
+    /// the state it ends in are returned. This tells an editor what may legally be typed next.
+    /// Characters matched through a unicode property class are all listed individually.
+    ///
+    /// # Parameters
+    ///
+    /// - `prefix`: The text typed so far.
+    ///
+    /// # Returns
+    ///
+    /// Returns the sorted characters that have a transition, or an empty `Vec` if `prefix`
+    /// already ran into a missing transition.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("a(b|c)", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.valid_next_chars("a"), vec!['b', 'c']);
+    /// assert!(regex.valid_next_chars("ab").is_empty());
+    /// ```
+    pub fn valid_next_chars(&self, prefix: &str) -> Vec<char> {
+        let dfa = self.dfa();
+        let states = dfa.trace(prefix);
+        if states.len() <= prefix.chars().count() {
+            return Vec::new();
+        }
+
+        let current_state = states[states.len() - 1];
+        let mut next_chars: BTreeSet<char> = BTreeSet::new();
+        for &(source_state, symbol) in dfa.get_transitions().keys() {
+            if source_state == current_state {
+                let (cell_start, cell_end) = dfa.get_alphabet().cell(symbol);
+                next_chars.extend(cell_start..=cell_end);
+            }
+        }
+        next_chars.into_iter().collect()
+    }
+
+    /// Determines whether `prefix` can still be extended to a full match.
+    ///
+    /// This is the case if walking the DFA over `prefix` ends in a state from which an
+    /// accepting state is reachable, so an input field can reject a keystroke as soon as it
+    /// makes a match impossible.
+    ///
+    /// # Parameters
+    ///
+    /// - `prefix`: The text typed so far.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if some continuation of `prefix`, possibly the empty one, is matched.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("abc", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert!(regex.is_prefix_acceptable("ab"));
+    /// assert!(!regex.is_prefix_acceptable("ax"));
+    /// ```
+    pub fn is_prefix_acceptable(&self, prefix: &str) -> bool {
+        let dfa = self.dfa();
+        let states = dfa.trace(prefix);
+        states.len() > prefix.chars().count()
+            && dfa.live_states().contains(&states[states.len() - 1])
+    }
+
+    /// Returns a regex with the same language whose DFA is complete.
+    ///
+    /// A compiled DFA only keeps the transitions that can still lead to a match, a missing
+    /// transition means the input is rejected. Completing it adds a single dead state that
+    /// every missing transition leads to and that never leaves itself. Only the cells of the
+    /// alphabet get a transition, characters that do not occur in the pattern still have none.
+    ///
+    /// # Returns
+    ///
+    /// Returns the completed `Regex`, matching exactly like `self`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.dead_state(), None);
+    /// let completed = regex.complete_dfa();
+    /// assert!(completed.dead_state().is_some());
+    /// assert!(completed.is_match("ab"));
+    /// ```
+    pub fn complete_dfa(&self) -> Regex {
+        let mut regex = self.clone();
+        let dfa = regex.dfa_mut();
+        let representatives: Vec<char> = dfa.get_alphabet().cell_representatives().collect();
+        let mut states: BTreeSet<u32> = dfa.get_accepting_states().iter().copied().collect();
+        states.insert(dfa.start_state());
+        for (&(source_state, _), &target_state) in dfa.get_transitions() {
+            states.insert(source_state);
+            states.insert(target_state);
+        }
+
+        let dead_state = states.last().map_or(0, |&state| state + 1);
+        let transitions = dfa.get_transitions_mut();
+        let mut needs_dead_state = false;
+        for &state in &states {
+            for &representative in &representatives {
+                transitions
+                    .entry((state, representative))
+                    .or_insert_with(|| {
+                        needs_dead_state = true;
+                        dead_state
+                    });
+            }
+        }
+        if needs_dead_state {
+            for &representative in &representatives {
+                transitions.insert((dead_state, representative), dead_state);
+            }
+        }
+        regex.dense_table = DenseTable::new(regex.dfa());
+        regex
+    }
+
+    /// Returns the dead state of a complete DFA, from which no input leads to a match.
+    ///
+    /// Matching can stop as soon as it enters this state. A DFA that is not complete, like
+    /// the one of a freshly compiled regex, has its dead state only implicitly as missing
+    /// transitions, see `complete_dfa`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the id of the dead state, or `None` if the DFA is not complete or every state
+    /// can still reach a match.
+    pub fn dead_state(&self) -> Option<u32> {
+        let dfa = self.dfa();
+        let representatives: Vec<char> = dfa.get_alphabet().cell_representatives().collect();
+        let mut states: BTreeSet<u32> = BTreeSet::from([dfa.start_state()]);
+        for (&(source_state, _), &target_state) in dfa.get_transitions() {
+            states.insert(source_state);
+            states.insert(target_state);
+        }
+
+        let is_complete = states.iter().all(|&state| {
+            representatives
+                .iter()
+                .all(|&representative| dfa.step(state, representative).is_some())
+        });
+        if !is_complete {
+            return None;
+        }
+        let live_states = dfa.live_states();
+        states
+            .into_iter()
+            .find(|state| !live_states.contains(state))
+    }
+
+    /// Finds the longest match that starts exactly at the byte offset `start_pos`.
+    ///
+    /// Unlike `find`, which searches forward for the leftmost match, the match has to begin
+    /// at `start_pos`. This is the maximal munch primitive of lexers, which take the longest
+    /// token at the current position.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice in which to search for the regex pattern.
+    /// - `start_pos`: The byte offset the match has to start at, it must lie on a char
+    ///   boundary.
+    ///
+    /// # Returns
+    ///
+    /// Returns the longest match, which starts at `start_pos`, or `None` if no match starts
+    /// there.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("a+", ConstructionType::Glushkov).expect("Valid regex");
+    /// let longest = regex.longest_match_at("aaab", 0).expect("Match at 0");
+    /// assert_eq!((longest.start(), longest.end(), longest.as_str()), (0, 3, "aaa"));
+    /// assert_eq!(regex.longest_match_at("aaab", 3), None);
+    /// ```
+    pub fn longest_match_at<'t>(&self, text: &'t str, start_pos: usize) -> Option<Match<'t>> {
+        let end = self.match_ends_at(text, start_pos).last()?;
+        Some(Match {
+            text,
+            start: start_pos,
+            end,
+        })
+    }
+
+    /// Finds the longest match that starts exactly at the byte offset `start_pos` like
+    /// `longest_match_at`, but measures it in chars instead of bytes.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice in which to search for the regex pattern.
+    /// - `start_pos`: The byte offset the match has to start at, it must lie on a char
+    ///   boundary.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of chars in the longest match, or `None` if no match starts at
+    /// `start_pos`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("\\u{E9}+", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.longest_match_at("ééx", 0).map(|m| m.end()), Some(4));
+    /// assert_eq!(regex.char_len_match_at("ééx", 0), Some(2));
+    /// ```
+    pub fn char_len_match_at(&self, text: &str, start_pos: usize) -> Option<usize> {
+        let longest = self.longest_match_at(text, start_pos)?;
+        Some(longest.as_str().chars().count())
+    }
+
+    /// Lazily splits `text` into consecutive longest matches, each starting right where the
+    /// previous one ended, like a lexer does with its tokens.
+    ///
+    /// The iteration stops at the end of `text` or as soon as no non-empty match starts at
+    /// the current position, `findall_anchored` also reports the rest that was not consumed.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice to split into matches from its start.
+    ///
+    /// # Returns
+    ///
+    /// Returns an iterator over the consecutive matches from the start of `text`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("a+|b", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.find_iter_anchored("aabaxa").collect::<Vec<_>>(), vec!["aa", "b", "a"]);
+    /// ```
+    pub fn find_iter_anchored<'t>(&self, text: &'t str) -> impl Iterator<Item = &'t str> {
+        let mut start_pos = 0;
+        std::iter::from_fn(move || {
+            let token = self
+                .longest_match_at(text, start_pos)
+                .filter(|token| token.end() > start_pos)?;
+            start_pos = token.end();
+            Some(token.as_str())
+        })
+    }
+
+    /// Splits `text` into consecutive longest matches like `find_iter_anchored` and returns
+    /// them together with the rest of `text` that could not be split.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice to split into matches from its start.
+    ///
+    /// # Returns
+    ///
+    /// Returns the matches in order and the unconsumed remainder, which is empty if all of
+    /// `text` was split into matches.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("[0-9]+|[a-z]+", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.findall_anchored("ab12 cd"), (vec!["ab", "12"], " cd"));
+    /// ```
+    pub fn findall_anchored<'t>(&self, text: &'t str) -> (Vec<&'t str>, &'t str) {
+        let tokens: Vec<&str> = self.find_iter_anchored(text).collect();
+        let consumed: usize = tokens.iter().map(|token| token.len()).sum();
+        (tokens, &text[consumed..])
+    }
+
+    /// Finds the shortest match that starts exactly at the byte offset `start_pos`.
+    ///
+    /// Unlike `find`, which extends every match as far as possible, the scan stops at the
+    /// first accepting state. This suits lexers that want the shortest token at the current
+    /// position.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice in which to search for the regex pattern.
+    /// - `start_pos`: The byte offset the match has to start at, it must lie on a char
+    ///   boundary.
+    ///
+    /// # Returns
+    ///
+    /// Returns the byte offset where the shortest match ends, or `None` if no match starts at
+    /// `start_pos`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("a+", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.shortest_match_at("baaa", 1), Some(2));
+    /// assert_eq!(regex.shortest_match_at("baaa", 0), None);
+    /// ```
+    pub fn shortest_match_at(&self, text: &str, start_pos: usize) -> Option<usize> {
+        self.match_ends_at(text, start_pos).next()
+    }
+
+    /// Returns the length of every prefix of `text` that the regex matches.
+    ///
+    /// The DFA is walked once over `text`, recording each position where it is in an
+    /// accepting state. A tokeniser can use this to see all the tokens it could take at the
+    /// start of `text` instead of only the longest one.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice whose prefixes are matched.
+    ///
+    /// # Returns
+    ///
+    /// Returns the matching prefix lengths in bytes in ascending order, starting with `0` if
+    /// the regex matches the empty string.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("a|aa|aaa", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.accepting_prefix_lengths("aaa"), vec![1, 2, 3]);
+    /// ```
+    pub fn accepting_prefix_lengths(&self, text: &str) -> Vec<usize> {
+        self.match_ends_at(text, 0).collect()
+    }
+
+    /// Finds all non overlapping matches of the regex in the given text.
+    ///
+    /// The matches are ordered strictly left to right by their start offset. Every match is
+    /// the leftmost-longest one starting at or after the end of the previous match, so the
+    /// result is the same for both construction types.
+    ///
+    /// Patterns that match the empty string also produce empty matches, following the
+    /// `regex` crate: an empty match directly after the previous match is skipped, so `a*`
+    /// over `"abc"` yields `"a"`, `""` before the `c` and `""` at the end.
+    ///
+    /// An anchored pattern matches at most once: with `^` the only match starts at offset 0
+    /// and with `$` the only match is the leftmost one ending at the end of `text`.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice in which to search for the regex pattern.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Vec<&str>` with the matching substrings in the order they occur in `text`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab*", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.findall("abb a cab"), vec!["abb", "a", "ab"]);
+    /// ```
+    pub fn findall<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        self.find_all_spans(text)
+            .into_iter()
+            .map(|(start, end)| &text[start..end])
+            .collect()
+    }
+
+    /// Lazily iterates over the non overlapping matches of the regex in the given text.
+    ///
+    /// Yields the same matches as `findall`, but only searches for the next match when it is
+    /// requested. After an empty match the search moves on by one char, so the iterator also
+    /// terminates for patterns like `a*` that match the empty string everywhere.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice in which to search for the regex pattern.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Matches` iterator over the matches with their positions in the order they
+    /// occur in `text`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("a*", ConstructionType::Glushkov).expect("Valid regex");
+    /// let matches: Vec<(usize, &str)> = regex
+    ///     .find_iter("abc")
+    ///     .map(|m| (m.start(), m.as_str()))
+    ///     .collect();
+    /// assert_eq!(matches, vec![(0, "a"), (2, ""), (3, "")]);
+    /// ```
+    pub fn find_iter<'r, 't>(&'r self, text: &'t str) -> Matches<'r, 't> {
+        Matches {
+            spans: self.spans(text),
+        }
+    }
+
+    /// Same as `findall`, but counts the matches in a first pass so the returned `Vec` is
+    /// allocated with its exact capacity up front.
+    ///
+    /// This trades a second scan of `text` for a `Vec` without reallocations or spare capacity,
+    /// so prefer `findall` unless the exact capacity matters.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice in which to search for the regex pattern.
+    ///
+    /// # Returns
+    ///
+    /// Returns the same matches as `findall` in a `Vec` whose capacity equals its length.
+    pub fn findall_exact<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let mut matches = Vec::with_capacity(self.count_matches(text));
+        matches.extend(self.spans(text).map(|(start, end)| &text[start..end]));
+        matches
+    }
+
+    /// Counts the non overlapping matches of the regex pattern in `text`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.count_matches("ab abab ba"), 3);
+    /// ```
+    pub fn count_matches(&self, text: &str) -> usize {
+        self.spans(text).count()
+    }
+
+    /// Determines whether `text` contains at most `limit` non overlapping matches.
+    ///
+    /// Unlike comparing `count_matches` against the limit, the search stops as soon as the
+    /// match after the limit is found, so a guardrail on match heavy input does not scan all
+    /// of it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert!(regex.matches_count_at_most("ab abab ba", 3));
+    /// assert!(!regex.matches_count_at_most("ab abab ba", 2));
+    /// ```
+    pub fn matches_count_at_most(&self, text: &str, limit: usize) -> bool {
+        self.spans(text).nth(limit).is_none()
+    }
+
+    /// Replaces all non overlapping matches of the regex pattern in `text` with `replacement`.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice in which to replace the matches.
+    /// - `replacement`: The literal string each match is replaced with.
+    ///
+    /// # Returns
+    ///
+    /// Returns a new `String` with every match replaced, or `text` itself without allocating
+    /// if nothing matches.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    /// use std::borrow::Cow;
+    ///
+    /// let regex = Regex::new("a+", ConstructionType::Thompson).expect("Valid regex");
+    /// assert_eq!(regex.replace_all("caaat and a bat", "o"), "cot ond o bot");
+    /// assert!(matches!(regex.replace_all("xyz", "o"), Cow::Borrowed("xyz")));
+    /// ```
+    pub fn replace_all<'a>(&self, text: &'a str, replacement: &str) -> Cow<'a, str> {
+        let mut spans = self.spans(text).peekable();
+        if spans.peek().is_none() {
+            return Cow::Borrowed(text);
+        }
+
+        let mut result = String::with_capacity(text.len());
+        let mut last_end = 0;
+        for (start, end) in spans {
+            result.push_str(&text[last_end..start]);
+            result.push_str(replacement);
+            last_end = end;
+        }
+        result.push_str(&text[last_end..]);
+        Cow::Owned(result)
+    }
+
+    /// Streams the result of `replace_all` into `out` instead of building a new `String`.
+    ///
+    /// Unmatched regions and replacements are written as soon as each match is found, which
+    /// avoids one big allocation for large inputs and allows writing directly to a sink.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice in which to replace the matches.
+    /// - `replacement`: The literal string each match is replaced with.
+    /// - `out`: The writer receiving the result.
+    ///
+    /// # Returns
+    ///
+    /// Returns an error if writing to `out` fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("b", ConstructionType::Glushkov).expect("Valid regex");
+    /// let mut out = String::new();
+    /// regex.replace_all_to("abcb", "-", &mut out).expect("Writing to a String");
+    /// assert_eq!(out, "a-c-");
+    /// ```
+    pub fn replace_all_to<W: std::fmt::Write>(
+        &self,
+        text: &str,
+        replacement: &str,
+        out: &mut W,
+    ) -> std::fmt::Result {
+        let mut last_end = 0;
+        for (start, end) in self.spans(text) {
+            out.write_str(&text[last_end..start])?;
+            out.write_str(replacement)?;
+            last_end = end;
+        }
+        out.write_str(&text[last_end..])
+    }
+
+    /// Splits `text` at every match of the regex pattern, returning the pieces between the
+    /// matches.
+    ///
+    /// This mirrors `str::split` with the same matches as `findall`: a match at the start or
+    /// end of `text` produces an empty first or last piece and an empty `text` produces a
+    /// single empty piece. An empty match splits between two chars, the search moves on by one
+    /// char after it so a pattern like `a*` still terminates.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice to split.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Vec<&str>` of the pieces, one more than there are matches.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("[0-9]", ConstructionType::Thompson).expect("Valid regex");
+    /// assert_eq!(regex.split("a1b2c"), vec!["a", "b", "c"]);
+    /// assert_eq!(regex.split("1a2"), vec!["", "a", ""]);
+    /// ```
+    pub fn split<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let mut pieces = Vec::new();
+        let mut piece_start = 0;
+        for (start, end) in self.spans(text) {
+            pieces.push(&text[piece_start..start]);
+            piece_start = end;
+        }
+        pieces.push(&text[piece_start..]);
+        pieces
+    }
+
+    /// Splits `text` at every match of the regex pattern, keeping each match at the end of
+    /// the piece it terminates.
+    ///
+    /// This mirrors `str::split_inclusive`: a trailing match does not produce an empty last
+    /// piece and an empty `text` produces no pieces at all.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice to split.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Vec<&str>` of the pieces, which concatenated give back `text`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new(";", ConstructionType::Thompson).expect("Valid regex");
+    /// assert_eq!(regex.split_inclusive("a;b;c"), vec!["a;", "b;", "c"]);
+    /// ```
+    pub fn split_inclusive<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let mut pieces = Vec::new();
+        let mut piece_start = 0;
+        for (_, end) in self.find_all_spans(text) {
+            // An empty match at the start of a piece does not terminate anything
+            if end > piece_start {
+                pieces.push(&text[piece_start..end]);
+                piece_start = end;
+            }
+        }
+        if piece_start < text.len() {
+            pieces.push(&text[piece_start..]);
+        }
+        pieces
+    }
+
+    /// Marks every byte of `text` that lies inside a match of the regex pattern.
+    ///
+    /// The mask is built from the same non overlapping, left to right matches that `findall`
+    /// returns, which makes it convenient for highlighting matches in terminals or diffs.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: A string slice in which to search for the regex pattern.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Vec<bool>` with one entry per byte of `text`, where `true` means the byte is
+    /// part of a match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("abc", ConstructionType::Thompson).expect("Valid regex");
+    /// assert_eq!(
+    ///     regex.match_mask("xabcx"),
+    ///     vec![false, true, true, true, false]
+    /// );
+    /// ```
+    pub fn match_mask(&self, text: &str) -> Vec<bool> {
+        let mut mask = vec![false; text.len()];
+        for (start, end) in self.find_all_spans(text) {
+            mask[start..end].fill(true);
+        }
+        mask
+    }
+
+    /// Generates a random string accepted by the regex by walking the underlying DFA.
+    ///
+    /// At every step the walk picks one of the outgoing transitions that can still reach an
+    /// accepting state within the remaining length budget, and may stop whenever it sits in an
+    /// accepting state. The same seed always yields the same string, which makes this useful
+    /// for generating reproducible fuzzing or test corpora.
+    ///
+    /// # Parameters
+    ///
+    /// - `rng_seed`: Seed for the internal pseudo random number generator.
+    /// - `max_len`: The maximum number of characters the generated string may contain.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(String)` with an accepted string of at most `max_len` characters, or `None`
+    /// if the regex accepts no string that short.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("a(b|c)*", ConstructionType::Glushkov).expect("Valid regex");
+    /// let sample = regex.sample_random(42, 10).expect("Regex accepts short strings");
+    /// assert!(regex.is_match(&sample));
+    /// ```
+    pub fn sample_random(&self, rng_seed: u64, max_len: usize) -> Option<String> {
+        self.dfa().sample_random(rng_seed, max_len)
+    }
+
+    /// Renders the underlying DFA in the graphviz DOT format.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `digraph` with one node per state, accepting states drawn as double circles
+    /// and one labelled edge per transition.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert!(regex.to_dot().starts_with("digraph dfa {"));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        self.dfa().to_dot(None)
+    }
+
+    /// Renders the underlying DFA like `to_dot`, highlighting how `input` is processed.
+    ///
+    /// The states and edges walked while consuming `input` are drawn in red. If a character
+    /// has no transition the walk stops there and a dashed edge labelled with that character
+    /// leads into a separate dead end node, which shows why the input is rejected.
+    ///
+    /// # Parameters
+    ///
+    /// - `input`: The text whose path through the DFA should be highlighted.
+    ///
+    /// # Returns
+    ///
+    /// Returns the DOT source of the highlighted DFA.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab", ConstructionType::Glushkov).expect("Valid regex");
+    /// let dot = regex.to_dot_trace("ax");
+    /// assert!(dot.contains("dead_end"));
+    /// ```
+    pub fn to_dot_trace(&self, input: &str) -> String {
+        self.dfa().to_dot(Some(input))
+    }
+
+    /// Returns the id of the start state of the underlying DFA.
+    ///
+    /// State ids are the ones shown by `to_dot` and accepted by `transitions_for_state`.
+    pub fn start_state(&self) -> u32 {
+        self.dfa().start_state()
+    }
+
+    /// Returns the outgoing transitions of a single state of the underlying DFA.
+    ///
+    /// Together with `start_state` this allows walking the DFA step by step, e.g. in a
+    /// debugger. Characters matched through a unicode property class are all listed
+    /// individually.
+    ///
+    /// # Parameters
+    ///
+    /// - `state`: The id of the state.
+    ///
+    /// # Returns
+    ///
+    /// Returns the pairs of character and target state sorted by character, or an empty `Vec`
+    /// for a state without outgoing transitions or an unknown id.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab", ConstructionType::Glushkov).expect("Valid regex");
+    /// let transitions = regex.transitions_for_state(regex.start_state());
+    /// assert_eq!(transitions.len(), 1);
+    /// assert_eq!(transitions[0].0, 'a');
+    /// ```
+    pub fn transitions_for_state(&self, state: u32) -> Vec<(char, u32)> {
+        let dfa = self.dfa();
+        let mut transitions: Vec<(char, u32)> = Vec::new();
+        for (&(source_state, symbol), &target_state) in dfa.get_transitions() {
+            if source_state == state {
+                let (cell_start, cell_end) = dfa.get_alphabet().cell(symbol);
+                transitions.extend((cell_start..=cell_end).map(|c| (c, target_state)));
+            }
+        }
+        transitions.sort_unstable();
+        transitions
+    }
+
+    /// Walks the underlying DFA over `text` one character at a time.
+    ///
+    /// Unlike `to_dot_trace` this returns the walk as data, e.g. to show in a REPL whether the
+    /// text read so far would already be a match.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: The text to walk the DFA with.
+    ///
+    /// # Returns
+    ///
+    /// Returns for every consumed character the character, the state reached and whether that
+    /// state is accepting. The walk stops at the first character without a transition.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab", ConstructionType::Glushkov).expect("Valid regex");
+    /// let steps = regex.step_debug("abc");
+    /// assert_eq!(steps.len(), 2);
+    /// assert!(!steps[0].2);
+    /// assert!(steps[1].2);
+    /// ```
+    pub fn step_debug(&self, text: &str) -> Vec<(char, u32, bool)> {
+        let dfa = self.dfa();
+        let states = dfa.trace(text);
+        text.chars()
+            .zip(&states[1..])
+            .map(|(c, &state)| (c, state, dfa.get_accepting_states().contains(&state)))
+            .collect()
+    }
+
+    /// Returns a byte key identifying the structure of the underlying DFA.
+    ///
+    /// The key does not depend on the construction type or on how states are numbered, so two
+    /// patterns compiling to the same minimal DFA get the same key. This allows caches to
+    /// share one compiled regex between equivalent patterns.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let thompson = Regex::new("ab|ac", ConstructionType::Thompson).expect("Valid regex");
+    /// let glushkov = Regex::new("a(b|c)", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(thompson.canonical_key(), glushkov.canonical_key());
+    /// ```
+    pub fn canonical_key(&self) -> Vec<u8> {
+        self.dfa().canonical_key()
+    }
+
+    /// Reconstructs a pattern accepting the same language from the minimised DFA.
+    ///
+    /// The result is not necessarily shorter than the original pattern, but it only uses the
+    /// core syntax and is handy to inspect generated patterns.
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the regex matches nothing. Characters that are matched alike, for
+    /// example by a unicode property, are written as bracket class ranges.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab|ac|ad", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.simplify().as_deref(), Some("a(b|c|d)"));
+    /// ```
+    pub fn simplify(&self) -> Option<String> {
+        state_elimination::dfa_to_pattern(self.dfa())
+    }
+
+    /// Serialises the compiled regex so it can be cached and loaded with `from_bytes` instead
+    /// of being compiled again.
+    ///
+    /// The bytes start with a header of magic bytes, a format version, the construction type
+    /// and the width of the state ids, followed by the DFA and the literals used to speed up
+    /// searching.
+    ///
+    /// # Returns
+    ///
+    /// Returns the serialised regex, or `None` if the regex uses a char map, which cannot be
+    /// serialised.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab+c", ConstructionType::Glushkov).expect("Valid regex");
+    /// let bytes = regex.to_bytes().expect("No char map");
+    /// let loaded = Regex::from_bytes(&bytes).expect("Serialised by this version");
+    /// assert_eq!(loaded.find("xabbc"), Some("abbc"));
+    /// ```
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        serialise::regex_to_bytes(self)
+    }
+
+    /// Loads a regex serialised by `to_bytes`.
+    ///
+    /// # Parameters
+    ///
+    /// - `bytes`: The serialised regex.
+    ///
+    /// # Returns
+    ///
+    /// Returns the regex, or `RegexError::BadSerialFormat` if the header does not match this
+    /// version of the format or the data is truncated or corrupt.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Regex, RegexError> {
+        serialise::regex_from_bytes(bytes)
+    }
+
+    /// Compiles the language of the regex with the `regex` crate, as a fallback for features
+    /// this engine does not support.
+    ///
+    /// The pattern is reconstructed from the minimised DFA like `simplify` does and keeps the
+    /// `^` and `$` anchors. Only available with the `regex_crate` feature. Note that the
+    /// `regex` crate searches leftmost-first, so for a pattern like `a|ab` it can find a
+    /// shorter match than `find` does.
+    ///
+    /// # Returns
+    ///
+    /// Returns the compiled `regex::Regex`, or an error if the regex matches nothing or uses a
+    /// char map, neither of which can be written as a pattern.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab+c", ConstructionType::Glushkov).expect("Valid regex");
+    /// let bridged = regex.to_regex_crate().expect("Reconstructable regex");
+    /// assert_eq!(bridged.find("xabbc").map(|m| m.as_str()), Some("abbc"));
+    /// ```
+    #[cfg(feature = "regex_crate")]
+    pub fn to_regex_crate(&self) -> Result<regex::Regex, String> {
+        if self.dfa().get_alphabet().has_char_map() {
+            return Err("A regex with a char map cannot be reconstructed".to_string());
+        }
+        let pattern = self.simplify().ok_or("The regex matches nothing")?;
+        let start_anchor = if self.anchored_start { "^" } else { "" };
+        let end_anchor = if self.anchored_end { "$" } else { "" };
+        regex::Regex::new(&format!("{start_anchor}(?:{pattern}){end_anchor}"))
+            .map_err(|error| error.to_string())
+    }
+
+    /// Estimates the heap memory taken by the DFA in bytes.
+    ///
+    /// Only the transitions and accepting states are counted, at their entry size without
+    /// the overhead of the hash maps, so this is a lower bound meant to compare patterns.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let small = Regex::new("ab", ConstructionType::Glushkov).expect("Valid regex");
+    /// let large = Regex::new("abcdefgh", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert!(small.approx_heap_bytes() < large.approx_heap_bytes());
+    /// ```
+    pub fn approx_heap_bytes(&self) -> usize {
+        let dfa = self.dfa();
+        approx_dfa_heap_bytes(
+            dfa.get_transitions().len(),
+            dfa.get_accepting_states().len(),
+        )
+    }
+
+    /// Returns the number of distinct symbols the DFA has transitions for, i.e. the number of
+    /// classes of chars it tells apart.
+    ///
+    /// Plain characters each count as a symbol of their own, while the chars of a class like
+    /// `[a-z]` that are matched alike share a single symbol.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// // `[a-w]`, `x` and `[y-z]` are matched differently
+    /// let regex = Regex::new("[a-z]+x", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(regex.alphabet_size_hint(), 3);
+    /// ```
+    pub fn alphabet_size_hint(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Returns how the transitions used by `is_match` and the unanchored searches are stored.
+    ///
+    /// The representation is chosen when the regex is compiled: a pattern whose alphabet only
+    /// contains ASCII chars gets a dense table indexed by state and char, as long as its DFA
+    /// has at most 1024 states. Any other pattern keeps the map of the DFA, which stays small
+    /// for large alphabets like unicode properties.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{ConstructionType, Regex, Representation};
+    ///
+    /// let ascii = Regex::new("[a-z]+[0-9]", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(ascii.representation(), Representation::DenseTable);
+    /// let unicode = Regex::new(r"\p{L}+", ConstructionType::Glushkov).expect("Valid regex");
+    /// assert_eq!(unicode.representation(), Representation::HashMap);
+    /// ```
+    pub fn representation(&self) -> Representation {
+        if self.dense_table.is_some() {
+            Representation::DenseTable
+        } else {
+            Representation::HashMap
+        }
+    }
+
+    /// Counts the states of the underlying DFA by their number of outgoing transitions.
+    ///
+    /// States with many transitions are candidates for a dense transition table, while most
+    /// states of typical patterns only have a few.
+    ///
+    /// # Returns
+    ///
+    /// Returns a map from out-degree to the number of states with that out-degree. States
+    /// without outgoing transitions are counted under `0`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{Regex, ConstructionType};
+    ///
+    /// let regex = Regex::new("ab", ConstructionType::Glushkov).expect("Valid regex");
+    /// let histogram = regex.out_degree_histogram();
+    /// assert_eq!(histogram.get(&1), Some(&2));
+    /// assert_eq!(histogram.get(&0), Some(&1));
+    /// ```
+    pub fn out_degree_histogram(&self) -> BTreeMap<usize, usize> {
+        let dfa = self.dfa();
+        let mut out_degrees: HashMap<u32, usize> = HashMap::from([(dfa.start_state(), 0)]);
+        for &state in dfa.get_accepting_states() {
+            out_degrees.entry(state).or_default();
+        }
+        for (&(source_state, _), &target_state) in dfa.get_transitions() {
+            *out_degrees.entry(source_state).or_default() += 1;
+            out_degrees.entry(target_state).or_default();
+        }
+
+        let mut histogram = BTreeMap::new();
+        for out_degree in out_degrees.into_values() {
+            *histogram.entry(out_degree).or_default() += 1;
+        }
+        histogram
+    }
+}
+
+impl TryFrom<&str> for Regex {
+    type Error = RegexError;
+
+    /// Compiles `pattern` with the default construction type.
+    fn try_from(pattern: &str) -> Result<Self, Self::Error> {
+        Regex::new(pattern, ConstructionType::default())
+    }
+}
+
+impl TryFrom<String> for Regex {
+    type Error = RegexError;
+
+    /// Compiles `pattern` with the default construction type.
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        Regex::try_from(pattern.as_str())
+    }
+}
+
+/// A match of a regex in a text together with its position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<'t> {
+    text: &'t str,
+    start: usize,
+    end: usize,
+}
+
+impl<'t> Match<'t> {
+    /// Returns the byte offset in the text at which the match starts.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the byte offset in the text just after the end of the match.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the matched part of the text.
+    pub fn as_str(&self) -> &'t str {
+        &self.text[self.start..self.end]
+    }
+}
+
+/// The spans of the capture groups of a match, created by `Regex::captures`.
+///
+/// Group 0 is the whole match, the other groups are numbered by their opening parenthesis.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Captures<'t> {
+    text: &'t str,
+    spans: Vec<Option<(usize, usize)>>,
+    names: Vec<Option<String>>,
+}
+
+impl<'t> Captures<'t> {
+    /// Returns the match of group `index`, or `None` if the group did not participate in the
+    /// match or does not exist.
+    pub fn get(&self, index: usize) -> Option<Match<'t>> {
+        let (start, end) = (*self.spans.get(index)?)?;
+        Some(Match {
+            text: self.text,
+            start,
+            end,
+        })
+    }
+
+    /// Returns the match of the group named `name`, see `get`.
+    pub fn name(&self, name: &str) -> Option<Match<'t>> {
+        let index = self
+            .names
+            .iter()
+            .position(|group_name| group_name.as_deref() == Some(name))?;
+        self.get(index)
+    }
+
+    /// Iterates over the matches of all groups in order, including group 0.
+    pub fn iter(&self) -> impl Iterator<Item = Option<Match<'t>>> + '_ {
+        (0..self.spans.len()).map(|index| self.get(index))
+    }
+}
+
+/// Iterator over the non overlapping matches in a text, created by `Regex::find_iter`.
+///
+/// Each step resumes the search where the previous match ended, so no matches are collected
+/// up front.
+pub struct Matches<'r, 't> {
+    spans: Spans<'r, 't>,
+}
+
+impl<'t> Iterator for Matches<'_, 't> {
+    type Item = Match<'t>;
+
+    fn next(&mut self) -> Option<Match<'t>> {
+        let (start, end) = self.spans.next()?;
+        Some(Match {
+            text: self.spans.text,
+            start,
+            end,
+        })
+    }
+}
+
+/// Iterator over the byte spans of the non overlapping matches in a text.
+///
+/// Empty matches are reported like in the `regex` crate: after an empty match the search
+/// continues one char further, and an empty match right where the previous match ended is
+/// skipped.
+struct Spans<'r, 't> {
+    regex: &'r Regex,
+    text: &'t str,
+    start_pos: usize,
+    last_end: Option<usize>,
+}
+
+impl Iterator for Spans<'_, '_> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        while self.start_pos <= self.text.len() {
+            let (start, end) = self.regex.find_at(self.text, self.start_pos)?;
+            if start < end {
+                self.start_pos = end;
+            } else {
+                let next_char_len = self.text[end..].chars().next().map_or(1, char::len_utf8);
+                self.start_pos = end + next_char_len;
+                if self.last_end == Some(end) {
+                    continue;
+                }
+            }
+            self.last_end = Some(end);
+            return Some((start, end));
+        }
+        None
+    }
+}
+
+/// Builder for a `Regex` with options beyond the construction type.
+pub struct RegexBuilder {
+    pattern: String,
+    construction: ConstructionType,
+    char_map: Option<fn(char) -> char>,
+    literal: bool,
+    dot_matches_newline: bool,
+    grapheme_clusters: bool,
+    repetition_limit: usize,
+    size_limit: Option<usize>,
+    dense_table: bool,
+}
+
+impl RegexBuilder {
+    pub fn new(pattern: &str, construction: ConstructionType) -> Self {
+        RegexBuilder {
+            pattern: pattern.to_string(),
+            construction,
+            char_map: None,
+            literal: false,
+            dot_matches_newline: false,
+            grapheme_clusters: false,
+            repetition_limit: DEFAULT_REPETITION_LIMIT,
+            size_limit: None,
+            dense_table: true,
+        }
+    }
+
+    /// Sets a function mapping every character to the one it is treated as.
+    ///
+    /// The map is applied to the literal characters of the pattern when compiling and to every
+    /// character of the input when matching, so characters mapping to the same char are
+    /// interchangeable. Matches are still reported as slices of the original input. Unicode
+    /// property classes and the `.` wildcard are tested against the mapped character. The
+    /// chars of a class range are mapped one by one, so `build` fails for very large ranges.
+    ///
+    /// # Parameters
+    ///
+    /// - `char_map`: The mapping applied to the pattern and the input.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{RegexBuilder, ConstructionType};
+    ///
+    /// let regex = RegexBuilder::new("Abc", ConstructionType::Glushkov)
+    ///     .char_map(|c| c.to_ascii_lowercase())
+    ///     .build()
+    ///     .expect("Valid regex");
+    /// assert!(regex.is_match("aBC"));
+    /// ```
+    pub fn char_map(mut self, char_map: fn(char) -> char) -> Self {
+        self.char_map = Some(char_map);
+        self
+    }
+
+    /// Sets whether the whole pattern is a plain string matched literally, like `grep -F`.
+    ///
+    /// No character of the pattern has a special meaning, so unlike escaping the pattern
+    /// beforehand nothing can be escaped twice. The automaton of a single string is built
+    /// directly, the construction type makes no difference. An empty pattern is allowed and
+    /// matches the empty string.
+    ///
+    /// # Parameters
+    ///
+    /// - `literal`: Whether the pattern is taken literally.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{RegexBuilder, ConstructionType};
+    ///
+    /// let regex = RegexBuilder::new("a.b", ConstructionType::Glushkov)
+    ///     .literal(true)
+    ///     .build()
+    ///     .expect("Any literal");
+    /// assert!(regex.is_match("a.b"));
+    /// assert!(!regex.is_match("axb"));
+    /// ```
+    pub fn literal(mut self, literal: bool) -> Self {
+        self.literal = literal;
+        self
+    }
+
+    /// Sets whether the `.` wildcard also matches a newline `\n`, which it does not by default.
+    ///
+    /// The anchors `^` and `$` still refer to the start and end of the whole text. Escaped dots
+    /// and dots in quoted text stay literal.
+    ///
+    /// # Parameters
+    ///
+    /// - `dot_matches_newline`: Whether `.` matches `\n`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{RegexBuilder, ConstructionType};
+    ///
+    /// let regex = RegexBuilder::new("a.b", ConstructionType::Glushkov)
+    ///     .dot_matches_newline(true)
+    ///     .build()
+    ///     .expect("Valid regex");
+    /// assert!(regex.is_match("a\nb"));
+    /// ```
+    pub fn dot_matches_newline(mut self, dot_matches_newline: bool) -> Self {
+        self.dot_matches_newline = dot_matches_newline;
+        self
+    }
+
+    /// Sets whether the `.` wildcard matches a whole grapheme cluster instead of a single char.
+    ///
+    /// A cluster is approximated as a char followed by any number of combining marks, the
+    /// class `\p{M}`, so `.` also consumes the accent in `e` followed by `U+0301`. Since `^`
+    /// and `$` only match at the start and end of the text, they are always cluster
+    /// boundaries, so `^.$` matches exactly one cluster.
+    ///
+    /// # Parameters
+    ///
+    /// - `grapheme_clusters`: Whether `.` matches a whole grapheme cluster.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{RegexBuilder, ConstructionType};
+    ///
+    /// let regex = RegexBuilder::new("^.$", ConstructionType::Glushkov)
+    ///     .grapheme_clusters(true)
+    ///     .build()
+    ///     .expect("Valid regex");
+    /// assert!(regex.is_match("e\u{301}"));
+    /// ```
+    pub fn grapheme_clusters(mut self, grapheme_clusters: bool) -> Self {
+        self.grapheme_clusters = grapheme_clusters;
+        self
+    }
+
+    /// Sets the largest count a bounded repetition like `a{2,5}` may use, 1000 by default.
+    ///
+    /// A repetition is compiled by writing out its atom once per count, so large counts make
+    /// the automaton large as well. Patterns with a larger count fail to build instead.
+    ///
+    /// # Parameters
+    ///
+    /// - `repetition_limit`: The largest allowed minimum or maximum count of a repetition.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{RegexBuilder, ConstructionType};
+    ///
+    /// let builder = RegexBuilder::new("a{3}", ConstructionType::Glushkov);
+    /// assert!(builder.repetition_limit(2).build().is_err());
+    /// ```
+    pub fn repetition_limit(mut self, repetition_limit: usize) -> Self {
+        self.repetition_limit = repetition_limit;
+        self
+    }
+
+    /// Caps the memory the DFA may take, estimated like `Regex::approx_heap_bytes`.
+    ///
+    /// The subset construction is aborted as soon as the DFA built so far exceeds the limit,
+    /// so a pattern whose DFA blows up exponentially fails fast instead of exhausting memory.
+    /// There is no limit by default.
+    ///
+    /// # Parameters
+    ///
+    /// - `size_limit`: The largest estimated size of the DFA in bytes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{RegexBuilder, ConstructionType};
+    ///
+    /// let builder = RegexBuilder::new("(a|b)*a(a|b)(a|b)", ConstructionType::Glushkov);
+    /// assert!(builder.size_limit(64).build().is_err());
+    /// ```
+    pub fn size_limit(mut self, size_limit: usize) -> Self {
+        self.size_limit = Some(size_limit);
+        self
+    }
+
+    /// Sets whether the transitions may be stored in a dense table, which they are by default
+    /// when the pattern allows it, see `Regex::representation`.
+    ///
+    /// Turning the table off keeps the map of the DFA for every pattern, which is slower to
+    /// match but lets both representations be compared on the same pattern.
+    ///
+    /// # Parameters
+    ///
+    /// - `dense_table`: Whether a dense table may be used.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{ConstructionType, RegexBuilder, Representation};
+    ///
+    /// let regex = RegexBuilder::new("[a-z]+[0-9]", ConstructionType::Glushkov)
+    ///     .dense_table(false)
+    ///     .build()
+    ///     .expect("Valid regex");
+    /// assert_eq!(regex.representation(), Representation::HashMap);
+    /// ```
+    pub fn dense_table(mut self, dense_table: bool) -> Self {
+        self.dense_table = dense_table;
+        self
+    }
+
+    /// Compiles the regex with the configured options.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Regex`, or an error if the pattern is not a valid regex. In literal mode
+    /// every pattern is valid.
+    pub fn build(&self) -> Result<Regex, RegexError> {
+        let mut regex = self.build_regex()?;
+        if !self.dense_table {
+            regex.dense_table = None;
+        }
+        Ok(regex)
+    }
+
+    fn build_regex(&self) -> Result<Regex, RegexError> {
+        let mut regex = if self.literal {
+            let literal: String = self
+                .pattern
+                .chars()
+                .map(|c| self.char_map.map_or(c, |char_map| char_map(c)))
+                .collect();
+            let mut regex = Regex::from_examples(&[&literal]);
+            regex.required_literal = (!literal.is_empty()).then_some(literal);
+            regex
+        } else {
+            // Quoted text is mapped like any other literal
+            let pattern = expand_quotes(&self.pattern);
+            let mut dot = String::from(if self.dot_matches_newline {
+                "(.|\n)"
+            } else {
+                "."
+            });
+            if self.grapheme_clusters {
+                dot = format!(r"({dot}\p{{M}}*)");
+            }
+            if dot == "." && self.char_map.is_none() {
+                return Regex::compile(
+                    &pattern,
+                    self.construction,
+                    self.repetition_limit,
+                    self.size_limit,
+                );
+            }
+
+            let mut rewritten = replace_dots(&pattern, &dot);
+            if let Some(char_map) = self.char_map {
+                rewritten = map_pattern_chars(&rewritten, char_map)?;
+            }
+            let mut regex = Regex::compile(
+                &rewritten,
+                self.construction,
+                self.repetition_limit,
+                self.size_limit,
+            )?;
+            // The groups added around the dots are not captures, so the capture program is
+            // built from the pattern with the dot kept as one atom
+            regex.capture_names = capture_names(&pattern);
+            let dot_pattern = dot.replace("(.|\n)", "[ -~\n]");
+            let mut capture_pattern = pattern.into_owned();
+            if let Some(char_map) = self.char_map {
+                capture_pattern = map_pattern_chars(&capture_pattern, char_map)?;
+            }
+            let (capture_pattern, _, _) = Regex::anchors_stripped_pattern(&capture_pattern);
+            regex.capture_program = Some(CaptureProgram::new(capture_pattern, &dot_pattern)?);
+            regex
+        };
+
+        let Some(char_map) = self.char_map else {
+            return Ok(regex);
+        };
+        regex.dfa_mut().get_alphabet_mut().set_char_map(char_map);
+        // The literal fast paths compare the unmapped input
+        regex.dot_star_literal = None;
+        regex.required_literal = None;
+        Ok(regex)
+    }
+}
+
+/// A collection of regexes that are matched against the same input.
+pub struct RegexSet {
+    regexes: Vec<Regex>,
+}
+
+impl RegexSet {
+    /// Compiles every pattern with the given construction type.
+    ///
+    /// # Parameters
+    ///
+    /// - `patterns`: The regex patterns making up the set, their indices identify them.
+    /// - `construction`: The construction type used for every pattern.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `RegexSet`, or the error of the first pattern that fails to compile.
+    pub fn new(patterns: &[&str], construction: ConstructionType) -> Result<Self, RegexError> {
+        let regexes = patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern, construction))
+            .collect::<Result<Vec<Regex>, RegexError>>()?;
+        Ok(RegexSet { regexes })
+    }
+
+    /// Returns the number of patterns in the set.
+    pub fn len(&self) -> usize {
+        self.regexes.len()
+    }
+
+    /// Returns whether the set contains no patterns.
+    pub fn is_empty(&self) -> bool {
+        self.regexes.is_empty()
+    }
+
+    /// Determines whether any pattern of the set matches the entire `text`.
+    ///
+    /// The patterns are tried in order and the search stops at the first one that matches,
+    /// use `matching` to find out which patterns match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use regex_engine::{RegexSet, ConstructionType};
+    ///
+    /// let set = RegexSet::new(&["get/a*", "post/b*"], ConstructionType::Glushkov)
+    ///     .expect("Valid regexes");
+    /// assert!(set.is_match("post/bb"));
+    /// assert!(!set.is_match("put/"));
+    /// ```
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regexes.iter().any(|regex| regex.is_match(text))
+    }
+
+    /// Returns the indices of all patterns that match the entire `text`, in ascending order.
+    pub fn matching(&self, text: &str) -> Vec<usize> {
+        self.regexes
+            .iter()
+            .enumerate()
+            .filter(|(_, regex)| regex.is_match(text))
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+/// A cache of compiled regexes keyed on their pattern, for callers compiling the same patterns
+/// over and over.
+///
+/// Every pattern is compiled with the construction type the cache was created with.
+///
+/// # Example
+///
+/// ```rust
+/// use regex_engine::{RegexCache, ConstructionType};
+///
+/// let mut cache = RegexCache::new(ConstructionType::Glushkov);
+/// assert!(cache.get_or_compile("a+b").expect("Valid regex").is_match("aab"));
+/// assert!(cache.get_or_compile("a+b").expect("Valid regex").is_match("ab"));
+/// assert_eq!((cache.hits(), cache.misses()), (1, 1));
+/// ```
+pub struct RegexCache {
+    construction: ConstructionType,
+    regexes: HashMap<String, Regex>,
+    hits: usize,
+    misses: usize,
+}
+
+impl RegexCache {
+    pub fn new(construction: ConstructionType) -> Self {
+        RegexCache {
+            construction,
+            regexes: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Returns the compiled regex for `pattern`, compiling it on the first request.
+    ///
+    /// Patterns that fail to compile are not cached, so they count as a miss every time.
+    pub fn get_or_compile(&mut self, pattern: &str) -> Result<&Regex, RegexError> {
+        if self.regexes.contains_key(pattern) {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+            let regex = Regex::new(pattern, self.construction)?;
+            self.regexes.insert(pattern.to_string(), regex);
+        }
+        Ok(&self.regexes[pattern])
+    }
+
+    /// Builds a `RegexSet` of `patterns`, taking every regex from the cache.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `RegexSet`, or the error of the first pattern that fails to compile.
+    pub fn compile_set(&mut self, patterns: &[&str]) -> Result<RegexSet, RegexError> {
+        let regexes = patterns
+            .iter()
+            .map(|pattern| self.get_or_compile(pattern).cloned())
+            .collect::<Result<Vec<Regex>, RegexError>>()?;
+        Ok(RegexSet { regexes })
+    }
+
+    /// Returns the number of lookups served from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Returns the number of lookups that had to compile their pattern.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Returns the number of cached regexes.
+    pub fn len(&self) -> usize {
+        self.regexes.len()
+    }
+
+    /// Returns whether the cache holds no regexes.
+    pub fn is_empty(&self) -> bool {
+        self.regexes.is_empty()
+    }
+}
+
+/// Replaces every `.` wildcard of `pattern` with the group `dot`.
+fn replace_dots(pattern: &str, dot: &str) -> String {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut rewritten = String::new();
+
+    let mut pos = 0;
+    while pos < chars.len() {
+        let c = chars[pos];
+        pos += 1;
+        match c {
+            '\\' => {
+                let end = alphabet::escape_end(&chars, pos);
+                rewritten.push(c);
+                rewritten.extend(&chars[pos..end]);
+                pos = end;
+            }
+            '(' => {
+                rewritten.push(c);
+                // Comments and group names may contain a dot that is not a wildcard
+                if let Some(end) = comment_end(&chars, pos) {
+                    rewritten.extend(&chars[pos..end]);
+                    pos = end;
+                } else if let Some((_, end)) = group_name(&chars, pos) {
+                    rewritten.extend(&chars[pos..end]);
+                    pos = end;
+                }
+            }
+            // A dot in a class is literal
+            '[' => {
+                let end = alphabet::class_end(&chars, pos).unwrap_or(pos);
+                rewritten.push(c);
+                rewritten.extend(&chars[pos..end]);
+                pos = end;
+            }
+            '.' => rewritten.push_str(dot),
+            _ => rewritten.push(c),
+        }
+    }
+
+    rewritten
+}
+
+/// Yields every char of `text` from the byte offset `start_pos` on together with the byte
+/// offsets it starts and ends at.
+fn byte_positions(text: &str, start_pos: usize) -> impl Iterator<Item = (usize, char, usize)> {
+    text[start_pos..].char_indices().map(move |(i, c)| {
+        let start = start_pos + i;
+        (start, c, start + c.len_utf8())
+    })
+}
+
+/// States already reached by a thread at the current char of `leftmost_longest_match`.
+pub(crate) trait SeenStates {
+    /// Marks `state` as seen and returns whether it was not seen before.
+    fn insert(&mut self, state: u32) -> bool;
+    fn remove(&mut self, state: u32);
+}
+
+impl SeenStates for HashSet<u32> {
+    fn insert(&mut self, state: u32) -> bool {
+        HashSet::insert(self, state)
+    }
+
+    fn remove(&mut self, state: u32) {
+        HashSet::remove(self, &state);
+    }
+}
+
+/// Indexed by state, for automata whose states are numbered densely.
+impl SeenStates for Vec<bool> {
+    fn insert(&mut self, state: u32) -> bool {
+        !std::mem::replace(&mut self[state as usize], true)
+    }
+
+    fn remove(&mut self, state: u32) {
+        self[state as usize] = false;
+    }
+}
+
+/// Finds the leftmost-longest match starting at or after `start_pos` over `chars`, which
+/// yields every char together with its start and end position, and returns its span.
+///
+/// Single pass search shared by every representation of a DFA, which only differ in how
+/// `step` follows a transition. Every live thread is a state paired with the offset it started
+/// at. Threads that reach the same state share the same future, so only the leftmost one is
+/// kept, which bounds the work per character by the number of states instead of rescanning
+/// the input from every possible start position. `seen_states` has to be empty.
+pub(crate) fn leftmost_longest_match(
+    start_state: u32,
+    start_pos: usize,
+    chars: impl Iterator<Item = (usize, char, usize)>,
+    seen_states: &mut impl SeenStates,
+    is_accepting: impl Fn(u32) -> bool,
+    mut step: impl FnMut(u32, char) -> Option<u32>,
+) -> Option<(usize, usize)> {
+    let mut threads: Vec<(u32, usize)> = Vec::new();
+    let mut next_threads: Vec<(u32, usize)> = Vec::new();
+    let mut best_match: Option<(usize, usize)> = None;
+
+    // An accepting start state matches the empty string, so the leftmost match starts right
+    // at `start_pos` and only has to be extended
+    if is_accepting(start_state) {
+        best_match = Some((start_pos, start_pos));
+        threads.push((start_state, start_pos));
+    }
+
+    for (i, c, end) in chars {
+        if best_match.is_none() {
+            threads.push((start_state, i));
+        }
+
+        for &(state, start) in &threads {
+            if let Some(next_state) = step(state, c)
+                && seen_states.insert(next_state)
+            {
+                next_threads.push((next_state, start));
+            }
+        }
+        for &(state, _) in &next_threads {
+            seen_states.remove(state);
+        }
+        std::mem::swap(&mut threads, &mut next_threads);
+        next_threads.clear();
+
+        // Threads are ordered by start, so the first accepting one is the leftmost match
+        if let Some(&(_, start)) = threads.iter().find(|&&(state, _)| is_accepting(state))
+            && best_match.is_none_or(|(best_start, _)| start <= best_start)
+        {
+            best_match = Some((start, end));
+        }
+
+        if let Some((best_start, _)) = best_match {
+            threads.retain(|&(_, start)| start <= best_start);
+            if threads.is_empty() {
+                break;
+            }
+        }
+    }
+
+    best_match
+}
+
+/// Ranges of more chars than this are not enumerated to apply a char map to each of them.
+const MAX_MAPPED_RANGE_LEN: u32 = 1 << 16;
+
+/// Applies `char_map` to every literal character of `pattern`, leaving its syntax intact.
+///
+/// Mapped characters that would be read as an operator are escaped. The chars of a class
+/// range are mapped one by one, which fails for a range longer than `MAX_MAPPED_RANGE_LEN`.
+fn map_pattern_chars(pattern: &str, char_map: fn(char) -> char) -> Result<String, RegexError> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut mapped = String::new();
+
+    let mut pos = 0;
+    while pos < chars.len() {
+        let c = chars[pos];
+        pos += 1;
+        match c {
+            '\\' => {
+                let end = alphabet::escape_end(&chars, pos);
+                let codepoint = match alphabet::parse_escape(&chars, pos) {
+                    Ok((class, _)) if chars[pos] == 'u' => Some(class.ranges()[0].0),
+                    _ => None,
+                };
+                if end == pos + 1 {
+                    mapped.push(c);
+                    mapped.push(char_map(chars[pos]));
+                } else if let Some(codepoint) = codepoint {
+                    mapped.push(c);
+                    mapped.push(char_map(codepoint));
+                } else {
+                    // Unicode properties, invalid and trailing escapes are kept verbatim
+                    mapped.push(c);
+                    mapped.extend(&chars[pos..end]);
+                }
+                pos = end;
+            }
+            '(' => {
+                mapped.push(c);
+                // Group names and comments are not matched, so they are kept verbatim
+                if let Some(end) = comment_end(&chars, pos) {
+                    mapped.extend(&chars[pos..end]);
+                    pos = end;
+                } else if let Some((_, end)) = group_name(&chars, pos) {
+                    mapped.extend(&chars[pos..end]);
+                    pos = end;
+                }
+            }
+            // Ranges are mapped char by char, the mapped chars need not form a range
+            '[' if alphabet::class_end(&chars, pos)
+                .is_some_and(|end| alphabet::class_items(&chars[pos..end - 1]).is_ok()) =>
+            {
+                let end = alphabet::class_end(&chars, pos).expect("Closed class");
+                mapped.push(c);
+                for item in alphabet::class_items(&chars[pos..end - 1]).expect("Valid class") {
+                    match item {
+                        alphabet::ClassItem::Member([member_char]) => {
+                            push_mapped_char(&mut mapped, char_map(*member_char));
+                        }
+                        alphabet::ClassItem::Member(member) => mapped.push_str(&map_pattern_chars(
+                            &member.iter().collect::<String>(),
+                            char_map,
+                        )?),
+                        alphabet::ClassItem::Range(start, end) => {
+                            if end as u32 - start as u32 >= MAX_MAPPED_RANGE_LEN {
+                                return Err(RegexError::CharMapRangeTooLarge { start, end });
+                            }
+                            for range_char in start..=end {
+                                push_mapped_char(&mut mapped, char_map(range_char));
+                            }
+                        }
+                    }
+                }
+                mapped.push(']');
+                pos = end;
+            }
+            // The counts of a repetition are not matched
+            '{' => {
+                let end = repetition_end(&chars, pos).unwrap_or(pos);
+                mapped.push(c);
+                mapped.extend(&chars[pos..end]);
+                pos = end;
+            }
+            // Operators and anchors, an unclosed `[` is kept for the validation to reject
+            _ if alphabet::META_CHARS.contains(&c) => mapped.push(c),
+            _ => push_mapped_char(&mut mapped, char_map(c)),
+        }
+    }
+
+    Ok(mapped)
+}
+
+/// Pushes a mapped literal character, escaping it if it would be read as an operator or, in a
+/// class, as part of a range.
+fn push_mapped_char(mapped: &mut String, mapped_char: char) {
+    if alphabet::META_CHARS.contains(&mapped_char) || mapped_char == '-' {
+        mapped.push('\\');
+    }
+    mapped.push(mapped_char);
+}
+
+/// Parses the `?P<name>` part of a named group whose `(` is right before `pos`.
+///
+/// Returns the name together with the index just after the closing `>`.
+fn group_name(chars: &[char], pos: usize) -> Option<(String, usize)> {
+    if chars.get(pos..pos + 3)? != ['?', 'P', '<'] {
+        return None;
+    }
+    let name_len = chars[pos + 3..].iter().position(|&c| c == '>')?;
+    let name: String = chars[pos + 3..pos + 3 + name_len].iter().collect();
+    Some((name, pos + 4 + name_len))
+}
+
+/// Returns whether `pattern` has a `|` outside of every group.
+fn has_top_level_alternation(pattern: &str) -> bool {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut depth = 0;
+    let mut pos = 0;
+    while pos < chars.len() {
+        match chars[pos] {
+            '\\' => pos = alphabet::escape_end(&chars, pos + 1),
+            '(' => match comment_end(&chars, pos + 1) {
+                Some(end) => pos = end,
+                None => {
+                    depth += 1;
+                    pos += 1;
+                }
+            },
+            ')' => {
+                depth -= 1;
+                pos += 1;
+            }
+            '[' => pos = alphabet::class_end(&chars, pos + 1).unwrap_or(pos + 1),
+            '|' if depth == 0 => return true,
+            _ => pos += 1,
+        }
+    }
+    false
+}
+
+/// Estimated heap bytes of a DFA with the given number of transitions and states.
+fn approx_dfa_heap_bytes(transition_count: usize, state_count: usize) -> usize {
+    transition_count * std::mem::size_of::<((u32, char), u32)>()
+        + state_count * std::mem::size_of::<u32>()
+}
+
+/// Returns an error if a DFA of the given size exceeds `size_limit` bytes.
+fn check_size_limit(
+    transition_count: usize,
+    state_count: usize,
+    size_limit: Option<usize>,
+) -> Result<(), RegexError> {
+    match size_limit {
+        Some(limit) if approx_dfa_heap_bytes(transition_count, state_count) > limit => {
+            Err(RegexError::StateLimitExceeded { limit })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Counts of a bounded repetition above this are rejected unless the builder allows them.
+const DEFAULT_REPETITION_LIMIT: usize = 1000;
+
+/// Parses the counts between the braces of a bounded repetition `{n}`, `{n,}` or `{n,m}`.
+///
+/// Returns the minimum and the maximum count, where `None` means unbounded, or `None` if the
+/// counts are malformed or the maximum is below the minimum.
+fn repetition_bounds(counts: &[char]) -> Option<(usize, Option<usize>)> {
+    let counts: String = counts.iter().collect();
+    let parse_count = |count: &str| {
+        (!count.is_empty() && count.chars().all(|c| c.is_ascii_digit()))
+            .then(|| count.parse::<usize>().ok())
+            .flatten()
+    };
+    match counts.split_once(',') {
+        None => parse_count(&counts).map(|count| (count, Some(count))),
+        Some((min, "")) => parse_count(min).map(|min| (min, None)),
+        Some((min, max)) => {
+            let (min, max) = (parse_count(min)?, parse_count(max)?);
+            (min <= max).then_some((min, Some(max)))
+        }
+    }
+}
+
+/// Returns the index just after the bounded repetition whose `{` is right before `pos`.
+fn repetition_end(chars: &[char], pos: usize) -> Option<usize> {
+    let counts_len = chars[pos..].iter().position(|&c| c == '}')?;
+    repetition_bounds(&chars[pos..pos + counts_len])?;
+    Some(pos + counts_len + 1)
+}
+
+/// Returns an error if a bounded repetition of `pattern` has a count above `limit`.
+fn check_repetition_limit(pattern: &str, limit: usize) -> Result<(), RegexError> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut pos = 0;
+    while pos < chars.len() {
+        match chars[pos] {
+            '\\' => pos = alphabet::escape_end(&chars, pos + 1),
+            '(' => pos = comment_end(&chars, pos + 1).unwrap_or(pos + 1),
+            '[' => pos = alphabet::class_end(&chars, pos + 1).unwrap_or(pos + 1),
+            '{' if repetition_end(&chars, pos + 1).is_some() => {
+                let end = repetition_end(&chars, pos + 1).expect("Valid repetition");
+                let (min, max) = repetition_bounds(&chars[pos + 1..end - 1]).expect("Valid counts");
+                let count = max.unwrap_or(min);
+                if count > limit {
+                    return Err(RegexError::RepetitionLimitExceeded { count, limit });
+                }
+                pos = end;
+            }
+            _ => pos += 1,
+        }
+    }
+    Ok(())
+}
+
+/// Returns whether an alternative of `pattern` is empty, i.e. a `|` is next to another `|`,
+/// a group boundary or an end of the pattern.
+///
+/// This looks at the pattern as written, since in the normalised pattern every `?` is an
+/// empty alternative as well.
+fn has_empty_alternative(pattern: &str) -> bool {
+    let chars: Vec<char> = pattern.chars().collect();
+    // Whether the current alternative has no token yet and whether the last token is a `|`
+    let mut alternative_empty = true;
+    let mut after_bar = false;
+    let mut pos = 0;
+    while pos < chars.len() {
+        match chars[pos] {
+            '|' if alternative_empty => return true,
+            ')' if after_bar => return true,
+            '|' => {
+                pos += 1;
+                alternative_empty = true;
+                after_bar = true;
+                continue;
+            }
+            '(' => match comment_end(&chars, pos + 1) {
+                Some(end) => {
+                    pos = end;
+                    continue;
+                }
+                None => {
+                    pos = group_name(&chars, pos + 1).map_or(pos + 1, |(_, end)| end);
+                    alternative_empty = true;
+                    after_bar = false;
+                    continue;
+                }
+            },
+            '\\' => pos = alphabet::escape_end(&chars, pos + 1),
+            '[' => pos = alphabet::class_end(&chars, pos + 1).unwrap_or(pos + 1),
+            _ => pos += 1,
+        }
+        alternative_empty = false;
+        after_bar = false;
+    }
+    after_bar
+}
+
+/// Returns whether `pattern` has an unescaped `^` or `$` outside of classes and comments.
+///
+/// Only anchors at the very start and end of a pattern are supported, those are stripped
+/// before this is checked.
+fn has_inner_anchor(pattern: &str) -> bool {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut pos = 0;
+    while pos < chars.len() {
+        match chars[pos] {
+            '\\' => pos = alphabet::escape_end(&chars, pos + 1),
+            '(' => pos = comment_end(&chars, pos + 1).unwrap_or(pos + 1),
+            '[' => pos = alphabet::class_end(&chars, pos + 1).unwrap_or(pos + 1),
+            '^' | '$' => return true,
+            _ => pos += 1,
+        }
+    }
+    false
+}
+
+/// Returns the index just after the comment `(?#...)` whose `(` is right before `pos`.
+///
+/// A comment runs up to the next `)`, so it cannot contain one itself.
+fn comment_end(chars: &[char], pos: usize) -> Option<usize> {
+    if chars.get(pos..pos + 2)? != ['?', '#'] {
+        return None;
+    }
+    let comment_len = chars[pos + 2..].iter().position(|&c| c == ')')?;
+    Some(pos + 3 + comment_len)
+}
+
+/// Returns the name of every capture group of `pattern` in the order of their opening
+/// parenthesis, preceded by `None` for the whole match.
+fn capture_names(pattern: &str) -> Vec<Option<String>> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut names = vec![None];
+
+    let mut pos = 0;
+    while pos < chars.len() {
+        match chars[pos] {
+            '\\' => pos = alphabet::escape_end(&chars, pos + 1),
+            '(' => match comment_end(&chars, pos + 1) {
+                Some(end) => pos = end,
+                None => {
+                    names.push(group_name(&chars, pos + 1).map(|(name, _)| name));
+                    pos += 1;
+                }
+            },
+            '[' => pos = alphabet::class_end(&chars, pos + 1).unwrap_or(pos + 1),
+            _ => pos += 1,
+        }
+    }
+
+    names
+}
+
+/// Returns whether the `.` wildcard matches `c`.
+fn is_dot_char(c: char) -> bool {
+    c == ' ' || c.is_ascii_graphic()
+}
+
+/// Detects patterns of the form `.*` followed by a plain literal and returns the literal.
+fn dot_star_literal(pattern: &str) -> Option<String> {
+    let mut chars = pattern.strip_prefix(".*")?.chars();
+    let mut literal = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                'p' | 'u' => return None,
+                escaped => literal.push(escaped),
+            },
+            '(' | ')' | '|' | '*' | '+' | '?' | '.' | '[' | ']' | '{' | '}' | '^' | '$' => {
+                return None;
+            }
+            _ => literal.push(c),
+        }
+    }
+    (!literal.is_empty()).then_some(literal)
+}
+
+/// Leftmost-longest search for `.*literal` without walking the DFA.
+///
+/// The leftmost match starts where the run of wildcard characters in front of the first
+/// occurrence of `literal` begins. It then extends to the last occurrence that is still only
+/// preceded by wildcard characters from that start.
+fn find_dot_star_literal(text: &str, start_pos: usize, literal: &str) -> Option<(usize, usize)> {
+    let first_occurrence = start_pos + text[start_pos..].find(literal)?;
+
+    let match_start = text[start_pos..first_occurrence]
+        .char_indices()
+        .rev()
+        .find(|&(_, c)| !is_dot_char(c))
+        .map_or(start_pos, |(i, c)| start_pos + i + c.len_utf8());
+
+    let mut checked_until = first_occurrence;
+    let mut match_end = first_occurrence + literal.len();
+    let first_char_len = literal.chars().next().map_or(1, char::len_utf8);
+    let mut search_from = first_occurrence + first_char_len;
+
+    while let Some(offset) = text[search_from..].find(literal) {
+        let occurrence = search_from + offset;
+        if !text[checked_until..occurrence].chars().all(is_dot_char) {
+            break;
+        }
+        checked_until = occurrence;
+        match_end = occurrence + literal.len();
+        search_from = occurrence + first_char_len;
+    }
+
+    Some((match_start, match_end))
+}
+
+/// Returns the longest literal that every match of the normalised pattern has to contain.
+fn required_literal(normalised_regex: &str) -> Option<String> {
+    let chars: Vec<char> = normalised_regex.chars().collect();
+    let mut pos = 0;
+    required_literal_in_group(&chars, &mut pos)
+}
+
+/// Finds the longest required literal of the sequence starting at `pos`, up to the
+/// parenthesis closing the current group, and leaves `pos` on that parenthesis.
+fn required_literal_in_group(chars: &[char], pos: &mut usize) -> Option<String> {
+    let mut longest = String::new();
+    let mut run = String::new();
+    let mut has_alternation = false;
+    let keep_longest = |longest: &mut String, candidate: &str| {
+        if candidate.chars().count() > longest.chars().count() {
+            *longest = candidate.to_string();
+        }
+    };
+
+    while *pos < chars.len() && chars[*pos] != ')' {
+        let (literal_char, group_literal) = match chars[*pos] {
+            '|' => {
+                has_alternation = true;
+                *pos += 1;
+                (None, None)
+            }
+            '(' => {
+                *pos += 1;
+                let group_literal = required_literal_in_group(chars, pos);
+                *pos += 1; // skip ')'
+                (None, group_literal)
+            }
+            '\\' => {
+                let end = alphabet::escape_end(chars, *pos + 1);
+                let literal_char = (end == *pos + 2).then(|| chars[*pos + 1]);
+                *pos = end;
+                (literal_char, None)
+            }
+            '[' => {
+                *pos = alphabet::class_end(chars, *pos + 1).unwrap_or(*pos + 1);
+                (None, None)
+            }
+            c => {
+                *pos += 1;
+                ((c != '.').then_some(c), None)
+            }
+        };
+
+        // An atom under a star may be skipped, so it interrupts the literal
+        let starred = chars.get(*pos) == Some(&'*');
+        while chars.get(*pos) == Some(&'*') {
+            *pos += 1;
+        }
+
+        match literal_char {
+            Some(c) if !starred => run.push(c),
+            _ => {
+                keep_longest(&mut longest, &run);
+                run.clear();
+                if let Some(group_literal) = group_literal.filter(|_| !starred) {
+                    keep_longest(&mut longest, &group_literal);
+                }
+            }
+        }
+    }
+    keep_longest(&mut longest, &run);
+
+    (!has_alternation && !longest.is_empty()).then_some(longest)
+}
+
+/// Returns the byte offset from which a match containing `literal` has to be searched.
+///
+/// Returns `None` if `literal` does not occur at or after `start_pos`. If matches are at most
+/// `max_match_len` chars long, no match can start further back than that before the end of
+/// the first occurrence.
+fn prefilter_start(
+    text: &str,
+    start_pos: usize,
+    literal: &str,
+    max_match_len: Option<usize>,
+) -> Option<usize> {
+    let occurrence_end = start_pos + text[start_pos..].find(literal)? + literal.len();
+    let Some(max_match_len) = max_match_len else {
+        return Some(start_pos);
+    };
+
+    let earliest_start = text[start_pos..occurrence_end]
+        .char_indices()
+        .rev()
+        .nth(max_match_len.saturating_sub(1))
+        .map_or(start_pos, |(i, _)| start_pos + i);
+    Some(earliest_start)
+}
+
+/// Rewrites every `\Q...\E` quote of `pattern` into escapes of the characters that need one.
+///
+/// Everything up to the next `\E`, or the end of the pattern if there is none, is taken
+/// literally.
+fn expand_quotes(pattern: &str) -> Cow<'_, str> {
+    if !pattern.contains("\\Q") {
+        return Cow::Borrowed(pattern);
+    }
+
+    let mut expanded = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Q') => {
+                let rest = chars.as_str();
+                let (quoted, after) = rest.split_once("\\E").unwrap_or((rest, ""));
+                for quoted_char in quoted.chars() {
+                    if alphabet::META_CHARS.contains(&quoted_char) {
+                        expanded.push('\\');
+                    }
+                    expanded.push(quoted_char);
+                }
+                chars = after.chars();
+            }
+            Some(escaped) => {
+                expanded.push(c);
+                expanded.push(escaped);
+            }
+            None => expanded.push(c),
+        }
+    }
+    Cow::Owned(expanded)
+}
+
+/// Checks that `regex` is a pattern the constructions can compile.
+///
+/// Every quantifier `*`, `+` or `?` has to follow an atom, i.e. a character, an escape or a
+/// group, so it cannot start the pattern, a group or an alternative. A quantifier may be
+/// followed by a single `?` marking it lazy, as in `a*?`. Matches are always leftmost-longest,
+/// so a lazy quantifier matches the same as a greedy one. Any other stacking of quantifiers
+/// such as `a**`, `a+*` or `a?*` is rejected rather than read as `(a+)*`, since other engines
+/// give `a*+` a possessive meaning this crate does not have. A bounded repetition `{n}`,
+/// `{n,}` or `{n,m}` is a quantifier as well and needs `n <= m`.
+///
+/// A raw NUL character is rejected anywhere in the pattern, even quoted or escaped, since it
+/// usually means a pattern from C was cut off or padded. The escape `\u{0}` matches NUL.
+///
+/// # Returns
+///
+/// Returns the first problem found in the pattern. Escapes are only checked to escape
+/// something, unknown properties and invalid codepoints are reported by the constructions.
+pub fn validate_regex(regex: &str) -> Result<(), RegexError> {
+    if regex.contains('\0') {
+        return Err(RegexError::NulInPattern);
+    }
+    let regex: &str = &expand_quotes(regex);
     if regex.is_empty() {
-        return false;
+        return Err(RegexError::EmptyPattern);
+    }
+
+    let mut open_paren_count = 0;
+    // Whether the previous token is an atom a quantifier can apply to
+    let mut can_quantify = false;
+    // Whether the previous token is a quantifier that can still be marked lazy
+    let mut can_be_lazy = false;
+    // Whether the previous token is a quantifier, lazy or not
+    let mut follows_quantifier = false;
+    let mut group_names: HashSet<String> = HashSet::new();
+
+    let mut chars = regex.chars().peekable();
+    while let Some(c) = chars.next() {
+        let was_quantifier = std::mem::replace(&mut follows_quantifier, false);
+        match c {
+            '(' => {
+                if chars.next_if_eq(&'?').is_some() {
+                    match chars.next() {
+                        // Comments `(?#...)` are ignored up to the next `)`
+                        Some('#') => {
+                            if !chars.any(|c| c == ')') {
+                                return Err(RegexError::UnbalancedParens);
+                            }
+                            follows_quantifier = was_quantifier;
+                            continue;
+                        }
+                        // Named groups `(?P<name>...)` need a unique name of word characters
+                        Some('P') if chars.next() == Some('<') => {}
+                        _ => return Err(RegexError::InvalidGroup),
+                    }
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('>') => break,
+                            Some(c) if c.is_alphanumeric() || c == '_' => name.push(c),
+                            _ => return Err(RegexError::InvalidGroup),
+                        }
+                    }
+                    if name.is_empty() || !group_names.insert(name) {
+                        return Err(RegexError::InvalidGroup);
+                    }
+                }
+                open_paren_count += 1;
+                can_quantify = false;
+                can_be_lazy = false;
+            }
+            ')' => {
+                if open_paren_count == 0 {
+                    return Err(RegexError::UnbalancedParens);
+                }
+                open_paren_count -= 1;
+                can_quantify = true;
+                can_be_lazy = false;
+            }
+            '|' => {
+                can_quantify = false;
+                can_be_lazy = false;
+            }
+            '{' => {
+                let mut counts: Vec<char> = Vec::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => counts.push(c),
+                        None => return Err(RegexError::InvalidRepetition),
+                    }
+                }
+                if was_quantifier {
+                    return Err(RegexError::StackedQuantifier(c));
+                }
+                if !can_quantify {
+                    return Err(RegexError::DanglingQuantifier);
+                }
+                if repetition_bounds(&counts).is_none() {
+                    return Err(RegexError::InvalidRepetition);
+                }
+                can_quantify = false;
+                can_be_lazy = true;
+                follows_quantifier = true;
+            }
+            '*' | '+' | '?' => {
+                if can_quantify {
+                    can_quantify = false;
+                    can_be_lazy = true;
+                } else if c == '?' && can_be_lazy {
+                    can_be_lazy = false;
+                } else if was_quantifier {
+                    return Err(RegexError::StackedQuantifier(c));
+                } else {
+                    return Err(RegexError::DanglingQuantifier);
+                }
+                follows_quantifier = true;
+            }
+            '[' => {
+                // Classes `[...]` need at least one member and a closing `]`, so a leading `]`
+                // is a member
+                let mut class: Vec<char> = Vec::new();
+                loop {
+                    match chars.next() {
+                        Some(']') if !class.is_empty() => break,
+                        None => return Err(RegexError::UnclosedClass),
+                        Some('\\') => {
+                            let Some(escaped) = chars.next() else {
+                                return Err(RegexError::UnclosedClass);
+                            };
+                            class.extend(['\\', escaped]);
+                        }
+                        Some(c) => class.push(c),
+                    }
+                }
+                alphabet::class_items(&class)?;
+                can_quantify = true;
+                can_be_lazy = false;
+            }
+            '\\' => {
+                // Handle escaped characters: ensure there's a character after the escape
+                let Some(escaped) = chars.next() else {
+                    return Err(RegexError::TrailingEscape);
+                };
+                // The braces of `\p{...}` and `\u{...}` are not a repetition
+                if matches!(escaped, 'p' | 'u') && chars.next_if_eq(&'{').is_some() {
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            break;
+                        }
+                    }
+                }
+                can_quantify = true;
+                can_be_lazy = false;
+            }
+
+            _ => {
+                can_quantify = true;
+                can_be_lazy = false;
+            }
+        }
+    }
+
+    if open_paren_count != 0 {
+        return Err(RegexError::UnbalancedParens);
+    }
+    Ok(())
+}
+
+/// Returns whether `regex` is a pattern the constructions can compile, see `validate_regex`
+/// for the rules and the reason a pattern is rejected.
+pub fn is_valid_regex(regex: &str) -> bool {
+    validate_regex(regex).is_ok()
+}
+
+/// Rewrites `regex` into the core syntax the constructions understand, expanding `+`, `?`,
+/// bounded repetitions and `.` into stars, alternations, groups and classes.
+///
+/// A bounded repetition is written out, e.g. `a{2,4}` becomes `aa(a|)(a|)` and `a{2,}`
+/// becomes `aaa*`.
+///
+/// A trailing backslash without an escaped character is kept as is instead of being dropped,
+/// so a pattern that skipped `is_valid_regex` is still rejected by the constructions.
+pub fn normalise_regex(regex: &str) -> String {
+    let chars: Vec<char> = expand_quotes(regex).chars().collect();
+    let mut normalised = String::new();
+    // Byte offset in `normalised` where the atom a following quantifier applies to starts
+    let mut last_atom_start: Option<usize> = None;
+    let mut group_starts: Vec<usize> = Vec::new();
+    // Whether the previous token is a quantifier, a `?` after it only marks it lazy
+    let mut follows_quantifier = false;
+
+    let mut pos = 0;
+    while pos < chars.len() {
+        let curr_char = chars[pos];
+        pos += 1;
+        let was_quantifier = std::mem::replace(&mut follows_quantifier, false);
+
+        match curr_char {
+            '\\' => {
+                // TODO: Implement further parsing features here (e.g. \w \d)
+                let end = alphabet::escape_end(&chars, pos);
+                last_atom_start = Some(normalised.len());
+                normalised.push(curr_char);
+                normalised.extend(&chars[pos..end]);
+                pos = end;
+            }
+            // A class stays a single atom, the constructions parse it into character ranges
+            '[' if alphabet::class_end(&chars, pos).is_some() => {
+                let end = alphabet::class_end(&chars, pos).expect("Closed class");
+                last_atom_start = Some(normalised.len());
+                normalised.push(curr_char);
+                normalised.extend(&chars[pos..end]);
+                pos = end;
+            }
+            '(' => {
+                // Comments are dropped, a following quantifier applies to the atom before them
+                if let Some(end) = comment_end(&chars, pos) {
+                    pos = end;
+                    follows_quantifier = was_quantifier;
+                    continue;
+                }
+                group_starts.push(normalised.len());
+                normalised.push(curr_char);
+                last_atom_start = None;
+                // Names only matter for captures, the group itself is a plain group
+                if let Some((_, end)) = group_name(&chars, pos) {
+                    pos = end;
+                }
+            }
+            ')' => {
+                normalised.push(curr_char);
+                last_atom_start = group_starts.pop();
+            }
+            '|' => {
+                normalised.push(curr_char);
+                last_atom_start = None;
+            }
+            '+' => {
+                if let Some(atom_start) = last_atom_start {
+                    let atom = normalised[atom_start..].to_string();
+                    normalised.push_str(&atom);
+                }
+                normalised.push('*');
+                follows_quantifier = true;
+            }
+            '{' if last_atom_start.is_some() && repetition_end(&chars, pos).is_some() => {
+                let end = repetition_end(&chars, pos).expect("Valid repetition");
+                let (min, max) = repetition_bounds(&chars[pos..end - 1]).expect("Valid counts");
+                let atom_start = last_atom_start.expect("Atom to repeat");
+                let atom = normalised.split_off(atom_start);
+                for _ in 0..min {
+                    normalised.push_str(&atom);
+                }
+                match max {
+                    Some(max) => {
+                        for _ in min..max {
+                            normalised.push('(');
+                            normalised.push_str(&atom);
+                            normalised.push_str("|)");
+                        }
+                    }
+                    None => {
+                        normalised.push_str(&atom);
+                        normalised.push('*');
+                    }
+                }
+                pos = end;
+                follows_quantifier = true;
+            }
+            // Matches are leftmost-longest either way, so laziness is dropped
+            '?' if was_quantifier => {}
+            '?' => {
+                if let Some(atom_start) = last_atom_start {
+                    normalised.insert(atom_start, '(');
+                }
+                normalised.push_str("|)");
+                follows_quantifier = true;
+            }
+            '.' => {
+                last_atom_start = Some(normalised.len());
+                // Every printable ASCII character, see `is_dot_char`
+                normalised.push_str("[ -~]");
+            }
+            _ => {
+                if curr_char == '*' {
+                    follows_quantifier = true;
+                } else {
+                    last_atom_start = Some(normalised.len());
+                }
+                normalised.push(curr_char);
+            }
+        }
+    }
+    normalised
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_regex_basic_test() {
+        let regex = "(a|b)*";
+        assert!(is_valid_regex(regex), "Expected valid regex.");
+    }
+
+    #[test]
+    fn invalid_empty_regex_test() {
+        let regex = "";
+        assert!(!is_valid_regex(regex), "Expected invalid regex (empty).");
+    }
+
+    #[test]
+    fn invalid_unbalanced_parentheses_test() {
+        let regex1 = "(a|b";
+        let regex2 = "a|b)";
+        assert!(
+            !is_valid_regex(regex1),
+            "Expected invalid regex (unbalanced parentheses)."
+        );
+        assert!(
+            !is_valid_regex(regex2),
+            "Expected invalid regex (unbalanced parentheses)."
+        );
+    }
+
+    #[test]
+    fn invalid_operator_placement_test() {
+        let regex1 = "*a";
+        let regex2 = "(+abc|x)";
+        assert!(
+            !is_valid_regex(regex1),
+            "Expected invalid regex (invalid quantifier placement)."
+        );
+        assert!(
+            !is_valid_regex(regex2),
+            "Expected invalid regex (invalid alternation placement)."
+        );
+    }
+
+    #[test]
+    fn quantifier_stacking_test() {
+        for pattern in ["a*?", "a+?", "a??", "(ab)*?c", "a*(?#lazy)?"] {
+            assert!(is_valid_regex(pattern), "{pattern} should be valid");
+        }
+        for pattern in [
+            "a**", "a+*", "a?*", "a*+", "a++", "a?+", "a*??", "?a", "(?a)", "a|?", "a|*b",
+        ] {
+            assert!(!is_valid_regex(pattern), "{pattern} should be invalid");
+        }
+        // Stacked quantifiers are not read as a quantified group, the group has to be written
+        for (pattern, quantifier) in [
+            ("a+*", '*'),
+            ("a*+", '+'),
+            ("a?*", '*'),
+            ("a*??", '?'),
+            ("a{2}*", '*'),
+            ("a*{2}", '{'),
+            ("a+(?#x)*", '*'),
+        ] {
+            assert_eq!(
+                validate_regex(pattern),
+                Err(RegexError::StackedQuantifier(quantifier)),
+                "Failed for pattern: {pattern}"
+            );
+        }
+        assert_eq!(validate_regex("*a"), Err(RegexError::DanglingQuantifier));
+        assert_eq!(validate_regex("(|+)"), Err(RegexError::DanglingQuantifier));
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("(a+)*", construction).expect("Valid regex");
+            assert!(regex.is_match(""));
+            assert!(regex.is_match("aaa"));
+        }
+
+        assert_eq!(normalise_regex("a+?"), "aa*");
+        assert_eq!(normalise_regex("a??b*?"), "(a|)b*");
+
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("a+?b", construction).expect("Valid regex");
+            assert!(regex.is_match("aab"));
+            assert!(!regex.is_match("b"));
+            assert_eq!(regex.find("xaab"), Some("aab"));
+        }
+    }
+
+    #[test]
+    fn bounded_repetition_test() {
+        assert_eq!(normalise_regex("a{3}"), "aaa");
+        assert_eq!(normalise_regex("a{2,}"), "aaa*");
+        assert_eq!(normalise_regex("a{2,4}"), "aa(a|)(a|)");
+        assert_eq!(normalise_regex("x(ab){0,1}?"), "x((ab)|)");
+        assert_eq!(normalise_regex("[a-c]{2}"), "[a-c][a-c]");
+
+        for pattern in [
+            "a{3}",
+            "a{2,}",
+            "a{2,4}",
+            "a{0}",
+            "(ab){1,2}?",
+            r"\p{L}{2}",
+            "[{]",
+        ] {
+            assert!(is_valid_regex(pattern), "{pattern} should be valid");
+        }
+        for pattern in [
+            "a{}", "a{2,1}", "{3}", "a{,2}", "a{x}", "a{2", "a*{2}", "(|{2})",
+        ] {
+            assert!(!is_valid_regex(pattern), "{pattern} should be invalid");
+        }
+
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("^(ab){2,3}c$", construction).expect("Valid regex");
+            for text in ["ababc", "abababc"] {
+                assert!(regex.is_match(text), "Failed for input: {text}");
+            }
+            for text in ["abc", "ababababc"] {
+                assert!(!regex.is_match(text), "Failed for input: {text}");
+            }
+
+            let regex = Regex::new("[0-9]{4}", construction).expect("Valid regex");
+            assert_eq!(regex.find("in 12 or 20245"), Some("2024"));
+
+            // Counts are checked before anything is expanded
+            let error = Regex::new("a{100000}", construction).err();
+            assert_eq!(
+                error,
+                Some(RegexError::RepetitionLimitExceeded {
+                    count: 100000,
+                    limit: 1000
+                })
+            );
+            let builder = RegexBuilder::new("a{2,3}", construction);
+            assert!(builder.repetition_limit(2).build().is_err());
+
+            // Digits of the counts are not literal chars of the pattern
+            let regex = RegexBuilder::new("A{2}", construction)
+                .char_map(|c| {
+                    if c == '2' {
+                        'x'
+                    } else {
+                        c.to_ascii_lowercase()
+                    }
+                })
+                .build()
+                .expect("Valid regex");
+            assert!(regex.is_match("aA"));
+        }
+    }
+
+    #[test]
+    fn valid_nested_parentheses_test() {
+        let regex = "((a|b)*c)";
+        assert!(
+            is_valid_regex(regex),
+            "Expected valid regex with nested parentheses."
+        );
+    }
+
+    #[test]
+    fn valid_escape_sequence_test() {
+        let regex = "a\\*b";
+        assert!(
+            is_valid_regex(regex),
+            "Expected valid regex with escape sequence."
+        );
+    }
+
+    #[test]
+    fn invalid_escape_sequence_test() {
+        let regex = "a\\";
+        assert!(
+            !is_valid_regex(regex),
+            "Expected invalid regex with unpaired escape."
+        );
+    }
+
+    #[test]
+    fn normalise_regex_test() {
+        let cases = [
+            (r"a+", r"aa*"),
+            (r"a\+", r"a\+"),
+            (r"a?", r"(a|)"),
+            (r"a\?", r"a\?"),
+            (r"(ab)?", r"((ab)|)"),
+            (r"\++", r"\+\+*"),
+            (r"\(?", r"(\(|)"),
+            (r".", "[ -~]"),
+        ];
+
+        for (input, expected) in cases {
+            let result = normalise_regex(input);
+            assert_eq!(result, expected, "Normalisation failed for input '{input}'");
+        }
+    }
+
+    #[test]
+    fn trailing_escape_test() {
+        assert_eq!(normalise_regex("a\\"), "a\\");
+        assert_eq!(normalise_regex("(a)+\\"), "(a)(a)*\\");
+        assert!(!is_valid_regex("a\\"));
+
+        assert!(ThompsonDfa::new("a\\", None).is_err());
+        assert!(GlushkovDfa::new("a\\", None).is_err());
+
+        // Without validation the dangling escape still fails when the alphabet is built
+        assert!(alphabet::pattern_classes(&normalise_regex("a\\")).is_err());
+    }
+
+    #[test]
+    fn capture_names_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object =
+                Regex::new("(?P<a>x)(y)(?P<b>z)", construction).expect("Valid regex");
+            assert_eq!(
+                regex_object.capture_names(),
+                vec![None, Some("a".to_string()), None, Some("b".to_string())]
+            );
+            assert!(regex_object.is_match("xyz"));
+            assert!(!regex_object.is_match("Pxyz"));
+        }
+
+        let regex_object = Regex::new(
+            r"\((?P<outer>a(?P<inner_1>b)+)?",
+            ConstructionType::Glushkov,
+        )
+        .expect("Valid regex");
+        assert_eq!(
+            regex_object.capture_names(),
+            vec![None, Some("outer".to_string()), Some("inner_1".to_string())]
+        );
+        assert!(regex_object.is_match("(abb"));
+        assert_eq!(normalise_regex("(?P<n>a)+"), "(a)(a)*");
+
+        assert!(!is_valid_regex("(?P<>a)"));
+        assert!(!is_valid_regex("(?P<a-b>a)"));
+        assert!(!is_valid_regex("(?P<a>x)(?P<a>y)"));
+        assert!(!is_valid_regex("(?Pa)"));
+        assert!(!is_valid_regex("(?a)"));
+    }
+
+    #[test]
+    fn comment_test() {
+        assert!(is_valid_regex("a(?#the letter a)b"));
+        assert!(is_valid_regex("(?#only a comment)a"));
+        assert!(!is_valid_regex("a(?#unterminated"));
+        assert!(!is_valid_regex("(?#comment)*a"));
+        assert!(!is_valid_regex("a*(?#comment)*"));
+        assert_eq!(normalise_regex("a(?#(x|y)b"), "ab");
+        assert_eq!(normalise_regex("a(?#plus)+"), "aa*");
+
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("a(?#the letter a)b", construction).expect("Valid regex");
+            assert!(regex.is_match("ab"));
+            for text in ["", "a", "b", "a(?#the letter a)b", "athe letter ab"] {
+                assert!(!regex.is_match(text));
+            }
+
+            let regex = Regex::new("(?#x)(?P<n>a)(?#y)(b)", construction).expect("Valid regex");
+            assert_eq!(
+                regex.capture_names(),
+                vec![None, Some("n".to_string()), None]
+            );
+        }
+    }
+
+    #[test]
+    fn is_match_test() {
+        let regex_object = Regex::new("a(a|b)*", ConstructionType::Thompson).expect("Valid regex");
+
+        let success_strings = vec!["abababaaaababa", "a"];
+        for string in success_strings {
+            assert!(regex_object.is_match(string));
+        }
+
+        let failing_strings = vec!["abc", "x"];
+        for string in failing_strings {
+            assert!(!regex_object.is_match(string));
+        }
+    }
+
+    #[test]
+    fn foreign_char_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("(a|b)*c", construction).expect("Valid regex");
+            assert_eq!(regex.symbols, HashSet::from(['a', 'b', 'c']));
+            assert!(regex.is_match("abac"));
+            assert!(!regex.is_match("abxc"));
+            assert!(!regex.is_match("(a|b)*c"));
+
+            let regex = Regex::new(r"\p{Lu}+", construction).expect("Valid regex");
+            assert!(regex.is_match("ÄB"));
+            assert!(!regex.is_match("Äb"));
+
+            let regex = RegexBuilder::new("ab", construction)
+                .char_map(|c| c.to_ascii_lowercase())
+                .build()
+                .expect("Valid regex");
+            assert!(regex.is_match("AB"));
+            assert!(!regex.is_match("AC"));
+        }
+    }
+
+    #[test]
+    fn is_match_bytes_test() {
+        let invalid = b"ab\xffab";
+
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("(a|b)*", construction).expect("Valid regex");
+            assert_eq!(regex.is_match_bytes(b"abab", Utf8Mode::Ascii), Ok(true));
+            assert_eq!(regex.is_match_bytes(invalid, Utf8Mode::Ascii), Ok(false));
+            assert_eq!(regex.is_match_bytes(invalid, Utf8Mode::Lossy), Ok(false));
+
+            // A pattern beyond ASCII is matched on the input decoded as UTF-8
+            let regex = Regex::new(r"ab\p{L}", construction).expect("Valid regex");
+            assert_eq!(regex.is_match_bytes(b"abc", Utf8Mode::Ascii), Ok(true));
+            assert_eq!(
+                regex.is_match_bytes(b"ab\xc3\xa9", Utf8Mode::Ascii),
+                Ok(true)
+            );
+            assert_eq!(
+                regex.is_match_bytes(b"ab\xc3", Utf8Mode::Ascii),
+                Err(RegexError::InvalidUtf8 { offset: 2 })
+            );
+            assert_eq!(
+                regex.is_match_bytes(b"ab\xc3\xa9", Utf8Mode::Lossy),
+                Ok(true)
+            );
+        }
+
+        let regex = Regex::new("ab\u{FFFD}ab", ConstructionType::Thompson).expect("Valid regex");
+        assert_eq!(
+            regex.is_match_bytes(invalid, Utf8Mode::Ascii),
+            Err(RegexError::InvalidUtf8 { offset: 2 })
+        );
+        assert_eq!(regex.is_match_bytes(invalid, Utf8Mode::Lossy), Ok(true));
+        assert_eq!(
+            regex.is_match_bytes(b"ab\xf0\x9fab", Utf8Mode::Lossy),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn quantifier_precedence_test() {
+        let cases = [
+            ("ab*", vec!["a", "ab", "abbb"], vec!["", "abab", "b"]),
+            ("(ab)*", vec!["", "ab", "abab"], vec!["a", "abb", "aba"]),
+            ("ab+", vec!["ab", "abbb"], vec!["a", "abab"]),
+            ("(ab)+", vec!["ab", "abab"], vec!["", "abb"]),
+            ("a|b*", vec!["", "a", "bb"], vec!["ab", "abb"]),
+            (
+                "a(bc)*d",
+                vec!["ad", "abcbcd"],
+                vec!["abcd*", "abccd", "abd"],
+            ),
+        ];
+
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            for (pattern, matching, non_matching) in &cases {
+                let regex = Regex::new(pattern, construction).expect("Valid regex");
+                for text in matching {
+                    assert!(regex.is_match(text), "{pattern} should match {text:?}");
+                }
+                for text in non_matching {
+                    assert!(!regex.is_match(text), "{pattern} should not match {text:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn line_test() {
+        let text = "error: disk full\r\nwarning: low memory\nerror: timeout\n\nerror\n";
+
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("error(: \\p{L}+)*", construction).expect("Valid regex");
+            assert_eq!(
+                regex.find_lines(text),
+                vec!["error: disk full", "error: timeout", "error"]
+            );
+            let whole_lines: Vec<&str> = text
+                .split_inclusive('\n')
+                .filter(|line| regex.matches_whole_line(line))
+                .collect();
+            assert_eq!(whole_lines, vec!["error: timeout\n", "error\n"]);
+
+            // A nullable pattern finds an empty match on every line, including empty ones
+            let regex = Regex::new("x*", construction).expect("Valid regex");
+            assert_eq!(regex.find_lines("a\n\nb").len(), 3);
+            assert!(regex.matches_whole_line("\n"));
+        }
+    }
+
+    #[cfg(feature = "debug")]
+    #[test]
+    fn normalised_pattern_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("a+", construction).expect("Valid regex");
+            assert_eq!(regex.normalised_pattern(), Some("aa*"));
+
+            let regex = Regex::new("(?P<x>b)?", construction).expect("Valid regex");
+            assert_eq!(regex.normalised_pattern(), Some("((b)|)"));
+        }
+        assert_eq!(Regex::from_examples(&["a"]).normalised_pattern(), None);
+    }
+
+    #[cfg(feature = "debug")]
+    #[test]
+    fn then_literal_normalised_pattern_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("a+", construction).expect("Valid regex");
+            let extended = regex.then_literal("bc");
+            // The DFA was extended directly, no pattern was normalised for it
+            assert_eq!(extended.normalised_pattern(), None);
+            assert!(extended.is_match("aabc"));
+            assert_eq!(regex.normalised_pattern(), Some("aa*"));
+        }
+    }
+
+    #[test]
+    fn is_finite_language_test() {
+        let cases = [
+            ("(a|b)", true),
+            ("a*", false),
+            ("abc", true),
+            ("ab?(c|de)", true),
+            ("x(a|b)+", false),
+            ("(ab*|c)d", false),
+        ];
+
+        for (pattern, finite) in cases {
+            for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+                let regex_object = Regex::new(pattern, construction).expect("Valid regex");
+                assert_eq!(
+                    regex_object.is_finite_language(),
+                    finite,
+                    "Failed for pattern: {pattern}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn match_len_test() {
+        let cases = [
+            ("abc", Some(3), Some(3)),
+            ("a|bcd|()", Some(0), Some(3)),
+            ("ab*c", Some(2), None),
+            ("(a|b)*", Some(0), None),
+            ("x(yz|w)?", Some(1), Some(3)),
+        ];
+
+        for (pattern, min_len, max_len) in cases {
+            for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+                let regex_object = Regex::new(pattern, construction).expect("Valid regex");
+                assert_eq!(regex_object.min_match_len(), min_len, "Min for '{pattern}'");
+                assert_eq!(regex_object.max_match_len(), max_len, "Max for '{pattern}'");
+            }
+        }
+
+        let regex_object = Regex::new("abc", ConstructionType::Thompson).expect("Valid regex");
+        assert!(!regex_object.is_match(&"abc".repeat(33)));
+        assert!(!regex_object.is_match("ab"));
+        assert!(!regex_object.is_match(""));
+        assert!(regex_object.is_match("abc"));
+
+        // Multibyte chars must not be rejected by the byte length bounds
+        let regex_object =
+            Regex::new(r"\p{L}\p{L}", ConstructionType::Glushkov).expect("Valid regex");
+        assert!(regex_object.is_match("éö"));
+        assert!(regex_object.is_match("\u{10400}\u{10401}"));
+    }
+
+    #[test]
+    fn find_test() {
+        let regex_object = Regex::new("abc", ConstructionType::Thompson).expect("Valid regex");
+        let test_cases = vec![
+            ("abcd", Some("abc")),
+            ("xyzabc", Some("abc")),
+            ("abc", Some("abc")),
+            ("ac", None),
+            ("def", None),
+            ("aabc", Some("abc")),
+        ];
+
+        for (text, expected) in test_cases {
+            let result = regex_object.find(text);
+            assert_eq!(result, expected, "Failed for input: {text}");
+        }
+    }
+
+    #[test]
+    fn find_leftmost_longest_test() {
+        let test_cases = vec![
+            ("abcd|c", "abcd", Some("abcd")),
+            ("abcd|c", "abce", Some("c")),
+            ("a*b", "aaaaaaaac", None),
+            ("a*b", "aaaacaab", Some("aab")),
+            ("(ab)*c", "abababx", None),
+        ];
+
+        for (pattern, text, expected) in test_cases {
+            for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+                let regex_object = Regex::new(pattern, construction).expect("Valid regex");
+                assert_eq!(
+                    regex_object.find(text),
+                    expected,
+                    "Failed for pattern '{pattern}' on input: {text}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn dot_matches_printable_ascii_test() {
+        let regex_object = Regex::new(".", ConstructionType::Thompson).expect("Valid regex");
+        for c in (0..128u8).map(char::from).chain(['é', '\u{1F600}']) {
+            assert_eq!(
+                regex_object.is_match(&c.to_string()),
+                is_dot_char(c),
+                "Mismatch for {c:?}"
+            );
+        }
+        assert!(!regex_object.is_match(""));
+    }
+
+    #[test]
+    fn dot_star_literal_test() {
+        assert_eq!(dot_star_literal(".*needle"), Some("needle".to_string()));
+        assert_eq!(dot_star_literal(r".*a\.b"), Some("a.b".to_string()));
+        assert_eq!(dot_star_literal(".*"), None);
+        assert_eq!(dot_star_literal(".*a+"), None);
+        assert_eq!(dot_star_literal("a.*b"), None);
+
+        let texts = [
+            "",
+            "aa",
+            "xaab",
+            "aaa",
+            "xx\naab|ab",
+            "é abba\naa b",
+            "b\nb\na",
+            "ab|ba\tab",
+        ];
+        // The rewrite is independent of the construction, Glushkov just compiles `.*` faster
+        for pattern in [".*ab", ".*aa", ".*a", ".*ba"] {
+            let regex_object =
+                Regex::new(pattern, ConstructionType::Glushkov).expect("Valid regex");
+            assert!(regex_object.dot_star_literal.is_some());
+            for text in texts {
+                for start_pos in text.char_indices().map(|(i, _)| i) {
+                    assert_eq!(
+                        regex_object.find_at(text, start_pos),
+                        regex_object.dfa().find_at(text, start_pos),
+                        "Mismatch for '{pattern}' on {text:?} from {start_pos}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn valid_next_chars_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new("a(b|c)", construction).expect("Valid regex");
+            assert_eq!(regex_object.valid_next_chars(""), vec!['a']);
+            assert_eq!(regex_object.valid_next_chars("a"), vec!['b', 'c']);
+            assert!(regex_object.valid_next_chars("ac").is_empty());
+            assert!(regex_object.valid_next_chars("x").is_empty());
+            assert!(regex_object.valid_next_chars("xa").is_empty());
+        }
+
+        let regex_object =
+            Regex::new(r"x*\p{Lu}", ConstructionType::Glushkov).expect("Valid regex");
+        let next_chars = regex_object.valid_next_chars("xx");
+        assert!(next_chars.contains(&'x'));
+        assert!(next_chars.contains(&'Ä'));
+        assert!(!next_chars.contains(&'a'));
+        assert!(next_chars.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn transitions_for_state_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("a(b|c)", construction).expect("Valid regex");
+
+            let start_transitions = regex.transitions_for_state(regex.start_state());
+            assert_eq!(start_transitions.len(), 1);
+            let (symbol, next_state) = start_transitions[0];
+            assert_eq!(symbol, 'a');
+
+            let next_transitions = regex.transitions_for_state(next_state);
+            let symbols: Vec<char> = next_transitions.iter().map(|&(c, _)| c).collect();
+            assert_eq!(symbols, vec!['b', 'c']);
+            // Both alternatives end in the same accepting state after minimisation
+            assert_eq!(next_transitions[0].1, next_transitions[1].1);
+            assert!(
+                regex
+                    .transitions_for_state(next_transitions[0].1)
+                    .is_empty()
+            );
+
+            assert!(regex.transitions_for_state(u32::MAX).is_empty());
+        }
+    }
+
+    #[test]
+    fn find_match_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new(r"b\u{E9}+", construction).expect("Valid regex");
+            // `ä` and `é` take two bytes, `😀` four
+            let text = "ä😀bééc";
+            let found = regex.find_match(text).expect("Match");
+            assert_eq!((found.start(), found.end()), (6, 11));
+            assert_eq!(found.as_str(), "béé");
+            assert_eq!(&text[found.start()..found.end()], found.as_str());
+            assert_eq!(regex.find_match("bx"), None);
+
+            let regex = Regex::new("x*", construction).expect("Valid regex");
+            let found = regex.find_match("äx").expect("Empty match");
+            assert_eq!((found.start(), found.end(), found.as_str()), (0, 0, ""));
+        }
+    }
+
+    #[test]
+    fn char_slice_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("abc", construction).expect("Valid regex");
+            assert!(regex.is_match_chars(&['a', 'b', 'c']));
+            assert!(!regex.is_match_chars(&['a', 'b']));
+            assert!(!regex.is_match_chars(&['a', 'b', 'c', 'c']));
+            assert_eq!(regex.find_chars(&['a', 'b', 'c']), Some((0, 3)));
+            // Spans count chars, not the bytes of `é`
+            assert_eq!(
+                regex.find_chars(&['é', 'é', 'a', 'b', 'c', 'd']),
+                Some((2, 5))
+            );
+            assert_eq!(regex.find_chars(&['a', 'b']), None);
+
+            let regex = Regex::new("a*", construction).expect("Valid regex");
+            assert!(regex.is_match_chars(&[]));
+            assert_eq!(regex.find_chars(&['b', 'a']), Some((0, 0)));
+
+            let regex = Regex::new("^(ab|b)", construction).expect("Valid regex");
+            assert_eq!(regex.find_chars(&['a', 'b', 'b']), Some((0, 2)));
+            assert_eq!(regex.find_chars(&['c', 'b']), None);
+            let regex = Regex::new("(ab|b)$", construction).expect("Valid regex");
+            assert_eq!(regex.find_chars(&['ü', 'a', 'b']), Some((1, 3)));
+            let regex = Regex::new("^b$", construction).expect("Valid regex");
+            assert_eq!(regex.find_chars(&['b']), Some((0, 1)));
+            assert_eq!(regex.find_chars(&['b', 'b']), None);
+
+            let regex = Regex::new(r"(a|\u{E4})bc", construction).expect("Valid regex");
+            for text in ["", "abc", "xxabcabc", "äbcabc"] {
+                let chars: Vec<char> = text.chars().collect();
+                let expected = regex.find(text).map(|found| {
+                    let start = text[..text.find(found).expect("Found in text")]
+                        .chars()
+                        .count();
+                    (start, start + found.chars().count())
+                });
+                assert_eq!(regex.find_chars(&chars), expected, "Failed for {text}");
+            }
+        }
+    }
+
+    #[test]
+    fn step_debug_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("a*b", construction).expect("Valid regex");
+
+            let steps = regex.step_debug("aab");
+            let summary: Vec<(char, bool)> = steps.iter().map(|&(c, _, a)| (c, a)).collect();
+            assert_eq!(summary, vec![('a', false), ('a', false), ('b', true)]);
+            // a* loops on the start state
+            assert_eq!(steps[0].1, regex.start_state());
+            assert_eq!(steps[1].1, regex.start_state());
+            assert_ne!(steps[2].1, regex.start_state());
+
+            assert_eq!(regex.step_debug("abx").len(), 2);
+            assert_eq!(regex.step_debug("ba").len(), 1);
+            assert!(regex.step_debug("").is_empty());
+            assert!(regex.step_debug("xab").is_empty());
+        }
+    }
+
+    #[test]
+    fn is_prefix_acceptable_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new("abc", construction).expect("Valid regex");
+            for (prefix, acceptable) in [
+                ("", true),
+                ("a", true),
+                ("ab", true),
+                ("abc", true),
+                ("ax", false),
+                ("abcd", false),
+                ("b", false),
+            ] {
+                assert_eq!(
+                    regex_object.is_prefix_acceptable(prefix),
+                    acceptable,
+                    "Failed for input: {prefix}"
+                );
+            }
+        }
+
+        // A DFA that still has a dead state from which no match is reachable
+        let dfa = GlushkovDfa::from_parts(
+            HashMap::from([((0, 'a'), 1), ((0, 'x'), 2), ((2, 'x'), 2)]),
+            HashSet::from([1]),
+            Alphabet::default(),
+        );
+        let regex_object = Regex::from_dfa(DfaType::Glushkov(dfa), None);
+        assert!(regex_object.is_prefix_acceptable("a"));
+        assert!(!regex_object.is_prefix_acceptable("x"));
+    }
+
+    #[test]
+    fn size_limit_test() {
+        // The DFA has to remember the last 13 chars, so it needs thousands of states
+        let pattern = "(a|b)*a(a|b){12}";
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let result = RegexBuilder::new(pattern, construction)
+                .size_limit(1024)
+                .build();
+            assert_eq!(
+                result.err(),
+                Some(RegexError::StateLimitExceeded { limit: 1024 })
+            );
+
+            let regex = RegexBuilder::new("ab+c", construction)
+                .size_limit(1024)
+                .build()
+                .expect("Small DFA");
+            assert!(regex.approx_heap_bytes() <= 1024);
+            assert!(regex.is_match("abbc"));
+        }
+    }
+
+    #[test]
+    fn dead_state_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("abc", construction).expect("Valid regex");
+            assert_eq!(regex.dead_state(), None);
+
+            let completed = regex.complete_dfa();
+            let dead_state = completed.dead_state().expect("Completed DFA");
+            let dfa = completed.dfa();
+            assert_eq!(dfa.step(dfa.start_state(), 'b'), Some(dead_state));
+            for c in ['a', 'b', 'c'] {
+                assert_eq!(dfa.step(dead_state, c), Some(dead_state));
+            }
+            assert!(!dfa.get_accepting_states().contains(&dead_state));
+
+            for text in ["abc", "ab", "bc", "abcc"] {
+                assert_eq!(
+                    completed.is_match(text),
+                    regex.is_match(text),
+                    "Failed for input: {text}"
+                );
+            }
+            assert_eq!(completed.find("babcab"), Some("abc"));
+            assert!(!completed.is_prefix_acceptable("b"));
+            assert!(completed.is_prefix_acceptable("ab"));
+        }
+    }
+
+    #[test]
+    fn findall_anchored_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("a+|b+|c+", construction).expect("Valid regex");
+            assert_eq!(
+                regex.findall_anchored("aabbcc"),
+                (vec!["aa", "bb", "cc"], "")
+            );
+            assert_eq!(regex.findall_anchored("aaxbb"), (vec!["aa"], "xbb"));
+            assert_eq!(regex.findall_anchored("xa"), (vec![], "xa"));
+            assert_eq!(regex.findall_anchored(""), (vec![], ""));
+            assert_eq!(
+                regex.find_iter_anchored("abca").collect::<Vec<_>>(),
+                vec!["a", "b", "c", "a"]
+            );
+
+            // An empty match consumes nothing, so tokenising stops there
+            let regex = Regex::new("a*", construction).expect("Valid regex");
+            assert_eq!(regex.findall_anchored("aab"), (vec!["aa"], "b"));
+            assert_eq!(regex.findall_anchored("\u{E9}a"), (vec![], "\u{E9}a"));
+        }
+    }
+
+    #[test]
+    fn longest_match_at_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let span = |regex: &Regex, text, start_pos| {
+                regex
+                    .longest_match_at(text, start_pos)
+                    .map(|longest| (longest.start(), longest.end(), longest.as_str()))
+            };
+
+            let regex = Regex::new("a+", construction).expect("Valid regex");
+            assert_eq!(span(&regex, "aaab", 0), Some((0, 3, "aaa")));
+            assert_eq!(span(&regex, "aaab", 1), Some((1, 3, "aa")));
+            assert_eq!(span(&regex, "aaab", 3), None);
+            assert_eq!(span(&regex, "aaab", 4), None);
+
+            let regex = Regex::new("a|ab|abc", construction).expect("Valid regex");
+            assert_eq!(span(&regex, "xabcd", 1), Some((1, 4, "abc")));
+            assert_eq!(span(&regex, "xabd", 1), Some((1, 3, "ab")));
+
+            let regex = Regex::new("b*", construction).expect("Valid regex");
+            assert_eq!(span(&regex, "éa", 2), Some((2, 2, "")));
+        }
+    }
+
+    #[test]
+    fn char_len_match_at_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new(r"a[\u{E9}\u{1F600}]+", construction).expect("Valid regex");
+            let text = "xaé😀éb";
+            assert_eq!(regex.longest_match_at(text, 1).map(|m| m.end()), Some(10));
+            assert_eq!(regex.char_len_match_at(text, 1), Some(4));
+            assert_eq!(regex.char_len_match_at(text, 0), None);
+
+            let regex = Regex::new("b*", construction).expect("Valid regex");
+            assert_eq!(regex.char_len_match_at("éa", 2), Some(0));
+        }
+    }
+
+    #[test]
+    fn accepting_prefix_lengths_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("a|aa|aaa", construction).expect("Valid regex");
+            assert_eq!(regex.accepting_prefix_lengths("aaa"), vec![1, 2, 3]);
+            assert_eq!(regex.accepting_prefix_lengths("aaaa"), vec![1, 2, 3]);
+            assert!(regex.accepting_prefix_lengths("baaa").is_empty());
+            assert!(regex.accepting_prefix_lengths("").is_empty());
+
+            let regex = Regex::new(r"(\p{L}b)*", construction).expect("Valid regex");
+            assert_eq!(regex.accepting_prefix_lengths("ébab"), vec![0, 3, 5]);
+        }
+    }
+
+    #[test]
+    fn shortest_match_at_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new("a+", construction).expect("Valid regex");
+            assert_eq!(regex_object.shortest_match_at("aaa", 0), Some(1));
+            assert_eq!(regex_object.shortest_match_at("aaa", 2), Some(3));
+            assert_eq!(regex_object.shortest_match_at("aaa", 3), None);
+            assert_eq!(regex_object.shortest_match_at("baa", 0), None);
+        }
+
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new("ab(cd)*|xx*", construction).expect("Valid regex");
+            assert_eq!(regex_object.shortest_match_at("abcd", 0), Some(2));
+            assert_eq!(regex_object.shortest_match_at("éxx", 2), Some(3));
+            assert_eq!(regex_object.shortest_match_at("éxx", 0), None);
+        }
+    }
+
+    #[test]
+    fn find_all_test() {
+        let regex_object = Regex::new("abc*", ConstructionType::Thompson).expect("Valid regex");
+        let test_cases = vec![
+            ("abcd", vec!["abc"]),
+            ("ac", vec![]),
+            ("abcab", vec!["abc", "ab"]),
+        ];
+
+        for (text, expected) in test_cases {
+            let result = regex_object.findall(text);
+            assert_eq!(result, expected, "Failed for input: {text}");
+        }
+    }
+
+    #[test]
+    fn findall_order_test() {
+        let test_cases = vec![
+            ("a|ab|b", "abba", vec![(0, 2), (2, 3), (3, 4)]),
+            ("b", "aab aab", vec![(2, 3), (6, 7)]),
+            ("x(yz)*", "xyzyzxxy", vec![(0, 5), (5, 6), (6, 7)]),
+            (r"\p{Ll}", "AéBöC", vec![(1, 3), (4, 6)]),
+            ("ab", "bababab", vec![(1, 3), (3, 5), (5, 7)]),
+        ];
+
+        for (pattern, text, expected) in test_cases {
+            let thompson = Regex::new(pattern, ConstructionType::Thompson).expect("Valid regex");
+            let glushkov = Regex::new(pattern, ConstructionType::Glushkov).expect("Valid regex");
+
+            let spans = thompson.find_all_spans(text);
+            assert_eq!(spans, expected, "Failed for input: {text}");
+            assert_eq!(
+                glushkov.find_all_spans(text),
+                spans,
+                "Failed for input: {text}"
+            );
+            assert!(spans.windows(2).all(|pair| pair[0].1 <= pair[1].0));
+
+            let matches: Vec<&str> = expected
+                .iter()
+                .map(|&(start, end)| &text[start..end])
+                .collect();
+            assert_eq!(thompson.findall(text), matches, "Failed for input: {text}");
+            assert_eq!(glushkov.findall(text), matches, "Failed for input: {text}");
+        }
+    }
+
+    #[test]
+    fn multibyte_text_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("l+o", construction).expect("Valid regex");
+            assert_eq!(regex.find("héllo"), Some("llo"));
+            assert_eq!(regex.findall("héllo 😀lo wörld"), vec!["llo", "lo"]);
+            assert!(!regex.is_match("héllo"));
+
+            let regex = Regex::new(r"h\u{E9}l*", construction).expect("Valid regex");
+            assert!(regex.is_match("héll"));
+            assert_eq!(regex.find("ahé😀"), Some("hé"));
+            assert_eq!(regex.findall("héhél😀hél"), vec!["hé", "hél", "hél"]);
+
+            let regex = Regex::new(r"\u{1F600}+x?", construction).expect("Valid regex");
+            assert!(regex.is_match("😀😀x"));
+            assert!(!regex.is_match("😀é"));
+            assert_eq!(regex.find("é😀😀é"), Some("😀😀"));
+            assert_eq!(regex.findall("😀x😁😀"), vec!["😀x", "😀"]);
+        }
+    }
+
+    #[test]
+    fn unicode_escape_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new(r"a\u{1F600}+\u{62}", construction).expect("Valid regex");
+            assert!(regex.is_match("a😀😀b"));
+            assert!(!regex.is_match("ab"));
+
+            assert!(Regex::new(r"\u{D800}", construction).is_err());
+            assert!(Regex::new(r"\u{DFFF}", construction).is_err());
+            assert!(Regex::new(r"\u{110000}", construction).is_err());
+            assert!(Regex::new(r"\u{zz}", construction).is_err());
+        }
+    }
+
+    #[test]
+    fn quote_test() {
+        assert_eq!(expand_quotes(r"\Qa.b*\E"), r"a\.b\*");
+        assert_eq!(expand_quotes(r"x\Q(a|b)"), r"x\(a\|b\)");
+        assert_eq!(expand_quotes(r"\\Qa\E"), r"\\Qa\E");
+        assert_eq!(expand_quotes(r"\Q\\Q\E+"), r"\\\\Q+");
+        assert!(is_valid_regex(r"\Q(((\E"));
+        assert!(is_valid_regex(r"\Q*\E"));
+        assert!(!is_valid_regex(r"\Q\E"));
+        assert_eq!(normalise_regex(r"\Qa+b\E+"), r"a\+bb*");
+
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new(r"\Qa+b\E", construction).expect("Valid regex");
+            assert!(regex.is_match("a+b"));
+            for text in ["ab", "aab", "a+bb", ""] {
+                assert!(!regex.is_match(text));
+            }
+
+            let regex = Regex::new(r"(\Q(?P<x>\E)+", construction).expect("Valid regex");
+            assert!(regex.is_match("(?P<x>(?P<x>"));
+            assert_eq!(regex.capture_names(), vec![None, None]);
+            assert_eq!(regex.find("a.*b.*"), None);
+
+            let regex = Regex::new(r"\Qa.b*", construction).expect("Valid regex");
+            assert_eq!(regex.find("xa.b*y"), Some("a.b*"));
+        }
+    }
+
+    #[test]
+    fn escaped_digit_test() {
+        assert_eq!(normalise_regex(r"(a)\1+"), r"(a)\1\1*");
+        assert!(is_valid_regex(r"(a)\1"));
+
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            // Backreferences are not supported, `\1` is the literal digit
+            let regex_object = Regex::new(r"(a)\1", construction).expect("Valid regex");
+            assert!(regex_object.is_match("a1"));
+            assert!(!regex_object.is_match("aa"));
+            assert!(!regex_object.is_match("a\\1"));
+        }
+
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new(r"\0\9+", construction).expect("Valid regex");
+            assert!(regex_object.is_match("099"));
+            assert_eq!(regex_object.find("x09x"), Some("09"));
+        }
+    }
+
+    #[test]
+    fn nul_in_pattern_test() {
+        for pattern in ["\0", "a\0", "[a\0]", "\\\0", "\\Q\0\\E", "(?#\0)a"] {
+            assert_eq!(
+                validate_regex(pattern),
+                Err(RegexError::NulInPattern),
+                "Failed for pattern: {pattern:?}"
+            );
+        }
+
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            assert_eq!(
+                Regex::new("a\0", construction).err(),
+                Some(RegexError::NulInPattern)
+            );
+            let regex_object = Regex::new(r"a\u{0}+", construction).expect("Valid regex");
+            assert!(regex_object.is_match("a\0\0"));
+            assert!(!regex_object.is_match("a0"));
+        }
+    }
+
+    #[test]
+    fn bracket_class_test() {
+        assert_eq!(normalise_regex("[abc]*"), "[abc]*");
+        assert_eq!(normalise_regex(r"[a|.\]\p{N}]"), r"[a|.\]\p{N}]");
+        assert_eq!(normalise_regex(r"\[a]"), r"\[a]");
+        for pattern in ["[abc]", "[abc]+?", "x[(]", r"[\]]", r"\[", "a]", "[[]"] {
+            assert!(is_valid_regex(pattern), "{pattern} should be valid");
+        }
+        for pattern in ["[abc", "[]", "a[]*", r"[\]", "*[a]", r"[a\"] {
+            assert!(!is_valid_regex(pattern), "{pattern} should be invalid");
+        }
+
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("[abc]*", construction).expect("Valid regex");
+            assert!(regex.is_match("abcabc"));
+            assert!(regex.is_match(""));
+            assert!(!regex.is_match("abcx"));
+
+            let regex = Regex::new("x[ab]+y", construction).expect("Valid regex");
+            assert_eq!(regex.find("xy xaby"), Some("xaby"));
+
+            // Operators are literal inside a class
+            let regex = Regex::new("[a|*(]", construction).expect("Valid regex");
+            for text in ["a", "|", "*", "("] {
+                assert!(regex.is_match(text), "Failed for input: {text}");
+            }
+            assert!(!regex.is_match("b"));
+            assert_eq!(regex.capture_names(), vec![None]);
+
+            // A `|` in a class is a member, not an alternation of the class
+            let regex = Regex::new("[a|b]", construction).expect("Valid regex");
+            for text in ["a", "|", "b"] {
+                assert!(regex.is_match(text), "Failed for input: {text}");
+            }
+            assert!(!regex.is_match("ab"));
+
+            let regex = Regex::new(r"\[a[\]\\]\]", construction).expect("Valid regex");
+            assert!(regex.is_match("[a]]"));
+            assert!(regex.is_match("[a\\]"));
+            assert!(!regex.is_match("[aa]"));
+
+            let regex = Regex::new("^[ab]$", construction).expect("Valid regex");
+            assert_eq!(regex.find("b"), Some("b"));
+            let regex = Regex::new("^([|]|b)$", construction).expect("Valid regex");
+            assert!(regex.is_match("|"));
+
+            let regex = RegexBuilder::new("[.X]", construction)
+                .dot_matches_newline(true)
+                .char_map(|c| c.to_ascii_lowercase())
+                .build()
+                .expect("Valid regex");
+            assert!(regex.is_match("."));
+            assert!(regex.is_match("X"));
+            assert!(!regex.is_match("\n"));
+        }
+    }
+
+    #[test]
+    fn class_corner_case_test() {
+        for pattern in ["[]a]", "[]]", r"[a\]\-z]", "[a^]", "[az-]"] {
+            assert!(is_valid_regex(pattern), "{pattern} should be valid");
+        }
+        for pattern in ["[]", "[]a", r"[a\]"] {
+            assert!(!is_valid_regex(pattern), "{pattern} should be invalid");
+        }
+        assert_eq!(capture_names("[](]"), vec![None]);
+        assert!(!has_top_level_alternation("[]|]"));
+
+        let cases = [
+            // Escaped `]` and `-` are members, not the end of the class or a range
+            (r"[a\]\-z]", vec!["a", "]", "-", "z"], vec!["b", "y", "\\"]),
+            // A leading `]` is a member
+            ("[]a]", vec!["]", "a"], vec!["[", "b"]),
+            ("[]]+", vec!["]", "]]"], vec!["", "["]),
+            // A trailing `-` is literal
+            ("[az-]", vec!["a", "z", "-"], vec!["b", "y"]),
+            // `^` is literal when it does not start the class
+            ("[a^]", vec!["a", "^"], vec!["b"]),
+        ];
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            for (pattern, matching, rejected) in &cases {
+                let regex = Regex::new(pattern, construction).expect("Valid regex");
+                for text in matching {
+                    assert!(regex.is_match(text), "{pattern} should match {text}");
+                }
+                for text in rejected {
+                    assert!(!regex.is_match(text), "{pattern} should not match {text}");
+                }
+            }
+            let regex = Regex::new("x[]y]", construction).expect("Valid regex");
+            assert_eq!(regex.findall("x] xy xx"), vec!["x]", "xy"]);
+        }
+    }
+
+    #[test]
+    fn class_range_test() {
+        assert_eq!(normalise_regex("[a-cx]?"), "([a-cx]|)");
+        for pattern in [
+            "[a-z0-9]",
+            "[-]",
+            "[a-a]",
+            r"[\u{41}-C]",
+            r"[\--/]",
+            "[a-c-e]",
+        ] {
+            assert!(is_valid_regex(pattern), "{pattern} should be valid");
+        }
+        for pattern in ["[z-a]", "[9-0]+", r"[\p{L}-z]", r"[a-\p{L}]"] {
+            assert!(!is_valid_regex(pattern), "{pattern} should be invalid");
+        }
+
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("[0-9]+", construction).expect("Valid regex");
+            assert!(regex.is_match("12345"));
+            assert!(!regex.is_match(""));
+            assert!(!regex.is_match("12a45"));
+            assert_eq!(regex.find("abc 2024-10"), Some("2024"));
+
+            let regex = Regex::new("[a-cxyz]", construction).expect("Valid regex");
+            for text in ["a", "b", "c", "x", "y", "z"] {
+                assert!(regex.is_match(text), "Failed for input: {text}");
+            }
+            for text in ["d", "w", "-", "A"] {
+                assert!(!regex.is_match(text), "Failed for input: {text}");
+            }
+
+            let regex = Regex::new("[-a][a-]", construction).expect("Valid regex");
+            for text in ["--", "a-", "-a", "aa"] {
+                assert!(regex.is_match(text), "Failed for input: {text}");
+            }
+            assert!(!regex.is_match("b-"));
+
+            assert!(Regex::new("[z-a]", construction).is_err());
+
+            // Every char of the range is mapped, `[` to `_` lie between `Z` and `a`
+            let regex = RegexBuilder::new("[X-b]", construction)
+                .char_map(|c| c.to_ascii_lowercase())
+                .build()
+                .expect("Valid regex");
+            for text in ["x", "Y", "z", "[", "_", "A", "b"] {
+                assert!(regex.is_match(text), "Failed for input: {text}");
+            }
+            assert!(!regex.is_match("c"));
+        }
+    }
+
+    #[test]
+    fn huge_class_range_test() {
+        // Expanding the range into one alternative per char would take far too long
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new(r"x[\u{0}-\u{10FFFF}]", construction).expect("Valid regex");
+            for text in ["xa", "x\u{0}", "x😀", "x\u{10FFFF}"] {
+                assert!(regex.is_match(text), "Failed for input: {text}");
+            }
+            assert!(!regex.is_match("x"));
+            assert_eq!(regex.find("ab x😀"), Some("x😀"));
+
+            let result = RegexBuilder::new(r"[\u{0}-\u{10FFFF}]", construction)
+                .char_map(|c| c.to_ascii_lowercase())
+                .build();
+            assert!(result.is_err());
+        }
+    }
+
+    #[cfg(feature = "regex_crate")]
+    #[test]
+    fn to_regex_crate_test() {
+        let texts = ["", "ac", "abc", "abbbc", "xabcx", "abd", "a-c ac"];
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            for pattern in ["ab*c", "^ab*c", "ab*c$", r"a(b|\-)*c"] {
+                let regex = Regex::new(pattern, construction).expect("Valid regex");
+                let bridged = regex.to_regex_crate().expect("Reconstructable regex");
+                for text in texts {
+                    assert_eq!(
+                        bridged.find(text).map(|m| m.as_str()),
+                        regex.find(text),
+                        "Failed for pattern {pattern} and input: {text}"
+                    );
+                }
+            }
+
+            let regex = Regex::new(r"\p{Lu}+", construction).expect("Valid regex");
+            let bridged = regex.to_regex_crate().expect("Reconstructable regex");
+            assert_eq!(
+                bridged.find("abC\u{C4}d").map(|m| m.as_str()),
+                Some("C\u{C4}")
+            );
+            let regex = RegexBuilder::new("a", construction)
+                .char_map(|c| c.to_ascii_lowercase())
+                .build()
+                .expect("Valid regex");
+            assert!(regex.to_regex_crate().is_err());
+        }
+    }
+
+    #[test]
+    fn empty_group_test() {
+        let texts = ["", "a", "ab", "b", "aab"];
+        let cases = [
+            ("()", vec![""]),
+            ("a()b", vec!["ab"]),
+            ("(())", vec![""]),
+            ("()*", vec![""]),
+            ("()+", vec![""]),
+            ("(()*)*", vec![""]),
+            ("a(())*b", vec!["ab"]),
+            ("()|a", vec!["", "a"]),
+            ("(|)", vec![""]),
+            ("(|)*", vec![""]),
+            ("a(|)b", vec!["ab"]),
+            ("(|)|b", vec!["", "b"]),
+        ];
+
+        for (pattern, expected) in cases {
+            let results: Vec<(Vec<&str>, Vec<&str>)> =
+                [ConstructionType::Thompson, ConstructionType::Glushkov]
+                    .into_iter()
+                    .map(|construction| {
+                        let regex = Regex::new(pattern, construction).expect("Valid regex");
+                        let matching = texts
+                            .into_iter()
+                            .filter(|text| regex.is_match(text))
+                            .collect();
+                        (matching, regex.findall("xab"))
+                    })
+                    .collect();
+
+            assert_eq!(results[0].0, expected, "Failed for pattern: {pattern}");
+            assert_eq!(results[0], results[1], "Failed for pattern: {pattern}");
+        }
+    }
+
+    #[test]
+    fn empty_input_test() {
+        let nullable_patterns = ["a*", "(a|)", "a?b?", "(ab)*c*"];
+        let non_nullable_patterns = ["a", "a+", "a*b", r"\p{L}"];
+
+        for pattern in nullable_patterns.into_iter().chain(non_nullable_patterns) {
+            let nullable = nullable_patterns.contains(&pattern);
+            for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+                let regex_object = Regex::new(pattern, construction).expect("Valid regex");
+                let expected_matches = if nullable { vec![""] } else { vec![] };
+
+                assert_eq!(regex_object.is_match(""), nullable, "Failed for: {pattern}");
+                assert_eq!(regex_object.find(""), nullable.then_some(""));
+                assert_eq!(regex_object.findall(""), expected_matches);
+                assert_eq!(regex_object.count_matches(""), expected_matches.len());
+                assert_eq!(
+                    regex_object.replace_all("", "-"),
+                    if nullable { "-" } else { "" }
+                );
+                assert!(regex_object.split_inclusive("").is_empty());
+                assert!(regex_object.match_mask("").is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn empty_match_findall_test() {
+        // Expected spans as reported by the `regex` crate
+        let test_cases = vec![
+            ("a?", "aaa", vec![(0, 1), (1, 2), (2, 3)]),
+            ("a*", "aaa", vec![(0, 3)]),
+            ("(a|)", "aaa", vec![(0, 1), (1, 2), (2, 3)]),
+            ("a?", "", vec![(0, 0)]),
+            ("a*", "", vec![(0, 0)]),
+            ("(a|)", "", vec![(0, 0)]),
+            ("a*", "aXa", vec![(0, 1), (2, 3)]),
+            ("a*", "abc", vec![(0, 1), (2, 2), (3, 3)]),
+            ("a*", "baaa", vec![(0, 0), (1, 4)]),
+            ("x*", "aé", vec![(0, 0), (1, 1), (3, 3)]),
+            ("(a|)", "aXa", vec![(0, 1), (2, 3)]),
+            ("(a|)", "Xé😀", vec![(0, 0), (1, 1), (3, 3), (7, 7)]),
+        ];
+
+        for (pattern, text, expected) in test_cases {
+            for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+                let regex_object = Regex::new(pattern, construction).expect("Valid regex");
+                let matches: Vec<&str> = expected
+                    .iter()
+                    .map(|&(start, end)| &text[start..end])
+                    .collect();
+                assert_eq!(
+                    regex_object.find_all_spans(text),
+                    expected,
+                    "Failed for '{pattern}' on input: {text}"
+                );
+                assert_eq!(regex_object.findall(text), matches);
+                let iter_matches: Vec<&str> =
+                    regex_object.find_iter(text).map(|m| m.as_str()).collect();
+                assert_eq!(iter_matches, matches);
+                let iter_spans: Vec<(usize, usize)> = regex_object
+                    .find_iter(text)
+                    .map(|m| (m.start(), m.end()))
+                    .collect();
+                assert_eq!(iter_spans, expected);
+                assert_eq!(regex_object.findall_exact(text), matches);
+                assert_eq!(regex_object.count_matches(text), expected.len());
+            }
+        }
+
+        let regex_object = Regex::new("x*", ConstructionType::Glushkov).expect("Valid regex");
+        assert_eq!(regex_object.replace_all("ab", "-"), "-a-b-");
+        assert_eq!(regex_object.split_inclusive("ab"), vec!["a", "b"]);
+        assert_eq!(regex_object.find("ab"), Some(""));
+    }
+
+    #[test]
+    fn unicode_property_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new(r"\p{L}+", construction).expect("Valid regex");
+            assert!(regex_object.is_match("café"));
+            assert!(regex_object.is_match("Ωmega"));
+            assert!(!regex_object.is_match("12"));
+            assert_eq!(regex_object.find("12 über 3"), Some("über"));
+        }
+
+        let regex_object =
+            Regex::new(r"\p{Lu}\p{Ll}*-\p{N}", ConstructionType::Glushkov).expect("Valid regex");
+        assert!(regex_object.is_match("Émile-٣"));
+        assert!(!regex_object.is_match("émile-3"));
+
+        // Literal characters inside a class still match through both paths
+        let regex_object =
+            Regex::new(r"é|\p{L}x", ConstructionType::Thompson).expect("Valid regex");
+        assert!(regex_object.is_match("é"));
+        assert!(regex_object.is_match("éx"));
+        assert!(regex_object.is_match("ax"));
+        assert!(!regex_object.is_match("a"));
+
+        assert!(Regex::new(r"\p{Foo}", ConstructionType::Thompson).is_err());
+        assert!(Regex::new(r"\p{L", ConstructionType::Glushkov).is_err());
+    }
+
+    #[test]
+    fn find_at_text_end_test() {
+        let cases = [
+            ("abc", "xabc", "abc"),
+            ("c+", "abcc", "cc"),
+            ("a|bc", "xxbc", "bc"),
+            ("ab*", "xab", "ab"),
+            (r"\u{E9}+", "x\u{E9}\u{E9}", "\u{E9}\u{E9}"),
+        ];
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            for (pattern, text, expected) in cases {
+                let regex = Regex::new(pattern, construction).expect("Valid regex");
+                assert_eq!(
+                    regex.find(text),
+                    Some(expected),
+                    "Failed for {pattern} on {text}"
+                );
+                assert_eq!(regex.findall(text).last(), Some(&expected));
+                assert_eq!(
+                    regex
+                        .find_chars(&text.chars().collect::<Vec<_>>())
+                        .map(|(_, end)| end),
+                    Some(text.chars().count())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn findall_match_start_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            // Matches start where they begin, not where the search for them started
+            let regex_object = Regex::new("b", construction).expect("Valid regex");
+            assert_eq!(regex_object.findall("aab aab"), vec!["b", "b"]);
+            assert_eq!(regex_object.find_all_spans("aab aab"), vec![(2, 3), (6, 7)]);
+
+            let regex_object = Regex::new("bc*", construction).expect("Valid regex");
+            assert_eq!(regex_object.findall("aabcc xab"), vec!["bcc", "b"]);
+        }
+    }
+
+    #[test]
+    fn findall_exact_test() {
+        let regex_object = Regex::new("abc*", ConstructionType::Glushkov).expect("Valid regex");
+        for text in ["abcd", "ac", "abcab", "ab ab abccc", ""] {
+            let exact = regex_object.findall_exact(text);
+            assert_eq!(
+                exact,
+                regex_object.findall(text),
+                "Failed for input: {text}"
+            );
+            assert_eq!(exact.len(), regex_object.count_matches(text));
+            assert_eq!(exact.capacity(), exact.len());
+        }
+    }
+
+    #[test]
+    fn replace_all_test() {
+        let test_cases = vec![
+            ("a+", "caaat and a bat", "o", "cot ond o bot"),
+            ("xyz", "no match here", "!", "no match here"),
+            ("(ab)*c", "abababc c éc", "_", "_ _ é_"),
+            ("b", "", "-", ""),
+        ];
+
+        for (pattern, text, replacement, expected) in test_cases {
+            for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+                let regex_object = Regex::new(pattern, construction).expect("Valid regex");
+                let mut streamed = String::new();
+                regex_object
+                    .replace_all_to(text, replacement, &mut streamed)
+                    .expect("Writing to a String");
+
+                assert_eq!(regex_object.replace_all(text, replacement), expected);
+                assert_eq!(streamed, expected, "Failed for input: {text}");
+            }
+        }
+
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("a+", construction).expect("Valid regex");
+            let text = "the bird sings";
+            match regex.replace_all(text, "o") {
+                Cow::Borrowed(unchanged) => assert!(std::ptr::eq(unchanged, text)),
+                Cow::Owned(_) => panic!("Expected the input to be borrowed"),
+            }
+            assert!(matches!(regex.replace_all("bat", "o"), Cow::Owned(_)));
+        }
+    }
+
+    #[test]
+    fn dense_table_find_test() {
+        let long_input = format!("{}bc", "a".repeat(1000));
+        let test_cases = [
+            ("(a|b)*c", long_input.as_str()),
+            ("(a|b)*c", "xxabacbc"),
+            ("a*", "baab"),
+            ("[0-9]+|x", "ab12x3"),
+            ("ab|b", "aab"),
+            ("(a|b)*c", "\u{E9}abc"),
+        ];
+
+        for (pattern, text) in test_cases {
+            for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+                let regex_object = Regex::new(pattern, construction).expect("Valid regex");
+                let table = regex_object.dense_table.as_ref().expect("ASCII alphabet");
+                let start_positions = text.char_indices().map(|(i, _)| i).take(8);
+                for start_pos in start_positions.chain([text.len()]) {
+                    assert_eq!(
+                        table.find_at(regex_object.dfa().get_alphabet(), text, start_pos),
+                        regex_object.dfa().find_at(text, start_pos),
+                        "Failed for pattern: {pattern} at {start_pos}"
+                    );
+                }
+            }
+        }
+
+        // The table maps the input with the char map like the DFA does
+        let regex_object = RegexBuilder::new("(a|b)*c", ConstructionType::Glushkov)
+            .char_map(|c| c.to_ascii_lowercase())
+            .build()
+            .expect("Valid regex");
+        assert!(regex_object.dense_table.is_some());
+        assert_eq!(regex_object.find("xAbC"), Some("AbC"));
+    }
+
+    #[test]
+    fn matches_count_at_most_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new("[0-9]+", construction).expect("Valid regex");
+            for (text, count) in [("", 0), ("a1b22c333", 3), ("12", 1)] {
+                assert_eq!(regex_object.count_matches(text), count);
+                assert!(regex_object.matches_count_at_most(text, count));
+                assert!(regex_object.matches_count_at_most(text, count + 1));
+                if count > 0 {
+                    assert!(!regex_object.matches_count_at_most(text, count - 1));
+                }
+            }
+
+            // Empty matches count like in `count_matches`
+            let regex_object = Regex::new("a*", construction).expect("Valid regex");
+            assert!(regex_object.matches_count_at_most("bb", 3));
+            assert!(!regex_object.matches_count_at_most("bb", 2));
+        }
+
+        // The search stops at the 11th match, the rest of the input is never scanned, which
+        // keeps this fast even though every char of the text is a match
+        let regex_object = Regex::new("a", ConstructionType::Glushkov).expect("Valid regex");
+        let text = "a".repeat(10_000_000);
+        let start = std::time::Instant::now();
+        assert!(!regex_object.matches_count_at_most(&text, 10));
+        assert!(start.elapsed() < std::time::Duration::from_millis(100));
+    }
+
+    #[test]
+    fn split_test() {
+        let test_cases = vec![
+            ("[0-9]", "a1b2c", vec!["a", "b", "c"]),
+            ("[0-9]", "1a2", vec!["", "a", ""]),
+            ("[0-9]", "a12b", vec!["a", "", "b"]),
+            ("[0-9]+", "a12b", vec!["a", "b"]),
+            ("[0-9]", "abc", vec!["abc"]),
+            ("[0-9]", "", vec![""]),
+            ("a*", "abc", vec!["", "b", "c", ""]),
+            ("x*", "\u{E9}b", vec!["", "\u{E9}", "b", ""]),
+        ];
+
+        for (pattern, text, expected) in test_cases {
+            for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+                let regex_object = Regex::new(pattern, construction).expect("Valid regex");
+                let pieces = regex_object.split(text);
+                assert_eq!(pieces, expected, "Failed for input: {text}");
+                assert_eq!(pieces.len(), regex_object.count_matches(text) + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn brzozowski_construction_test() {
+        let test_cases = [
+            ("[a-c]+x?", vec!["a", "cbx", "abcx"], vec!["", "x", "ad"]),
+            ("(a|)*b", vec!["b", "aab"], vec!["", "ba"]),
+            (
+                r".\p{Lu}\u{E9}",
+                vec!["xA\u{E9}", " \u{C4}\u{E9}"],
+                vec!["xa\u{E9}", "\nA\u{E9}"],
+            ),
+            (
+                "a{2,3}|(ab)*",
+                vec!["", "aa", "aaa", "abab"],
+                vec!["a", "aaaa", "aba"],
+            ),
+        ];
+
+        for (pattern, accepted, rejected) in test_cases {
+            let brzozowski =
+                Regex::new(pattern, ConstructionType::Brzozowski).expect("Valid regex");
+            let thompson = Regex::new(pattern, ConstructionType::Thompson).expect("Valid regex");
+            assert_eq!(brzozowski.canonical_key(), thompson.canonical_key());
+            for text in accepted {
+                assert!(brzozowski.is_match(text), "{pattern} should match {text:?}");
+            }
+            for text in rejected {
+                assert!(
+                    !brzozowski.is_match(text),
+                    "{pattern} should not match {text:?}"
+                );
+            }
+        }
+
+        assert!(Regex::new("\u{E9}", ConstructionType::Brzozowski).is_err());
+        assert!(
+            RegexBuilder::new("(a|b)*a(a|b)(a|b)", ConstructionType::Brzozowski)
+                .size_limit(64)
+                .build()
+                .is_err()
+        );
+        let regex_object = Regex::new("a(b+)", ConstructionType::Brzozowski).expect("Valid regex");
+        let bytes = regex_object.to_bytes().expect("No char map");
+        let restored = Regex::from_bytes(&bytes).expect("Valid bytes");
+        assert_eq!(restored.canonical_key(), regex_object.canonical_key());
+        assert_eq!(restored.to_bytes(), Some(bytes));
+    }
+
+    #[test]
+    fn captures_test() {
+        let test_cases: Vec<(&str, &str, Vec<Option<&str>>)> = vec![
+            (
+                "(a+)(b+)",
+                "aaabb",
+                vec![Some("aaabb"), Some("aaa"), Some("bb")],
+            ),
+            ("x(a|b)*y", "xabby", vec![Some("xabby"), Some("b")]),
+            ("(a)|(b)", "b", vec![Some("b"), None, Some("b")]),
+            ("a(b)?c", "ac", vec![Some("ac"), None]),
+            (
+                "((a)(b))c",
+                "zabc",
+                vec![Some("abc"), Some("ab"), Some("a"), Some("b")],
+            ),
+            ("(a*)(a)", "aaa", vec![Some("aaa"), Some("aa"), Some("a")]),
+            (
+                "(?#skip)(a{2})(?P<rest>.*)",
+                "aaxy",
+                vec![Some("aaxy"), Some("aa"), Some("xy")],
+            ),
+            (
+                "^(\\u{E9}+)x$",
+                "\u{E9}\u{E9}x",
+                vec![Some("\u{E9}\u{E9}x"), Some("\u{E9}\u{E9}")],
+            ),
+            ("(ab)\\Q()\\E", "ab()", vec![Some("ab()"), Some("ab")]),
+        ];
+
+        for (pattern, text, expected) in test_cases {
+            for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+                let regex_object = Regex::new(pattern, construction).expect("Valid regex");
+                let captures = regex_object.captures(text).expect("Match");
+                let groups: Vec<Option<&str>> =
+                    captures.iter().map(|m| m.map(|m| m.as_str())).collect();
+                assert_eq!(groups, expected, "Failed for pattern: {pattern}");
+            }
+        }
+
+        let regex_object = Regex::new(
+            "(?P<key>[a-z]+)=(?P<value>[0-9]+)",
+            ConstructionType::Glushkov,
+        )
+        .expect("Valid regex");
+        let captures = regex_object.captures("x: key=42").expect("Match");
+        assert_eq!(captures.name("key").map(|m| m.as_str()), Some("key"));
+        assert_eq!(
+            captures.name("value").map(|m| (m.start(), m.end())),
+            Some((7, 9))
+        );
+        assert!(captures.name("other").is_none());
+        assert!(captures.get(3).is_none());
+        assert!(regex_object.captures("none").is_none());
+
+        // The dot options and char maps keep the groups of the pattern as written
+        let regex_object = RegexBuilder::new("(.)(b)", ConstructionType::Thompson)
+            .dot_matches_newline(true)
+            .char_map(|c| c.to_ascii_lowercase())
+            .build()
+            .expect("Valid regex");
+        let captures = regex_object.captures("\nB").expect("Match");
+        assert_eq!(captures.get(1).map(|m| m.as_str()), Some("\n"));
+        assert_eq!(captures.get(2).map(|m| m.as_str()), Some("B"));
+
+        let regex_object = Regex::new("(a)", ConstructionType::Glushkov)
+            .expect("Valid regex")
+            .then_literal("b");
+        let captures = regex_object.captures("ab").expect("Match");
+        assert_eq!(captures.get(1).map(|m| m.as_str()), Some("a"));
+
+        // Regexes without a pattern only know the whole match
+        let regex_object = Regex::from_examples(&["ab"]);
+        let captures = regex_object.captures("xab").expect("Match");
+        assert_eq!(captures.get(0).map(|m| m.as_str()), Some("ab"));
+        assert_eq!(captures.iter().count(), 1);
+    }
+
+    #[test]
+    fn split_inclusive_test() {
+        let test_cases = vec![
+            (";", "a;b;c", vec!["a;", "b;", "c"]),
+            (";", "a;b;", vec!["a;", "b;"]),
+            (";", ";a", vec![";", "a"]),
+            (";", "abc", vec!["abc"]),
+            (";", "", vec![]),
+            ("(,| )+", "x, y  z", vec!["x, ", "y  ", "z"]),
+        ];
+
+        for (pattern, text, expected) in test_cases {
+            for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+                let regex_object = Regex::new(pattern, construction).expect("Valid regex");
+                let pieces = regex_object.split_inclusive(text);
+                assert_eq!(pieces, expected, "Failed for input: {text}");
+                assert_eq!(pieces.concat(), text);
+            }
+        }
+    }
+
+    #[test]
+    fn anchors_stripped_pattern_test() {
+        let cases = [
+            ("^abc$", ("abc", true, true)),
+            ("abc$", ("abc", false, true)),
+            ("^abc", ("abc", true, false)),
+            ("abc", ("abc", false, false)),
+            (r"abc\$", (r"abc\$", false, false)),
+            (r"abc\\$", (r"abc\\", false, true)),
+            ("^$", ("", true, true)),
+        ];
+
+        for (pattern, expected) in cases {
+            assert_eq!(
+                Regex::anchors_stripped_pattern(pattern),
+                expected,
+                "Failed for pattern: {pattern}"
+            );
+        }
+    }
+
+    #[test]
+    fn match_mask_test() {
+        let regex_object = Regex::new("abc", ConstructionType::Glushkov).expect("Valid regex");
+        assert_eq!(
+            regex_object.match_mask("xabcx"),
+            vec![false, true, true, true, false]
+        );
+        assert_eq!(regex_object.match_mask(""), Vec::<bool>::new());
+
+        let regex_object = Regex::new("a+", ConstructionType::Thompson).expect("Valid regex");
+        assert_eq!(
+            regex_object.match_mask("aabaé"),
+            vec![true, true, false, true, false, false]
+        );
+    }
+
+    #[test]
+    fn to_dot_trace_test() {
+        let highlighted_edges = |dot: &str| {
+            dot.lines()
+                .filter(|line| line.contains("-> ") && line.contains("penwidth=2"))
+                .count()
+        };
+
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new("ab", construction).expect("Valid regex");
+
+            let dot = regex_object.to_dot();
+            assert_eq!(highlighted_edges(&dot), 0);
+            assert_eq!(dot.matches("doublecircle").count(), 1);
+
+            // The edge from the start marker plus the two transitions
+            let dot = regex_object.to_dot_trace("ab");
+            assert_eq!(highlighted_edges(&dot), 3);
+            assert!(!dot.contains("dead_end"));
+
+            let dot = regex_object.to_dot_trace("ac");
+            assert_eq!(highlighted_edges(&dot), 2);
+            assert!(dot.contains("-> dead_end [label=\"c\", style=dashed"));
+        }
+    }
+
+    #[test]
+    fn canonical_key_test() {
+        let key = |pattern: &str, construction: ConstructionType| {
+            Regex::new(pattern, construction)
+                .expect("Valid regex")
+                .canonical_key()
+        };
+
+        let thompson_key = key("(a|b)*", ConstructionType::Thompson);
+        assert_eq!(thompson_key, key("(a|b)*", ConstructionType::Glushkov));
+        assert_eq!(thompson_key, key("(b|a)*", ConstructionType::Glushkov));
+        assert_eq!(thompson_key, key("(a*b*)*", ConstructionType::Thompson));
+        assert_ne!(thompson_key, key("(a|c)*", ConstructionType::Glushkov));
+
+        assert_eq!(
+            key("ab|ac", ConstructionType::Glushkov),
+            key("a(b|c)", ConstructionType::Thompson)
+        );
+        assert_eq!(
+            key(r"x\p{Lu}", ConstructionType::Glushkov),
+            key(r"x\p{Lu}", ConstructionType::Thompson)
+        );
+        assert_ne!(
+            key(r"\p{Lu}", ConstructionType::Glushkov),
+            key(r"\p{Ll}", ConstructionType::Glushkov)
+        );
+    }
+
+    #[test]
+    fn simplify_test() {
+        for pattern in [
+            "ab|ac|ad",
+            "(a|b)*abb",
+            "a+b?",
+            "(ab|ac)*",
+            r"x(\*|\()+y",
+            "a*|b",
+            "()",
+            "(a|)(b|)c*",
+            r"\p{L}",
+            r"[a-z]+\p{Lu}?",
+            r"[\u{0}-\u{10FFFF}]",
+        ] {
+            let regex = Regex::new(pattern, ConstructionType::Glushkov).expect("Valid regex");
+            let simplified = regex.simplify().expect("Expressible language");
+            let simplified_regex =
+                Regex::new(&simplified, ConstructionType::Glushkov).expect("Valid regex");
+            assert_eq!(
+                regex.canonical_key(),
+                simplified_regex.canonical_key(),
+                "{pattern} simplified to {simplified}"
+            );
+        }
+
+        let regex = Regex::new(r"[a-c]*d", ConstructionType::Glushkov).expect("Valid regex");
+        assert_eq!(regex.simplify().as_deref(), Some("[a-c]*d"));
+    }
+
+    #[test]
+    fn representation_test() {
+        let texts = ["", "abc1", "zz9", "a", "ab\u{E9}1", "A1", "abc12"];
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("[a-z]+[0-9]", construction).expect("Valid regex");
+            assert_eq!(regex.representation(), Representation::DenseTable);
+            for text in texts {
+                assert_eq!(
+                    regex.is_match(text),
+                    regex.dfa().process(text),
+                    "Failed for input: {text}"
+                );
+            }
+            let completed = regex.complete_dfa();
+            assert_eq!(completed.representation(), Representation::DenseTable);
+            assert!(completed.is_match("abc1"));
+            assert!(!completed.is_match("abc"));
+
+            for pattern in [r"\p{L}+[0-9]", r"\u{E9}|a", r"[ -\u{FF}]"] {
+                let regex = Regex::new(pattern, construction).expect("Valid regex");
+                assert_eq!(regex.representation(), Representation::HashMap);
+            }
+            // Too many states for a table
+            let regex = Regex::new("(a|b)*a(a|b){10}", construction).expect("Valid regex");
+            assert_eq!(regex.representation(), Representation::HashMap);
+            assert!(regex.is_match("abbbbbbbbbb"));
+
+            let regex = RegexBuilder::new("[a-z]+", construction)
+                .char_map(|c| c.to_ascii_lowercase())
+                .build()
+                .expect("Valid regex");
+            assert_eq!(regex.representation(), Representation::DenseTable);
+            assert!(regex.is_match("AbC"));
+            assert!(!regex.is_match("Ab1"));
+
+            let regex = RegexBuilder::new("[a-z]+[0-9]", construction)
+                .dense_table(false)
+                .build()
+                .expect("Valid regex");
+            assert_eq!(regex.representation(), Representation::HashMap);
+            assert!(regex.is_match("abc1"));
+            assert_eq!(regex.find("AB ab12"), Some("ab1"));
+        }
+    }
+
+    #[test]
+    fn serialise_test() {
+        let texts = ["", "ab", "xabbc abc", "a\u{E9}b", "AbC1", "ccab"];
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            for pattern in [
+                "ab+c",
+                "^ab",
+                "b$",
+                ".*ab",
+                r"\p{Lu}[a-z]?",
+                "(?P<x>a)(b)*",
+                "a|",
+            ] {
+                let regex = Regex::new(pattern, construction).expect("Valid regex");
+                let bytes = regex.to_bytes().expect("No char map");
+                let loaded = Regex::from_bytes(&bytes).expect("Serialised by this version");
+                assert_eq!(loaded.to_bytes().as_ref(), Some(&bytes));
+                assert_eq!(loaded.capture_names(), regex.capture_names());
+                for text in texts {
+                    assert_eq!(
+                        loaded.findall(text),
+                        regex.findall(text),
+                        "Failed for {pattern} on {text}"
+                    );
+                }
+
+                let mut trailing = bytes.clone();
+                trailing.push(0);
+                assert!(Regex::from_bytes(&trailing).is_err());
+            }
+        }
+
+        let bytes = Regex::new("ab", ConstructionType::Glushkov)
+            .expect("Valid regex")
+            .to_bytes()
+            .expect("No char map");
+        assert_eq!(&bytes[..4], b"RGXE");
+        for len in 0..bytes.len() {
+            assert_eq!(
+                Regex::from_bytes(&bytes[..len]).err(),
+                Some(RegexError::BadSerialFormat)
+            );
+        }
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b'X';
+        assert_eq!(
+            Regex::from_bytes(&wrong_magic).err(),
+            Some(RegexError::BadSerialFormat)
+        );
+        // The capture names follow the header, no `.*` literal and the required literal "ab".
+        // Without any name there is no group 0 for the whole match.
+        let captures_pos = 8 + 1 + 1 + 4 + 2;
+        assert_eq!(bytes[captures_pos..captures_pos + 5], [1, 0, 0, 0, 0]);
+        let mut no_captures = bytes[..captures_pos].to_vec();
+        no_captures.extend([0, 0, 0, 0]);
+        no_captures.extend(&bytes[captures_pos + 5..]);
+        assert_eq!(
+            Regex::from_bytes(&no_captures).err(),
+            Some(RegexError::BadSerialFormat)
+        );
+        // The version, construction type and state id width follow the magic bytes
+        for header_pos in 4..7 {
+            let mut incompatible = bytes.clone();
+            incompatible[header_pos] = 9;
+            assert!(Regex::from_bytes(&incompatible).is_err());
+        }
+
+        let regex = RegexBuilder::new("ab", ConstructionType::Glushkov)
+            .char_map(|c| c.to_ascii_lowercase())
+            .build()
+            .expect("Valid regex");
+        assert!(regex.to_bytes().is_none());
+    }
+
+    #[test]
+    fn union_all_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::union_all(&["ab+", "c*d"], construction).expect("Valid regexes");
+            for text in ["ab", "abbb", "d", "cccd"] {
+                assert!(regex.is_match(text), "Failed for input: {text}");
+            }
+            for text in ["", "a", "abd", "cab", "c"] {
+                assert!(!regex.is_match(text), "Failed for input: {text}");
+            }
+            assert_eq!(regex.findall("xabb cd"), vec!["abb", "cd"]);
+
+            // Overlapping classes are split into common cells
+            let regex = Regex::union_all(&[r"\p{L}x", "ax*", r"\p{Lu}"], construction)
+                .expect("Valid regexes");
+            let expected = Regex::new(r"\p{L}x|ax*|\p{Lu}", construction).expect("Valid regex");
+            for text in ["ax", "a", "axx", "éx", "É", "Éx", "é", "x", ""] {
+                assert_eq!(
+                    regex.is_match(text),
+                    expected.is_match(text),
+                    "Failed for input: {text}"
+                );
+            }
+
+            let error = Regex::union_all(&["a", "b**", "("], construction).err();
+            assert_eq!(
+                error,
+                Some(RegexError::InPattern {
+                    index: 1,
+                    error: Box::new(RegexError::StackedQuantifier('*'))
+                })
+            );
+            assert!(
+                !Regex::union_all(&[], construction)
+                    .expect("No patterns")
+                    .is_match("")
+            );
+        }
+    }
+
+    #[test]
+    fn from_examples_test() {
+        let examples = ["if", "in", "int", "for", "", "fort"];
+        let regex_object = Regex::from_examples(&examples);
+
+        for example in examples {
+            assert!(
+                regex_object.is_match(example),
+                "Failed for input: {example}"
+            );
+        }
+        for non_example in ["i", "fo", "intt", "forts", "x"] {
+            assert!(
+                !regex_object.is_match(non_example),
+                "Failed for input: {non_example}"
+            );
+        }
+        assert_eq!(regex_object.max_match_len(), Some(4));
+        assert_eq!(regex_object.findall("for int"), vec!["for", "int"]);
+
+        let regex_object = Regex::from_examples(&[]);
+        assert!(!regex_object.is_match(""));
+        assert_eq!(regex_object.find("abc"), None);
+    }
+
+    #[test]
+    fn then_literal_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new("(a|b)", construction)
+                .expect("Valid regex")
+                .then_literal("xy");
+            for (text, expected) in [
+                ("axy", true),
+                ("bxy", true),
+                ("a", false),
+                ("xy", false),
+                ("abxy", false),
+                ("axyxy", false),
+                ("cxy", false),
+            ] {
+                assert_eq!(
+                    regex_object.is_match(text),
+                    expected,
+                    "Failed for input: {text}"
+                );
+            }
+        }
+
+        // The literal overlaps with what the pattern itself can still consume
+        let regex_object = Regex::new(r"\p{L}*", ConstructionType::Glushkov)
+            .expect("Valid regex")
+            .then_literal("ab|");
+        assert!(regex_object.is_match("ab|"));
+        assert!(regex_object.is_match("éabab|"));
+        assert!(!regex_object.is_match("ab"));
+        assert_eq!(regex_object.find("1 xab| 2"), Some("xab|"));
+        assert_eq!(regex_object.min_match_len(), Some(3));
+
+        let regex_object = Regex::new(".*a", ConstructionType::Glushkov)
+            .expect("Valid regex")
+            .then_literal("b");
+        assert_eq!(regex_object.find("xxab"), Some("xxab"));
+        assert!(!regex_object.is_match("xxa"));
+    }
+
+    #[test]
+    fn try_from_test() {
+        let regex_object = Regex::try_from("a(b|c)*").expect("Valid regex");
+        assert!(regex_object.is_match("abcb"));
+        let regex_object = Regex::try_from(String::from("x+")).expect("Valid regex");
+        assert_eq!(regex_object.find("axxb"), Some("xx"));
+
+        assert!(Regex::try_from("(a").is_err());
+        assert!(Regex::try_from(String::from("*a")).is_err());
+        assert!(Regex::try_from(r"\p{Foo}").is_err());
     }
 
-    let mut open_paren_count = 0;
-    let mut last_was_quantifier = true;
+    #[test]
+    fn anchor_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new("abc", construction).expect("Valid regex");
+            let start_anchored = regex_object.prepend_anchor();
+            let end_anchored = regex_object.append_anchor();
+            let both_anchored = start_anchored.append_anchor();
 
-    let mut chars = regex.chars().peekable();
-    while let Some(c) = chars.next() {
-        match c {
-            '(' => {
-                open_paren_count += 1;
-                last_was_quantifier = true;
+            assert_eq!(regex_object.find("xabc abc"), Some("abc"));
+            assert_eq!(start_anchored.find("xabc abc"), None);
+            assert_eq!(start_anchored.find("abcabc"), Some("abc"));
+            assert_eq!(start_anchored.findall("abcabc"), vec!["abc"]);
+            assert_eq!(end_anchored.find("abcx"), None);
+            assert_eq!(end_anchored.find("abc xabc"), Some("abc"));
+            assert_eq!(end_anchored.find_all_spans("abc xabc"), vec![(5, 8)]);
+            assert_eq!(both_anchored.find("abcabc"), None);
+            assert_eq!(both_anchored.find("abc"), Some("abc"));
+            assert!(start_anchored.is_match("abc"));
+        }
+
+        let regex_object = Regex::new("a(b|c)*", ConstructionType::Glushkov).expect("Valid regex");
+        assert_eq!(regex_object.prepend_anchor().find("abcbx"), Some("abcb"));
+        assert_eq!(regex_object.append_anchor().find("abxacb"), Some("acb"));
+        assert_eq!(regex_object.append_anchor().find("xaab"), Some("ab"));
+
+        let regex_object = Regex::new("a*", ConstructionType::Glushkov).expect("Valid regex");
+        assert_eq!(regex_object.append_anchor().findall("aba"), vec!["a"]);
+        assert_eq!(regex_object.append_anchor().findall("ab"), vec![""]);
+        assert_eq!(regex_object.prepend_anchor().findall("ba"), vec![""]);
+    }
+
+    #[test]
+    fn anchored_longest_match_at_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new("a+", construction).expect("Valid regex");
+            let end = |regex: &Regex, text, start_pos| {
+                regex.longest_match_at(text, start_pos).map(|m| m.end())
+            };
+
+            let start_anchored = regex_object.prepend_anchor();
+            assert_eq!(end(&start_anchored, "baaa", 1), None);
+            assert_eq!(end(&start_anchored, "aaab", 0), Some(3));
+            let start_anchored = Regex::new("^a+", construction).expect("Valid regex");
+            assert_eq!(end(&start_anchored, "baaa", 1), None);
+
+            let end_anchored = regex_object.append_anchor();
+            assert_eq!(end(&end_anchored, "aaab", 0), None);
+            assert_eq!(end(&end_anchored, "baaa", 1), Some(4));
+        }
+    }
+
+    #[test]
+    fn anchored_shortest_match_at_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new("a+", construction).expect("Valid regex");
+
+            let start_anchored = regex_object.prepend_anchor();
+            assert_eq!(start_anchored.shortest_match_at("baaa", 1), None);
+            assert_eq!(start_anchored.shortest_match_at("aaab", 0), Some(1));
+
+            // The shortest match reaching the end is the whole rest of the text
+            let end_anchored = regex_object.append_anchor();
+            assert_eq!(end_anchored.shortest_match_at("baaa", 1), Some(4));
+            assert_eq!(end_anchored.shortest_match_at("aaab", 0), None);
+        }
+    }
+
+    #[test]
+    fn anchored_accepting_prefix_lengths_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new("a|aa|aaa", construction).expect("Valid regex");
+            assert_eq!(
+                regex_object
+                    .prepend_anchor()
+                    .accepting_prefix_lengths("aaa"),
+                vec![1, 2, 3]
+            );
+
+            let end_anchored = regex_object.append_anchor();
+            assert_eq!(end_anchored.accepting_prefix_lengths("aaa"), vec![3]);
+            assert_eq!(end_anchored.accepting_prefix_lengths("aa"), vec![2]);
+            assert!(end_anchored.accepting_prefix_lengths("aaaa").is_empty());
+        }
+    }
+
+    #[test]
+    fn anchored_find_iter_anchored_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new("a+|b", construction).expect("Valid regex");
+            let tokens = |regex: &Regex, text| regex.find_iter_anchored(text).collect::<Vec<_>>();
+
+            // Only the first token starts at the beginning of the text
+            assert_eq!(tokens(&regex_object.prepend_anchor(), "aaba"), vec!["aa"]);
+
+            // Only a token reaching the end of the text can be taken
+            let end_anchored = regex_object.append_anchor();
+            assert!(tokens(&end_anchored, "aaba").is_empty());
+            assert_eq!(tokens(&end_anchored, "aaa"), vec!["aaa"]);
+        }
+    }
+
+    #[test]
+    fn anchored_alternation_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("^(foo|bar)$", construction).expect("Valid regex");
+            assert!(regex.is_match("foo"));
+            assert!(regex.is_match("bar"));
+            for text in ["", "foobar", "^foo$", "xfoo"] {
+                assert!(!regex.is_match(text), "Failed for input: {text}");
             }
-            ')' => {
-                if open_paren_count == 0 {
-                    return false;
-                }
-                open_paren_count -= 1;
-                last_was_quantifier = false;
+            assert_eq!(regex.find("xfoox"), None);
+            assert_eq!(regex.find("foox"), None);
+            assert_eq!(regex.find("bar"), Some("bar"));
+
+            let regex = Regex::new("^(foo|bar)", construction).expect("Valid regex");
+            assert_eq!(regex.find("barfoo"), Some("bar"));
+            assert_eq!(regex.findall("foofoo"), vec!["foo"]);
+            assert_eq!(regex.find("xbar"), None);
+
+            let regex = Regex::new("(foo|bar)$", construction).expect("Valid regex");
+            assert_eq!(regex.find("foo xbar"), Some("bar"));
+            assert_eq!(regex.find("barx"), None);
+
+            // Escaped anchors and anchors inside quotes stay literal
+            let regex = Regex::new(r"\^a\Q$\E", construction).expect("Valid regex");
+            assert!(regex.is_match("^a$"));
+            assert_eq!(regex.find("x^a$x"), Some("^a$"));
+
+            assert!(Regex::new("^foo|bar$", construction).is_err());
+            assert!(Regex::new("^foo|bar", construction).is_err());
+            assert!(Regex::union_all(&["a", "^b"], construction).is_err());
+        }
+    }
+
+    #[test]
+    fn warnings_test() {
+        let cases = [
+            (
+                "(a*)*",
+                vec![
+                    Warning::RedundantNestedQuantifier,
+                    Warning::MatchesEmptyString,
+                ],
+            ),
+            (
+                "a|",
+                vec![Warning::EmptyAlternative, Warning::MatchesEmptyString],
+            ),
+            ("(|b)c", vec![Warning::EmptyAlternative]),
+            ("x(a||b)", vec![Warning::EmptyAlternative]),
+            ("(a?b?)+c", vec![Warning::RedundantNestedQuantifier]),
+            ("^a*$", vec![Warning::MatchesEmptyString]),
+            ("a?b", vec![]),
+            ("(ab)*c|d", vec![]),
+            (r"a\||[|]|(?#|)b", vec![]),
+        ];
+        for (pattern, expected) in cases {
+            for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+                let (regex, warnings) =
+                    Regex::new_with_warnings(pattern, construction).expect("Valid regex");
+                assert_eq!(warnings, expected, "Failed for pattern: {pattern}");
+                assert_eq!(
+                    regex.canonical_key(),
+                    Regex::new(pattern, construction)
+                        .expect("Valid regex")
+                        .canonical_key()
+                );
             }
-            '*' | '+' => {
-                // Ensure quantifiers are not the first character and are not repeated
-                if last_was_quantifier {
-                    return false;
-                }
-                last_was_quantifier = true;
+        }
+
+        assert_eq!(
+            Warning::RedundantNestedQuantifier.to_string(),
+            "redundant nested quantifier"
+        );
+        assert!(Regex::new_with_warnings("(a", ConstructionType::Glushkov).is_err());
+    }
+
+    #[test]
+    fn regex_error_test() {
+        let cases = [
+            ("", RegexError::EmptyPattern),
+            ("a\0b", RegexError::NulInPattern),
+            ("(ab", RegexError::UnbalancedParens),
+            ("ab)", RegexError::UnbalancedParens),
+            ("*a", RegexError::DanglingQuantifier),
+            ("a+*", RegexError::StackedQuantifier('*')),
+            ("(|{2})", RegexError::DanglingQuantifier),
+            ("ab\\", RegexError::TrailingEscape),
+            ("(?x)", RegexError::InvalidGroup),
+            ("a{3,1}", RegexError::InvalidRepetition),
+            ("[ab", RegexError::UnclosedClass),
+            (
+                "[z-a]",
+                RegexError::ReversedRange {
+                    start: 'z',
+                    end: 'a',
+                },
+            ),
+            (
+                r"\p{Greek}",
+                RegexError::UnknownProperty("Greek".to_string()),
+            ),
+            (
+                r"\u{D800}",
+                RegexError::InvalidCodepoint("D800".to_string()),
+            ),
+            ("^a|b$", RegexError::AnchoredAlternation),
+            ("a^b", RegexError::MisplacedAnchor),
+        ];
+        for (pattern, expected) in cases {
+            for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+                assert_eq!(
+                    Regex::new(pattern, construction).err(),
+                    Some(expected.clone()),
+                    "Failed for pattern: {pattern}"
+                );
             }
-            '\\' => {
-                // Handle escaped characters: ensure there's a character after the escape
-                if chars.peek().is_none() {
-                    return false;
-                }
-                chars.next(); // Skip the escaped character
-                last_was_quantifier = false;
+        }
+        assert_eq!(validate_regex("a(b|c)*"), Ok(()));
+
+        assert_eq!(
+            RegexError::ReversedRange {
+                start: 'z',
+                end: 'a'
             }
+            .to_string(),
+            "Reversed range in class: z-a"
+        );
+        let error = RegexError::InPattern {
+            index: 2,
+            error: Box::new(RegexError::UnbalancedParens),
+        };
+        assert_eq!(error.to_string(), "Pattern 2: Unbalanced parentheses");
+        assert_eq!(
+            RegexError::InvalidUtf8 { offset: 3 }.to_string(),
+            "Invalid UTF-8 at byte offset 3"
+        );
+        let source = std::error::Error::source(&error).map(ToString::to_string);
+        assert_eq!(source.as_deref(), Some("Unbalanced parentheses"));
+    }
 
-            _ => {
-                last_was_quantifier = false;
+    #[test]
+    fn pattern_anchor_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("^a", construction).expect("Valid regex");
+            assert_eq!(regex.find_all_spans("aba a"), vec![(0, 1)]);
+            assert_eq!(regex.find("ba"), None);
+
+            let regex = Regex::new("a$", construction).expect("Valid regex");
+            assert_eq!(regex.find_all_spans("aba a"), vec![(4, 5)]);
+            assert_eq!(regex.find("ab"), None);
+
+            let regex = Regex::new("^a$", construction).expect("Valid regex");
+            assert_eq!(regex.findall("a"), vec!["a"]);
+            assert!(regex.findall("aa").is_empty());
+
+            let regex = Regex::new("^abc", construction).expect("Valid regex");
+            assert_eq!(regex.find_all_spans("abcabc"), vec![(0, 3)]);
+            let regex = Regex::new("abc$", construction).expect("Valid regex");
+            assert_eq!(regex.find_all_spans("abcabc"), vec![(3, 6)]);
+
+            // Anchors anywhere else can never match and are rejected instead of being literal
+            for pattern in ["a^b", "a$b", "(^a)", "(a$)b", "^^a", "a$$"] {
+                assert!(
+                    Regex::new(pattern, construction).is_err(),
+                    "{pattern} should be rejected"
+                );
+            }
+            for pattern in [r"a\^b", "a[$^]b", "a(?#^)b"] {
+                assert!(
+                    Regex::new(pattern, construction).is_ok(),
+                    "{pattern} is valid"
+                );
+            }
+
+            // An end anchor before anything, or a start anchor after the end, is unsatisfiable
+            for pattern in ["$a", "$^"] {
+                assert_eq!(
+                    Regex::new(pattern, construction).err(),
+                    Some(RegexError::MisplacedAnchor),
+                    "{pattern} should be rejected"
+                );
             }
         }
     }
 
-    open_paren_count == 0
-}
+    #[test]
+    fn regex_set_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let set = RegexSet::new(&["a(b|c)*", "ab*", "x"], construction).expect("Valid regexes");
+            assert_eq!(set.len(), 3);
 
-pub fn normalise_regex(regex: &str) -> String {
-    let mut normalised = String::new();
-    let mut escape_sequence = false;
-    let mut prev_char = '\0';
-    for curr_char in regex.chars() {
-        if escape_sequence {
-            // TODO: Implement further parsing features here (e.g. \w \d)
-            normalised.push(curr_char);
-            escape_sequence = false;
-            prev_char = curr_char;
-            continue;
+            for (text, matching) in [
+                ("ab", vec![0, 1]),
+                ("acb", vec![0]),
+                ("abbb", vec![0, 1]),
+                ("x", vec![2]),
+                ("", vec![]),
+                ("ax", vec![]),
+            ] {
+                assert_eq!(set.matching(text), matching, "Failed for input: {text}");
+                assert_eq!(
+                    set.is_match(text),
+                    !matching.is_empty(),
+                    "Failed for input: {text}"
+                );
+            }
         }
-        if curr_char == '\\' {
-            escape_sequence = true;
-            normalised.push(curr_char);
-            continue;
+
+        assert!(RegexSet::new(&["a", "(b"], ConstructionType::Glushkov).is_err());
+        let set = RegexSet::new(&[], ConstructionType::Glushkov).expect("Empty set");
+        assert!(set.is_empty());
+        assert!(!set.is_match(""));
+    }
+
+    /// Hand built DFA for `ab*` whose start state is not `0`.
+    struct OffsetStartDfa {
+        transitions: HashMap<(u32, char), u32>,
+        accepting_states: HashSet<u32>,
+        alphabet: Alphabet,
+    }
+
+    impl Dfa for OffsetStartDfa {
+        fn new(_regex: &str, _size_limit: Option<usize>) -> Result<Self, RegexError> {
+            Ok(OffsetStartDfa {
+                transitions: HashMap::from([((7, 'a'), 0), ((0, 'b'), 0)]),
+                accepting_states: HashSet::from([0]),
+                alphabet: Alphabet::default(),
+            })
         }
-        if curr_char == '+' {
-            match prev_char {
-                ')' => {
-                    let mut balance = 0;
-                    let mut group_start = 0;
-
-                    for j in (0..normalised.len()).rev() {
-                        let ch = normalised.chars().nth(j).unwrap();
-                        if ch == ')' {
-                            balance += 1;
-                        } else if ch == '(' {
-                            balance -= 1;
-                            if balance == 0 {
-                                group_start = j;
-                                break;
-                            }
-                        }
-                    }
 
-                    let group = String::from(&normalised[group_start..normalised.len()]);
-                    normalised.push_str(&group);
-                }
-                _ => {
-                    normalised.push(prev_char);
-                }
+        fn from_parts(
+            transitions: HashMap<(u32, char), u32>,
+            accepting_states: HashSet<u32>,
+            alphabet: Alphabet,
+        ) -> Self {
+            OffsetStartDfa {
+                transitions,
+                accepting_states,
+                alphabet,
             }
-            normalised.push('*');
-            prev_char = '*';
-            continue;
         }
-        if curr_char == '?' {
-            match prev_char {
-                ')' => {
-                    let mut balance = 0;
-                    for j in (0..normalised.len()).rev() {
-                        let ch = normalised.chars().nth(j).unwrap();
-                        if ch == ')' {
-                            balance += 1;
-                        } else if ch == '(' {
-                            balance -= 1;
-                            if balance == 0 {
-                                normalised.insert(j, '(');
-                                break;
-                            }
-                        }
-                    }
-                }
-                _ => {
-                    if normalised.len() > 0 {
-                        normalised.insert(normalised.len() - 1, '(');
-                    }
-                }
-            }
-            normalised.push_str("|)");
-            prev_char = ')';
-            continue;
+
+        fn get_transitions(&self) -> &HashMap<(u32, char), u32> {
+            &self.transitions
         }
-        if curr_char == '.' {
-            normalised.push_str("(a|b|c|d|e|f|g|h|i|j|k|l|m|n|o|p|q|r|s|t|u|v|w|x|y|z|A|B|C|D|E|F|G|H|I|J|K|L|M|N|O|P|Q|R|S|T|U|V|W|X|Y|Z|0|1|2|3|4|5|6|7|8|9| |!|\"|#|$|%|&|'|\\(|\\)|\\*|\\+|,|-|.|/|:|;|<|=|>|?|@|[|\\\\|]|^|_|`|{|}|~)");
-            prev_char = ')';
-            continue;
+
+        fn get_accepting_states(&self) -> &HashSet<u32> {
+            &self.accepting_states
         }
-        normalised.push(curr_char);
-        prev_char = curr_char;
-    }
-    normalised
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        fn get_transitions_mut(&mut self) -> &mut HashMap<(u32, char), u32> {
+            &mut self.transitions
+        }
 
-    #[test]
-    fn valid_regex_basic_test() {
-        let regex = "(a|b)*";
-        assert!(is_valid_regex(regex), "Expected valid regex.");
+        fn get_accepting_states_mut(&mut self) -> &mut HashSet<u32> {
+            &mut self.accepting_states
+        }
+
+        fn get_alphabet(&self) -> &Alphabet {
+            &self.alphabet
+        }
+
+        fn get_alphabet_mut(&mut self) -> &mut Alphabet {
+            &mut self.alphabet
+        }
+
+        fn start_state(&self) -> u32 {
+            7
+        }
     }
 
     #[test]
-    fn invalid_empty_regex_test() {
-        let regex = "";
-        assert!(!is_valid_regex(regex), "Expected invalid regex (empty).");
+    fn start_state_test() {
+        let mut dfa = OffsetStartDfa::new("ab*", None).expect("Hand built DFA");
+
+        for _ in 0..2 {
+            assert!(dfa.process("a"));
+            assert!(dfa.process("abb"));
+            assert!(!dfa.process(""));
+            assert!(!dfa.process("b"));
+            assert_eq!(dfa.find_at("xabbya", 0), Some((1, 4)));
+            let states = dfa.trace("abc");
+            assert_eq!((states.len(), states[0]), (3, 7));
+            assert_eq!(dfa.min_match_len(), Some(1));
+            assert_eq!(dfa.max_match_len(), None);
+
+            // Minimisation keeps the start state where it is
+            dfa.optimise_dfa();
+            assert_eq!(dfa.start_state(), 7);
+        }
     }
 
+    #[cfg(debug_assertions)]
     #[test]
-    fn invalid_unbalanced_parentheses_test() {
-        let regex1 = "(a|b";
-        let regex2 = "a|b)";
-        assert!(
-            !is_valid_regex(regex1),
-            "Expected invalid regex (unbalanced parentheses)."
-        );
-        assert!(
-            !is_valid_regex(regex2),
-            "Expected invalid regex (unbalanced parentheses)."
+    fn language_difference_test() {
+        // `ab*` with a redundant copy of the looping state
+        let redundant_transitions = HashMap::from([((0, 'a'), 1), ((1, 'b'), 2), ((2, 'b'), 1)]);
+        let redundant_accepting_states = HashSet::from([1, 2]);
+        let minimal_transitions = HashMap::from([((0, 'a'), 1), ((1, 'b'), 1)]);
+        let minimal_accepting_states = HashSet::from([1]);
+        assert_eq!(
+            language_difference(
+                (&redundant_transitions, &redundant_accepting_states, 0),
+                (&minimal_transitions, &minimal_accepting_states, 0),
+            ),
+            None
         );
-    }
 
-    #[test]
-    fn invalid_operator_placement_test() {
-        let regex1 = "*a";
-        let regex2 = "(+abc|x)";
-        assert!(
-            !is_valid_regex(regex1),
-            "Expected invalid regex (invalid quantifier placement)."
+        // Dropping the accepting state behind `b` changes the language
+        let wrong_accepting_states = HashSet::from([1]);
+        assert_eq!(
+            language_difference(
+                (&redundant_transitions, &redundant_accepting_states, 0),
+                (&redundant_transitions, &wrong_accepting_states, 0),
+            ),
+            Some("ab".to_string())
         );
-        assert!(
-            !is_valid_regex(regex2),
-            "Expected invalid regex (invalid alternation placement)."
+
+        // Minimising the crafted DFAs runs the verification and has to pass it
+        let mut dfa = GlushkovDfa::from_parts(
+            redundant_transitions,
+            redundant_accepting_states,
+            Alphabet::default(),
         );
+        dfa.optimise_dfa();
+        assert_eq!(dfa.get_transitions(), &minimal_transitions);
+        let mut dfa = OffsetStartDfa::new("ab*", None).expect("Hand built DFA");
+        dfa.optimise_dfa();
+        assert!(dfa.process("abb"));
     }
 
     #[test]
-    fn valid_nested_parentheses_test() {
-        let regex = "((a|b)*c)";
-        assert!(
-            is_valid_regex(regex),
-            "Expected valid regex with nested parentheses."
-        );
+    fn literal_mode_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = RegexBuilder::new("a.b", construction)
+                .literal(true)
+                .build()
+                .expect("Any literal");
+            assert!(regex.is_match("a.b"));
+            assert!(!regex.is_match("axb"));
+            assert_eq!(regex.findall("axb a.b a.bb"), vec!["a.b", "a.b"]);
+
+            let regex = RegexBuilder::new(r"(a|\Qé*\", construction)
+                .literal(true)
+                .build()
+                .expect("Any literal");
+            assert!(regex.is_match(r"(a|\Qé*\"));
+            assert_eq!(regex.find(r"x(a|\Qé*\y"), Some(r"(a|\Qé*\"));
+
+            let regex = RegexBuilder::new("", construction)
+                .literal(true)
+                .build()
+                .expect("Any literal");
+            assert!(regex.is_match(""));
+            assert!(!regex.is_match("a"));
+
+            let regex = RegexBuilder::new("A+B", construction)
+                .literal(true)
+                .char_map(|c| c.to_ascii_lowercase())
+                .build()
+                .expect("Any literal");
+            assert!(regex.is_match("a+b"));
+            assert_eq!(regex.find("xxA+bx"), Some("A+b"));
+            assert!(!regex.is_match("aab"));
+        }
     }
 
     #[test]
-    fn valid_escape_sequence_test() {
-        let regex = "a\\*b";
-        assert!(
-            is_valid_regex(regex),
-            "Expected valid regex with escape sequence."
-        );
+    fn dot_matches_newline_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("a.b", construction).expect("Valid regex");
+            assert!(!regex.is_match("a\nb"));
+
+            let regex = RegexBuilder::new("a.b", construction)
+                .dot_matches_newline(false)
+                .build()
+                .expect("Valid regex");
+            assert!(!regex.is_match("a\nb"));
+            assert!(regex.is_match("axb"));
+
+            let regex = RegexBuilder::new("a.b", construction)
+                .dot_matches_newline(true)
+                .build()
+                .expect("Valid regex");
+            assert!(regex.is_match("a\nb"));
+            assert!(regex.is_match("axb"));
+            assert!(!regex.is_match("a\n\nb"));
+
+            // Escaped and quoted dots stay literal
+            let regex = RegexBuilder::new(r"(?P<x>a\.)\Q.\E(?#.)", construction)
+                .dot_matches_newline(true)
+                .build()
+                .expect("Valid regex");
+            assert!(regex.is_match("a.."));
+            assert!(!regex.is_match("a.\n"));
+            assert_eq!(regex.capture_names(), vec![None, Some("x".to_string())]);
+
+            let regex = RegexBuilder::new("^A.b$", construction)
+                .dot_matches_newline(true)
+                .char_map(|c| c.to_ascii_lowercase())
+                .build()
+                .expect("Valid regex");
+            assert!(regex.is_match("a\nB"));
+            assert_eq!(regex.find("A\nb"), Some("A\nb"));
+            assert_eq!(regex.find("xa\nb"), None);
+        }
     }
 
     #[test]
-    fn invalid_escape_sequence_test() {
-        let regex = "a\\";
-        assert!(
-            !is_valid_regex(regex),
-            "Expected invalid regex with unpaired escape."
-        );
+    fn grapheme_clusters_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex = Regex::new("^.$", construction).expect("Valid regex");
+            assert!(!regex.is_match("e\u{301}"));
+
+            let regex = RegexBuilder::new("^.$", construction)
+                .grapheme_clusters(true)
+                .build()
+                .expect("Valid regex");
+            assert!(regex.is_match("e"));
+            // `e` with a combining acute accent, and with an acute and a dot below
+            assert!(regex.is_match("e\u{301}"));
+            assert!(regex.is_match("e\u{301}\u{323}"));
+            assert!(!regex.is_match("ee\u{301}"));
+            assert!(!regex.is_match("\u{301}"));
+
+            let regex = RegexBuilder::new("a.c", construction)
+                .grapheme_clusters(true)
+                .build()
+                .expect("Valid regex");
+            assert_eq!(regex.find("xab\u{308}cx"), Some("ab\u{308}c"));
+            assert_eq!(
+                regex.findall("abc ab\u{308}\u{20DD}c"),
+                vec!["abc", "ab\u{308}\u{20DD}c"]
+            );
+            assert_eq!(regex.find("ab\u{308}\nc"), None);
+
+            // Only the dot is cluster aware, a literal char does not consume the marks
+            let regex = RegexBuilder::new("e$", construction)
+                .grapheme_clusters(true)
+                .build()
+                .expect("Valid regex");
+            assert_eq!(regex.find("e\u{301}"), None);
+
+            let regex = RegexBuilder::new("a.c", construction)
+                .grapheme_clusters(true)
+                .dot_matches_newline(true)
+                .build()
+                .expect("Valid regex");
+            assert!(regex.is_match("a\n\u{301}c"));
+            assert!(regex.is_match("abc"));
+        }
     }
 
     #[test]
-    fn normalise_regex_test() {
-        let cases = [
-            (r"a+", r"aa*"),
-            (r"a\+", r"a\+"),
-            (r"a?", r"(a|)"),
-            (r"a\?", r"a\?"),
-            (r"(ab)?", r"((ab)|)"),
-            (
-                r".",
-                "(a|b|c|d|e|f|g|h|i|j|k|l|m|n|o|p|q|r|s|t|u|v|w|x|y|z|A|B|C|D|E|F|G|H|I|J|K|L|M|N|O|P|Q|R|S|T|U|V|W|X|Y|Z|0|1|2|3|4|5|6|7|8|9| |!|\"|#|$|%|&|'|\\(|\\)|\\*|\\+|,|-|.|/|:|;|<|=|>|?|@|[|\\\\|]|^|_|`|{|}|~)",
-            ),
-        ];
+    fn char_map_test() {
+        let digits_to_zero = |c: char| if c.is_ascii_digit() { '0' } else { c };
 
-        for (input, expected) in cases {
-            let result = normalise_regex(input);
-            assert_eq!(result, expected, "Normalisation failed for input '{input}'");
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = RegexBuilder::new(r"0+-\7", construction)
+                .char_map(digits_to_zero)
+                .build()
+                .expect("Valid regex");
+            assert!(regex_object.is_match("0-0"));
+            assert!(regex_object.is_match("123-4"));
+            assert!(!regex_object.is_match("a-1"));
+            assert_eq!(regex_object.find("ab 42-1 c"), Some("42-1"));
         }
+
+        // Mapping to an operator character keeps it literal
+        let regex_object = RegexBuilder::new("x+", ConstructionType::Glushkov)
+            .char_map(|c| if c == 'x' { '*' } else { c })
+            .build()
+            .expect("Valid regex");
+        assert!(regex_object.is_match("x*x"));
+        assert!(!regex_object.is_match(""));
+        assert_eq!(
+            map_pattern_chars(r"x+\p{L}\.", |c| c.to_ascii_uppercase()),
+            Ok(r"X+\p{L}\.".to_string())
+        );
+
+        let regex_object = RegexBuilder::new(".*ab", ConstructionType::Glushkov)
+            .char_map(|c| c.to_ascii_lowercase())
+            .build()
+            .expect("Valid regex")
+            .then_literal("C");
+        assert_eq!(regex_object.find("xxABc"), Some("xxABc"));
     }
 
     #[test]
-    fn is_match_test() {
-        let regex_object = Regex::new("a(a|b)*", ConstructionType::Thompson).expect("Valid regex");
+    fn required_literal_test() {
+        let cases = [
+            ("ab*c-foo-(d|e)*", Some("c-foo-")),
+            ("x(yzw)*v", Some("x")),
+            (r"a\.b\p{L}c", Some("a.b")),
+            ("(a|b)(cd|ce)", None),
+            ("(ab|cd)-(efg)", Some("efg")),
+            ("a+bcd", Some("bcd")),
+            ("a?bc", Some("bc")),
+            (".", None),
+        ];
 
-        let success_strings = vec!["abababaaaababa", "a"];
-        for string in success_strings {
-            assert!(regex_object.is_match(string));
+        for (pattern, expected) in cases {
+            assert_eq!(
+                required_literal(&normalise_regex(pattern)),
+                expected.map(str::to_string),
+                "Failed for pattern: {pattern}"
+            );
         }
 
-        let failing_strings = vec!["abc", "x"];
-        for string in failing_strings {
-            assert!(!regex_object.is_match(string));
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new("(a|b)*-foo-(a|b)", construction).expect("Valid regex");
+            assert_eq!(regex_object.find("ab-fo-a abb-foo-b"), Some("abb-foo-b"));
+            assert_eq!(regex_object.find("ab-fo-a abb-foo-"), None);
+            assert_eq!(
+                regex_object.findall("a-foo-b-foo-b"),
+                vec!["a-foo-b", "-foo-b"]
+            );
         }
+
+        // A bounded match length lets the search start right before the literal
+        assert_eq!(prefilter_start("xxxxxab", 0, "ab", Some(3)), Some(4));
+        assert_eq!(prefilter_start("xéxab", 0, "ab", Some(3)), Some(3));
+        assert_eq!(prefilter_start("xab", 0, "ab", Some(5)), Some(0));
+        assert_eq!(prefilter_start("xxab", 1, "ab", None), Some(1));
+        assert_eq!(prefilter_start("xxa", 0, "ab", None), None);
+
+        let regex_object = Regex::new("c(x|y)ab", ConstructionType::Glushkov).expect("Valid regex");
+        assert_eq!(regex_object.find("cxab cyab"), Some("cxab"));
+        assert_eq!(regex_object.find("ccyab"), Some("cyab"));
     }
 
     #[test]
-    fn find_test() {
-        let regex_object = Regex::new("abc", ConstructionType::Thompson).expect("Valid regex");
-        let test_cases = vec![
-            ("abcd", Some("abc")),
-            ("xyzabc", Some("abc")),
-            ("abc", Some("abc")),
-            ("ac", None),
-            ("def", None),
-            ("aabc", Some("abc")),
-        ];
-
-        for (text, expected) in test_cases {
-            let result = regex_object.find(text);
-            assert_eq!(result, expected, "Failed for input: {text}");
+    fn out_degree_histogram_test() {
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new("(a|b)*", construction).expect("Valid regex");
+            assert_eq!(
+                regex_object.out_degree_histogram(),
+                BTreeMap::from([(2, 1)])
+            );
         }
+
+        let regex_object =
+            Regex::new("a(b|c|d)e*", ConstructionType::Glushkov).expect("Valid regex");
+        assert_eq!(
+            regex_object.out_degree_histogram(),
+            BTreeMap::from([(1, 2), (3, 1)])
+        );
     }
 
     #[test]
-    fn find_all_test() {
-        let regex_object = Regex::new("abc*", ConstructionType::Thompson).expect("Valid regex");
-        let test_cases = vec![
-            ("abcd", vec!["abc"]),
-            ("ac", vec![]),
-            ("abcab", vec!["abc", "ab"]),
-        ];
+    fn sample_random_test() {
+        for pattern in ["a(b|c)*", "(ab|cd)+e?", "x*", "hel+o"] {
+            for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+                let regex_object = Regex::new(pattern, construction).expect("Valid regex");
+                for seed in 0..20 {
+                    let sample = regex_object
+                        .sample_random(seed, 12)
+                        .expect("Pattern accepts short strings");
+                    assert!(sample.chars().count() <= 12);
+                    assert!(
+                        regex_object.is_match(&sample),
+                        "Sample '{sample}' for '{pattern}' (seed {seed}) does not match"
+                    );
+                    assert_eq!(regex_object.sample_random(seed, 12), Some(sample));
+                }
+            }
+        }
 
-        for (text, expected) in test_cases {
-            let result = regex_object.findall(text);
-            assert_eq!(result, expected, "Failed for input: {text}");
+        let regex_object = Regex::new("abcdef", ConstructionType::Thompson).expect("Valid regex");
+        assert_eq!(regex_object.sample_random(7, 3), None);
+
+        // The chars of a class are drawn from all of it, not only its representative
+        for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+            let regex_object = Regex::new(r"\p{Lu}", construction).expect("Valid regex");
+            let samples: HashSet<String> = (0..20)
+                .map(|seed| regex_object.sample_random(seed, 1).expect("One char"))
+                .collect();
+            assert!(samples.len() > 1, "Only sampled {samples:?}");
+            assert!(samples.iter().all(|sample| regex_object.is_match(sample)));
+        }
+
+        let mut rng = SplitMix64::new(3);
+        for _ in 0..100 {
+            let c = random_cell_member(&mut rng, ('\u{D7FF}', '\u{E000}'));
+            assert!(c == '\u{D7FF}' || c == '\u{E000}');
         }
     }
 }
@@ -0,0 +1,217 @@
+use crate::alphabet::Alphabet;
+use crate::{
+    AntimirovDfa, BrzozowskiDfa, Dfa, DfaType, GlushkovDfa, Regex, RegexError, ThompsonDfa,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Bytes every serialised regex starts with.
+const MAGIC: &[u8; 4] = b"RGXE";
+/// Version of the layout below, bumped whenever it changes.
+const FORMAT_VERSION: u8 = 1;
+/// Width in bytes of the state ids, which are `u32`.
+const STATE_ID_WIDTH: u8 = 4;
+
+const THOMPSON_TAG: u8 = 0;
+const GLUSHKOV_TAG: u8 = 1;
+const BRZOZOWSKI_TAG: u8 = 2;
+const ANTIMIROV_TAG: u8 = 3;
+
+/// Serialises `regex` into the header followed by its DFA and search metadata.
+///
+/// The header is the magic bytes, the format version, the construction type and the width of
+/// the state ids. All numbers are little endian `u32`s, strings are prefixed with their
+/// length and optional values with a `0` or `1` byte. Returns `None` for a regex with a char
+/// map, since a function cannot be serialised.
+pub(crate) fn regex_to_bytes(regex: &Regex) -> Option<Vec<u8>> {
+    let dfa = regex.dfa();
+    let alphabet = dfa.get_alphabet();
+    if alphabet.has_char_map() {
+        return None;
+    }
+
+    let mut bytes = Vec::new();
+    bytes.extend(MAGIC);
+    bytes.push(FORMAT_VERSION);
+    bytes.push(match regex.dfa {
+        DfaType::Thompson(_) => THOMPSON_TAG,
+        DfaType::Glushkov(_) => GLUSHKOV_TAG,
+        DfaType::Brzozowski(_) => BRZOZOWSKI_TAG,
+        DfaType::Antimirov(_) => ANTIMIROV_TAG,
+    });
+    bytes.push(STATE_ID_WIDTH);
+    bytes.push(u8::from(regex.anchored_start) | u8::from(regex.anchored_end) << 1);
+
+    write_optional_str(&mut bytes, regex.dot_star_literal.as_deref());
+    write_optional_str(&mut bytes, regex.required_literal.as_deref());
+    write_u32(&mut bytes, regex.capture_names.len());
+    for name in &regex.capture_names {
+        write_optional_str(&mut bytes, name.as_deref());
+    }
+
+    let cells = alphabet.cells();
+    write_u32(&mut bytes, cells.len());
+    for &(start, end) in cells {
+        write_u32(&mut bytes, start as usize);
+        write_u32(&mut bytes, end as usize);
+    }
+
+    // Sorted, so equal regexes serialise to equal bytes
+    let mut accepting_states: Vec<u32> = dfa.get_accepting_states().iter().copied().collect();
+    accepting_states.sort_unstable();
+    write_u32(&mut bytes, accepting_states.len());
+    for state in accepting_states {
+        write_u32(&mut bytes, state as usize);
+    }
+
+    let mut transitions: Vec<(&(u32, char), &u32)> = dfa.get_transitions().iter().collect();
+    transitions.sort_unstable();
+    write_u32(&mut bytes, transitions.len());
+    for (&(source_state, symbol), &target_state) in transitions {
+        write_u32(&mut bytes, source_state as usize);
+        write_u32(&mut bytes, symbol as usize);
+        write_u32(&mut bytes, target_state as usize);
+    }
+
+    Some(bytes)
+}
+
+/// Deserialises a regex written by `regex_to_bytes`.
+///
+/// Any mismatch in the header, a truncated buffer, trailing bytes or a value that is not a
+/// char or a string is reported as `RegexError::BadSerialFormat`.
+pub(crate) fn regex_from_bytes(bytes: &[u8]) -> Result<Regex, RegexError> {
+    let mut reader = Reader { bytes, pos: 0 };
+    if reader.take(MAGIC.len())? != MAGIC || reader.byte()? != FORMAT_VERSION {
+        return Err(RegexError::BadSerialFormat);
+    }
+    let construction = reader.byte()?;
+    if !matches!(
+        construction,
+        THOMPSON_TAG | GLUSHKOV_TAG | BRZOZOWSKI_TAG | ANTIMIROV_TAG
+    ) || reader.byte()? != STATE_ID_WIDTH
+    {
+        return Err(RegexError::BadSerialFormat);
+    }
+    let anchors = reader.byte()?;
+    if anchors > 0b11 {
+        return Err(RegexError::BadSerialFormat);
+    }
+
+    let dot_star_literal = reader.optional_string()?;
+    let required_literal = reader.optional_string()?;
+    let capture_names = (0..reader.u32()?)
+        .map(|_| reader.optional_string())
+        .collect::<Result<Vec<_>, _>>()?;
+    // The whole match is always capture group 0
+    if capture_names.is_empty() {
+        return Err(RegexError::BadSerialFormat);
+    }
+
+    let cells = (0..reader.u32()?)
+        .map(|_| Ok((reader.char()?, reader.char()?)))
+        .collect::<Result<Vec<_>, RegexError>>()?;
+    if cells.iter().any(|(start, end)| start > end) || cells.windows(2).any(|w| w[0].1 >= w[1].0) {
+        return Err(RegexError::BadSerialFormat);
+    }
+    let alphabet = Alphabet::from_cells(&cells);
+
+    let accepting_states = (0..reader.u32()?)
+        .map(|_| reader.u32())
+        .collect::<Result<HashSet<u32>, _>>()?;
+    let transitions = (0..reader.u32()?)
+        .map(|_| Ok(((reader.u32()?, reader.char()?), reader.u32()?)))
+        .collect::<Result<HashMap<(u32, char), u32>, RegexError>>()?;
+    if reader.pos != bytes.len() {
+        return Err(RegexError::BadSerialFormat);
+    }
+
+    let dfa_type = match construction {
+        THOMPSON_TAG => DfaType::Thompson(ThompsonDfa::from_parts(
+            transitions,
+            accepting_states,
+            alphabet,
+        )),
+        GLUSHKOV_TAG => DfaType::Glushkov(GlushkovDfa::from_parts(
+            transitions,
+            accepting_states,
+            alphabet,
+        )),
+        BRZOZOWSKI_TAG => DfaType::Brzozowski(BrzozowskiDfa::from_parts(
+            transitions,
+            accepting_states,
+            alphabet,
+        )),
+        _ => DfaType::Antimirov(AntimirovDfa::from_parts(
+            transitions,
+            accepting_states,
+            alphabet,
+        )),
+    };
+    let mut regex = Regex::from_dfa(dfa_type, dot_star_literal);
+    regex.required_literal = required_literal;
+    regex.capture_names = capture_names;
+    regex.anchored_start = anchors & 1 != 0;
+    regex.anchored_end = anchors & 0b10 != 0;
+    Ok(regex)
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: usize) {
+    let value = u32::try_from(value).expect("Fits the u32 state ids");
+    bytes.extend(value.to_le_bytes());
+}
+
+fn write_optional_str(bytes: &mut Vec<u8>, value: Option<&str>) {
+    match value {
+        Some(value) => {
+            bytes.push(1);
+            write_u32(bytes, value.len());
+            bytes.extend(value.as_bytes());
+        }
+        None => bytes.push(0),
+    }
+}
+
+/// Reads the values of a serialised regex one after another.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], RegexError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or(RegexError::BadSerialFormat)?;
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, RegexError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, RegexError> {
+        let bytes = self.take(4)?.try_into().expect("Four bytes");
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn char(&mut self) -> Result<char, RegexError> {
+        char::from_u32(self.u32()?).ok_or(RegexError::BadSerialFormat)
+    }
+
+    fn optional_string(&mut self) -> Result<Option<String>, RegexError> {
+        match self.byte()? {
+            0 => Ok(None),
+            1 => {
+                let len = self.u32()? as usize;
+                let value = std::str::from_utf8(self.take(len)?)
+                    .map_err(|_| RegexError::BadSerialFormat)?;
+                Ok(Some(value.to_string()))
+            }
+            _ => Err(RegexError::BadSerialFormat),
+        }
+    }
+}
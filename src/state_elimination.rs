@@ -0,0 +1,226 @@
+use crate::{Dfa, alphabet::META_CHARS};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Regular expression over the representatives of a DFA, built up while eliminating states.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Expr {
+    Epsilon,
+    Char(char),
+    /// Inclusive range of characters, written as a bracket class.
+    Range(char, char),
+    Union(Vec<Expr>),
+    Concat(Vec<Expr>),
+    Star(Box<Expr>),
+}
+
+impl Expr {
+    fn union(self, other: Expr) -> Expr {
+        let mut items = Vec::new();
+        for expr in [self, other] {
+            match expr {
+                Expr::Union(inner) => items.extend(inner),
+                expr => items.push(expr),
+            }
+        }
+        items.sort();
+        items.dedup();
+
+        // A star already matches the empty string
+        if items.iter().any(|item| matches!(item, Expr::Star(_))) {
+            items.retain(|item| *item != Expr::Epsilon);
+        }
+
+        if items.len() == 1 {
+            items.pop().expect("One item left")
+        } else {
+            Expr::Union(items)
+        }
+    }
+
+    fn concat(self, other: Expr) -> Expr {
+        match (self, other) {
+            (Expr::Epsilon, expr) | (expr, Expr::Epsilon) => expr,
+            (left, right) => {
+                let mut items = Vec::new();
+                for expr in [left, right] {
+                    match expr {
+                        Expr::Concat(inner) => items.extend(inner),
+                        expr => items.push(expr),
+                    }
+                }
+                Expr::Concat(items)
+            }
+        }
+    }
+
+    fn star(self) -> Expr {
+        match self {
+            Expr::Epsilon | Expr::Star(_) => self,
+            Expr::Union(items) if items.contains(&Expr::Epsilon) => items
+                .into_iter()
+                .filter(|item| *item != Expr::Epsilon)
+                .reduce(Expr::union)
+                .map_or(Expr::Epsilon, Expr::star),
+            expr => Expr::Star(Box::new(expr)),
+        }
+    }
+
+    fn write(&self, pattern: &mut String) {
+        match self {
+            Expr::Epsilon => pattern.push_str("()"),
+            Expr::Char(c) => write_char(*c, false, pattern),
+            Expr::Range(start, end) => {
+                pattern.push('[');
+                write_char(*start, true, pattern);
+                pattern.push('-');
+                write_char(*end, true, pattern);
+                pattern.push(']');
+            }
+            Expr::Union(items) if items.contains(&Expr::Epsilon) => {
+                let rest = items
+                    .iter()
+                    .filter(|item| **item != Expr::Epsilon)
+                    .cloned()
+                    .reduce(Expr::union)
+                    .expect("Union has at least two items");
+                rest.write_atom(pattern);
+                pattern.push('?');
+            }
+            Expr::Union(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        pattern.push('|');
+                    }
+                    item.write(pattern);
+                }
+            }
+            Expr::Concat(items) => {
+                for item in items {
+                    match item {
+                        Expr::Union(inner) if !inner.contains(&Expr::Epsilon) => {
+                            item.write_atom(pattern)
+                        }
+                        _ => item.write(pattern),
+                    }
+                }
+            }
+            Expr::Star(inner) => {
+                inner.write_atom(pattern);
+                pattern.push('*');
+            }
+        }
+    }
+
+    /// Writes the expression so that a following quantifier applies to all of it.
+    fn write_atom(&self, pattern: &mut String) {
+        if let Expr::Char(_) | Expr::Range(..) = self {
+            self.write(pattern);
+        } else {
+            pattern.push('(');
+            self.write(pattern);
+            pattern.push(')');
+        }
+    }
+}
+
+/// Writes a literal character, as a codepoint escape unless it is printable ASCII.
+///
+/// Inside a bracket class a `-` is escaped as well, so it is not read as a range.
+fn write_char(c: char, in_class: bool, pattern: &mut String) {
+    if !c.is_ascii_graphic() && c != ' ' {
+        pattern.push_str(&format!("\\u{{{:X}}}", c as u32));
+    } else {
+        if META_CHARS.contains(&c) || (in_class && c == '-') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Node {
+    Start,
+    State(u32),
+    End,
+}
+
+/// Converts `dfa` back into a pattern accepting the same language by state elimination.
+///
+/// A fresh start and end node are connected to the DFA with empty transitions, then the states
+/// are removed one by one, the state with the fewest paths through it first, rerouting every
+/// path through it over a single edge labelled with the combined expression. A transition on
+/// a cell of more than one character becomes a range. Returns `None` for the empty language.
+pub(crate) fn dfa_to_pattern(dfa: &dyn Dfa) -> Option<String> {
+    let alphabet = dfa.get_alphabet();
+    let mut remaining = dfa.live_states();
+    if !remaining.contains(&dfa.start_state()) {
+        return None;
+    }
+
+    let mut edges: BTreeMap<(Node, Node), Expr> = BTreeMap::new();
+    edges.insert((Node::Start, Node::State(dfa.start_state())), Expr::Epsilon);
+    for &state in dfa.get_accepting_states() {
+        edges.insert((Node::State(state), Node::End), Expr::Epsilon);
+    }
+    for (&(source_state, representative), target_state) in dfa.get_transitions() {
+        if !remaining.contains(&source_state) || !remaining.contains(target_state) {
+            continue;
+        }
+        let cell_expr = match alphabet.cell(representative) {
+            (cell_start, cell_end) if cell_start == cell_end => Expr::Char(representative),
+            (cell_start, cell_end) => Expr::Range(cell_start, cell_end),
+        };
+        let key = (Node::State(source_state), Node::State(*target_state));
+        let expr = match edges.remove(&key) {
+            Some(existing) => existing.union(cell_expr),
+            None => cell_expr,
+        };
+        edges.insert(key, expr);
+    }
+
+    let mut remaining: BTreeSet<u32> = remaining.drain().collect();
+    while let Some(state) = remaining.iter().copied().min_by_key(|&state| {
+        let node = Node::State(state);
+        let incoming = edges.keys().filter(|&&(_, target)| target == node).count();
+        let outgoing = edges.keys().filter(|&&(source, _)| source == node).count();
+        incoming * outgoing
+    }) {
+        remaining.remove(&state);
+        let node = Node::State(state);
+
+        let self_loop = edges
+            .remove(&(node, node))
+            .map_or(Expr::Epsilon, Expr::star);
+        let incoming: Vec<(Node, Expr)> = edges
+            .iter()
+            .filter(|&(&(_, target), _)| target == node)
+            .map(|(&(source, _), expr)| (source, expr.clone()))
+            .collect();
+        let outgoing: Vec<(Node, Expr)> = edges
+            .iter()
+            .filter(|&(&(source, _), _)| source == node)
+            .map(|(&(_, target), expr)| (target, expr.clone()))
+            .collect();
+        edges.retain(|&(source, target), _| source != node && target != node);
+
+        for (source, incoming_expr) in &incoming {
+            for (target, outgoing_expr) in &outgoing {
+                let path = incoming_expr
+                    .clone()
+                    .concat(self_loop.clone())
+                    .concat(outgoing_expr.clone());
+                let key = (*source, *target);
+                let expr = match edges.remove(&key) {
+                    Some(existing) => existing.union(path),
+                    None => path,
+                };
+                edges.insert(key, expr);
+            }
+        }
+    }
+
+    let expr = edges.remove(&(Node::Start, Node::End))?;
+    let mut pattern = String::new();
+    expr.write(&mut pattern);
+    Some(pattern)
+}
@@ -1,29 +1,44 @@
-use crate::{Dfa, is_valid_regex, normalise_regex};
-use std::collections::{HashMap, HashSet};
+use crate::alphabet::{Alphabet, parse_class, parse_escape, pattern_classes};
+use crate::{Dfa, RegexError, check_size_limit, normalise_regex, validate_regex};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 struct Nfa {
     transitions: HashMap<(u32, Option<char>), Vec<u32>>,
     accepting_state: u32, // the thompson construction always has one accepting_state
 }
 
+#[derive(Clone)]
 pub struct ThompsonDfa {
     transitions: HashMap<(u32, char), u32>,
     accepting_states: HashSet<u32>,
+    alphabet: Alphabet,
 }
 
 impl Dfa for ThompsonDfa {
-    fn new(regex: &str) -> Result<Self, String> {
-        if !is_valid_regex(regex) {
-            return Err("{regex} is not a valid regular expression!".to_string());
-        }
+    fn new(regex: &str, size_limit: Option<usize>) -> Result<Self, RegexError> {
+        validate_regex(regex)?;
 
         let normalised_regex = normalise_regex(regex);
-        let regex_nfa: Nfa = thompson_construction(&normalised_regex);
-        let mut regex_dfa = nfa_to_dfa(&regex_nfa);
+        let alphabet = Alphabet::new(&pattern_classes(&normalised_regex)?);
+        let regex_nfa: Nfa = thompson_construction(&normalised_regex, &alphabet)?;
+        let mut regex_dfa = nfa_to_dfa(&regex_nfa, size_limit)?;
+        regex_dfa.alphabet = alphabet;
         <Self as Dfa>::optimise_dfa(&mut regex_dfa);
         Ok(regex_dfa)
     }
 
+    fn from_parts(
+        transitions: HashMap<(u32, char), u32>,
+        accepting_states: HashSet<u32>,
+        alphabet: Alphabet,
+    ) -> Self {
+        ThompsonDfa {
+            transitions,
+            accepting_states,
+            alphabet,
+        }
+    }
+
     fn get_transitions(&self) -> &HashMap<(u32, char), u32> {
         &self.transitions
     }
@@ -39,41 +54,50 @@ impl Dfa for ThompsonDfa {
     fn get_accepting_states_mut(&mut self) -> &mut HashSet<u32> {
         &mut self.accepting_states
     }
+
+    fn get_alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    fn get_alphabet_mut(&mut self) -> &mut Alphabet {
+        &mut self.alphabet
+    }
 }
 
 // THOMPSON CONSTRUCTION ---
-fn thompson_construction(normalised_regex: &str) -> Nfa {
-    fn apply_operator(nfa_stack: &mut Vec<Nfa>, operator: char) {
+fn thompson_construction(normalised_regex: &str, alphabet: &Alphabet) -> Result<Nfa, RegexError> {
+    fn pop_operand(nfa_stack: &mut Vec<Nfa>, operation: &str) -> Result<Nfa, RegexError> {
+        nfa_stack
+            .pop()
+            .ok_or_else(|| RegexError::Syntax(format!("Expected NFA for {operation}")))
+    }
+
+    fn apply_operator(nfa_stack: &mut Vec<Nfa>, operator: char) -> Result<(), RegexError> {
         match operator {
             '|' => {
-                let nfa_right = nfa_stack.pop().expect("Expected NFA for union");
-                let nfa_left = nfa_stack.pop().expect("Expected NFA for union");
+                let nfa_right = pop_operand(nfa_stack, "union")?;
+                let nfa_left = pop_operand(nfa_stack, "union")?;
                 nfa_stack.push(union(&nfa_left, &nfa_right));
             }
             '.' => {
-                let nfa_right = nfa_stack.pop().expect("Expected NFA for concatenation");
-                let nfa_left = nfa_stack.pop().expect("Expected NFA for concatenation");
+                let nfa_right = pop_operand(nfa_stack, "concatenation")?;
+                let nfa_left = pop_operand(nfa_stack, "concatenation")?;
                 nfa_stack.push(concatenate(&nfa_left, &nfa_right));
             }
             _ => unreachable!("Unknown operator {}", operator),
         }
+        Ok(())
     }
 
     let mut operators: Vec<char> = Vec::new();
     let mut nfa_stack: Vec<Nfa> = Vec::new();
     let mut concat_flag = false;
-    let mut escape_sequence = false;
 
-    for symbol in normalised_regex.chars() {
-        if escape_sequence {
-            if concat_flag {
-                operators.push('.');
-            }
-            nfa_stack.push(create_basic_nfa(&symbol));
-            concat_flag = true;
-            escape_sequence = false;
-            continue;
-        }
+    let chars: Vec<char> = normalised_regex.chars().collect();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let symbol = chars[pos];
+        pos += 1;
 
         match symbol {
             '(' => {
@@ -94,7 +118,7 @@ fn thompson_construction(normalised_regex: &str) -> Nfa {
                     if op == '(' {
                         break;
                     }
-                    apply_operator(&mut nfa_stack, op);
+                    apply_operator(&mut nfa_stack, op)?;
                 }
 
                 // If stack is empty after processing, we had completely empty parentheses
@@ -105,7 +129,11 @@ fn thompson_construction(normalised_regex: &str) -> Nfa {
                 concat_flag = true;
             }
             '*' => {
-                let last_nfa = nfa_stack.pop().expect("Expected NFA for Kleene Star");
+                // Without an operand the star would apply to an NFA outside the current group
+                if !concat_flag {
+                    return Err(RegexError::DanglingQuantifier);
+                }
+                let last_nfa = pop_operand(&mut nfa_stack, "Kleene Star")?;
                 nfa_stack.push(apply_kleene_star(&last_nfa));
                 concat_flag = true;
             }
@@ -116,7 +144,7 @@ fn thompson_construction(normalised_regex: &str) -> Nfa {
                         break;
                     }
                     operators.pop();
-                    apply_operator(&mut nfa_stack, op);
+                    apply_operator(&mut nfa_stack, op)?;
                 }
 
                 // If we have no operand for the left side of union, create epsilon
@@ -127,8 +155,18 @@ fn thompson_construction(normalised_regex: &str) -> Nfa {
                 operators.push('|');
                 concat_flag = false;
             }
-            '\\' => {
-                escape_sequence = true;
+            '\\' | '[' => {
+                let (class, end) = if symbol == '\\' {
+                    parse_escape(&chars, pos)?
+                } else {
+                    parse_class(&chars, pos)?
+                };
+                if concat_flag {
+                    operators.push('.');
+                }
+                nfa_stack.push(create_class_nfa(&alphabet.representatives(&class)));
+                concat_flag = true;
+                pos = end;
             }
             _ => {
                 if concat_flag {
@@ -150,19 +188,19 @@ fn thompson_construction(normalised_regex: &str) -> Nfa {
     // Process remaining operators
     while let Some(op) = operators.pop() {
         if op == '(' {
-            panic!("Unmatched opening parenthesis");
+            return Err(RegexError::UnbalancedParens);
         }
-        apply_operator(&mut nfa_stack, op);
+        apply_operator(&mut nfa_stack, op)?;
     }
 
     if nfa_stack.len() != 1 {
-        panic!(
+        return Err(RegexError::Syntax(format!(
             "Invalid Regex, unexpected final NFA stack size: {}",
             nfa_stack.len()
-        );
+        )));
     }
 
-    nfa_stack.pop().unwrap()
+    Ok(nfa_stack.pop().unwrap())
 }
 
 fn apply_kleene_star(last_nfa: &Nfa) -> Nfa {
@@ -269,6 +307,16 @@ fn create_basic_nfa(letter: &char) -> Nfa {
     }
 }
 
+fn create_class_nfa(letters: &[char]) -> Nfa {
+    Nfa {
+        transitions: letters
+            .iter()
+            .map(|&letter| ((0, Some(letter)), vec![1]))
+            .collect(),
+        accepting_state: 1,
+    }
+}
+
 fn create_basic_epsilon_nfa() -> Nfa {
     Nfa {
         transitions: HashMap::from([((0, None), vec![1])]),
@@ -279,14 +327,13 @@ fn create_basic_epsilon_nfa() -> Nfa {
 
 // NFA to DFA functions ---
 fn epsilon_closure(nfa: &Nfa, states: &mut HashSet<u32>) {
-    let mut stack = states.clone();
+    let mut stack: Vec<u32> = states.iter().copied().collect();
 
-    while let Some(&state_id) = stack.iter().next() {
-        stack.remove(&state_id);
+    while let Some(state_id) = stack.pop() {
         if let Some(epsilon_states) = nfa.transitions.get(&(state_id, None)) {
             for &next_state in epsilon_states {
                 if states.insert(next_state) {
-                    stack.insert(next_state);
+                    stack.push(next_state);
                 }
             }
         }
@@ -311,7 +358,7 @@ fn hash_set_to_sorted_vec(set: &HashSet<u32>) -> Vec<u32> {
     vec
 }
 
-fn nfa_to_dfa(nfa: &Nfa) -> ThompsonDfa {
+fn nfa_to_dfa(nfa: &Nfa, size_limit: Option<usize>) -> Result<ThompsonDfa, RegexError> {
     // Start from the initial state of the NFA, assuming it's state 0
     let mut start_closure = HashSet::from([0]);
     epsilon_closure(nfa, &mut start_closure);
@@ -324,6 +371,13 @@ fn nfa_to_dfa(nfa: &Nfa) -> ThompsonDfa {
 
     let mut unmarked_states = vec![start_closure];
 
+    // Symbols are visited in order, so the DFA states are numbered the same on every run
+    let symbols: BTreeSet<char> = nfa
+        .transitions
+        .keys()
+        .filter_map(|(_, symbol)| *symbol)
+        .collect();
+
     while let Some(current_closure) = unmarked_states.pop() {
         let current_dfa_state_id = state_map[&hash_set_to_sorted_vec(&current_closure)];
 
@@ -331,14 +385,7 @@ fn nfa_to_dfa(nfa: &Nfa) -> ThompsonDfa {
             dfa_accepting_states.insert(current_dfa_state_id);
         }
 
-        // Collect symbols from transitions
-        let symbols: HashSet<_> = nfa
-            .transitions
-            .keys()
-            .filter_map(|(_, symbol)| *symbol)
-            .collect();
-
-        for symbol in symbols {
+        for &symbol in &symbols {
             let mut move_closure = move_nfa(nfa, &current_closure, symbol);
             epsilon_closure(nfa, &mut move_closure);
 
@@ -356,13 +403,15 @@ fn nfa_to_dfa(nfa: &Nfa) -> ThompsonDfa {
             }
 
             transitions.insert((current_dfa_state_id, symbol), state_map[&sorted_vec]);
+            check_size_limit(transitions.len(), state_map.len(), size_limit)?;
         }
     }
 
-    ThompsonDfa {
+    Ok(ThompsonDfa {
         transitions,
         accepting_states: dfa_accepting_states,
-    }
+        alphabet: Alphabet::default(),
+    })
 }
 // END NFA to DFA functions ---
 
@@ -372,14 +421,14 @@ mod tests {
 
     #[test]
     fn create_dfa_test() {
-        let generated_dfa = ThompsonDfa::new("(a|b)*").expect("Valid dfa");
+        let generated_dfa = ThompsonDfa::new("(a|b)*", None).expect("Valid dfa");
         let expected_transitions = HashMap::from([((0, 'a'), 0), ((0, 'b'), 0)]);
         let expected_accepting_states = HashSet::from([0]);
 
         assert_eq!(expected_transitions, generated_dfa.transitions);
         assert_eq!(expected_accepting_states, generated_dfa.accepting_states);
 
-        let generated_dfa_2 = ThompsonDfa::new("a|()").expect("Valid dfa");
+        let generated_dfa_2 = ThompsonDfa::new("a|()", None).expect("Valid dfa");
         let expected_transitions_2 = HashMap::from([((0, 'a'), 1)]);
         let expected_accepting_states_2 = HashSet::from([0, 1]);
 
@@ -389,7 +438,7 @@ mod tests {
             generated_dfa_2.accepting_states
         );
 
-        let generated_dfa = ThompsonDfa::new("a*b").expect("Valid dfa");
+        let generated_dfa = ThompsonDfa::new("a*b", None).expect("Valid dfa");
         let expected_transitions = HashMap::from([((0, 'a'), 0), ((0, 'b'), 1)]);
         let expected_accepting_states = HashSet::from([1]);
 
@@ -399,7 +448,7 @@ mod tests {
 
     #[test]
     fn prozess_regex_test() {
-        let generated_dfa = ThompsonDfa::new("(a|b)*").expect("Valid dfa");
+        let generated_dfa = ThompsonDfa::new("(a|b)*", None).expect("Valid dfa");
         let test_strings = vec!["abbbababaaaa", ""];
         for string in test_strings {
             assert!(generated_dfa.process(string));
@@ -469,7 +518,7 @@ mod tests {
 
     #[test]
     fn thompson_construction_test() {
-        let regex_nfa = thompson_construction("(a|b)*");
+        let regex_nfa = thompson_construction("(a|b)*", &Alphabet::default()).expect("Valid regex");
 
         let expected_transitions = HashMap::from([
             ((0, None), vec![1, 7]),
@@ -486,6 +535,61 @@ mod tests {
         assert_eq!(regex_nfa.accepting_state, expected_accepting_state);
     }
 
+    #[test]
+    fn empty_alternation_group_test() {
+        let regex_nfa = thompson_construction("(|)", &Alphabet::default()).expect("Valid regex");
+        // Both operands of the union are epsilon automata
+        assert!(
+            regex_nfa
+                .transitions
+                .keys()
+                .all(|(_, symbol)| symbol.is_none())
+        );
+
+        let generated_dfa = ThompsonDfa::new("(|)", None).expect("Valid dfa");
+        assert!(generated_dfa.transitions.is_empty());
+        assert_eq!(generated_dfa.accepting_states, HashSet::from([0]));
+        assert!(generated_dfa.process(""));
+        assert!(!generated_dfa.process("a"));
+    }
+
+    #[test]
+    fn thompson_construction_underflow_test() {
+        for pattern in ["*", "(*)", "a(*)", "a|*", "a(", "(a|b"] {
+            assert!(
+                thompson_construction(pattern, &Alphabet::default()).is_err(),
+                "{pattern} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn epsilon_closure_test() {
+        // State 0 fans out to every other state, which are also chained and close a cycle back
+        // to 0. The closure has to hold each of them exactly once and must not grow past them.
+        let state_count: u32 = 100_000;
+        let mut transitions: HashMap<(u32, Option<char>), Vec<u32>> =
+            HashMap::from([((0, None), (1..state_count).collect())]);
+        for state in 1..state_count {
+            transitions.insert((state, None), vec![(state + 1) % state_count]);
+        }
+        transitions.insert((state_count, Some('a')), vec![state_count + 1]);
+        let nfa = Nfa {
+            transitions,
+            accepting_state: state_count + 1,
+        };
+
+        for start_state in [0, state_count - 1] {
+            let mut states = HashSet::from([start_state]);
+            epsilon_closure(&nfa, &mut states);
+            assert_eq!(states, (0..state_count).collect());
+        }
+
+        let mut states = HashSet::from([state_count]);
+        epsilon_closure(&nfa, &mut states);
+        assert_eq!(states, HashSet::from([state_count]));
+    }
+
     #[test]
     fn nfa_to_dfa_test() {
         let input_nfa = Nfa {
@@ -501,32 +605,38 @@ mod tests {
             accepting_state: 7,
         };
 
-        let generated_dfa = nfa_to_dfa(&input_nfa);
+        let generated_dfa = nfa_to_dfa(&input_nfa, None).expect("No size limit");
 
-        let expected_options = [
-            HashMap::from([
-                ((0, 'a'), 1),
-                ((0, 'b'), 2),
-                ((1, 'a'), 1),
-                ((1, 'b'), 2),
-                ((2, 'a'), 1),
-                ((2, 'b'), 2),
-            ]),
-            HashMap::from([
-                ((0, 'a'), 2),
-                ((0, 'b'), 1),
-                ((1, 'a'), 2),
-                ((1, 'b'), 1),
-                ((2, 'a'), 2),
-                ((2, 'b'), 1),
-            ]),
-        ];
+        let expected_transitions = HashMap::from([
+            ((0, 'a'), 1),
+            ((0, 'b'), 2),
+            ((1, 'a'), 1),
+            ((1, 'b'), 2),
+            ((2, 'a'), 1),
+            ((2, 'b'), 2),
+        ]);
         let expected_accepting_states = HashSet::from([0, 1, 2]);
 
-        assert!(
-            expected_options.contains(&generated_dfa.transitions),
-            "Transitions did not match any of the expected options."
-        );
+        assert_eq!(expected_transitions, generated_dfa.transitions);
         assert_eq!(expected_accepting_states, generated_dfa.accepting_states);
     }
+
+    #[test]
+    fn deterministic_nfa_to_dfa_test() {
+        let compile = |regex: &str| {
+            let normalised_regex = normalise_regex(regex);
+            let alphabet =
+                Alphabet::new(&pattern_classes(&normalised_regex).expect("Valid classes"));
+            let nfa = thompson_construction(&normalised_regex, &alphabet).expect("Valid nfa");
+            nfa_to_dfa(&nfa, None).expect("No size limit")
+        };
+        for regex in ["(a|b|c)*(ab|cd)e", "[a-f]x|[d-k]y|z*"] {
+            let first = compile(regex);
+            for _ in 0..20 {
+                let dfa = compile(regex);
+                assert_eq!(dfa.transitions, first.transitions, "Failed for '{regex}'");
+                assert_eq!(dfa.accepting_states, first.accepting_states);
+            }
+        }
+    }
 }
@@ -13,5 +13,12 @@ fn test_all_bench_cases() {
             case.expected_first_match.as_deref()
         );
         assert_eq!(regex.findall(&case.input), case.expected_all_matches);
+        assert_eq!(
+            regex
+                .find_iter(&case.input)
+                .map(|m| m.as_str())
+                .collect::<Vec<_>>(),
+            case.expected_all_matches
+        );
     }
 }
@@ -0,0 +1,27 @@
+include!("../benches/bench_cases.rs");
+use regex_engine::{ConstructionType, RegexCache};
+
+#[test]
+fn test_bench_cases_warm_cache() {
+    let cases = get_bench_cases();
+
+    for construction in [ConstructionType::Thompson, ConstructionType::Glushkov] {
+        let mut cache = RegexCache::new(construction);
+        let cold_set = compile_bench_cases(&mut cache);
+        assert_eq!(cold_set.len(), cases.len());
+        assert_eq!(cache.hits() + cache.misses(), cases.len());
+        assert_eq!(cache.misses(), cache.len());
+
+        let hits_before = cache.hits();
+        let warm_set = compile_bench_cases(&mut cache);
+        assert_eq!(cache.hits() - hits_before, cases.len());
+        assert_eq!(cache.misses(), cache.len());
+
+        for case in &cases {
+            assert_eq!(
+                cold_set.is_match(&case.input),
+                warm_set.is_match(&case.input)
+            );
+        }
+    }
+}
@@ -0,0 +1,38 @@
+include!("../benches/bench_cases.rs");
+use regex_engine::{ConstructionType, Regex};
+
+// Number of states and transitions of the minimised DFA
+fn dfa_size(regex: &Regex) -> (usize, usize) {
+    let histogram = regex.out_degree_histogram();
+    let states = histogram.values().sum();
+    let transitions = histogram
+        .iter()
+        .map(|(out_degree, count)| out_degree * count)
+        .sum();
+    (states, transitions)
+}
+
+#[test]
+fn test_minimised_state_counts() {
+    for case in get_bench_cases() {
+        let thompson = Regex::new(case.regex, ConstructionType::Thompson).expect("Valid regex");
+        let glushkov = Regex::new(case.regex, ConstructionType::Glushkov).expect("Valid regex");
+        let brzozowski = Regex::new(case.regex, ConstructionType::Brzozowski).expect("Valid regex");
+
+        // All constructions minimise to the same DFA
+        assert_eq!(
+            dfa_size(&thompson),
+            dfa_size(&glushkov),
+            "Failed for pattern: {}",
+            case.regex
+        );
+        assert_eq!(
+            dfa_size(&thompson),
+            dfa_size(&brzozowski),
+            "Failed for pattern: {}",
+            case.regex
+        );
+        assert_eq!(thompson.canonical_key(), glushkov.canonical_key());
+        assert_eq!(thompson.canonical_key(), brzozowski.canonical_key());
+    }
+}